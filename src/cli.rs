@@ -0,0 +1,502 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use bevy::asset::AssetPlugin;
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::core_pipeline::core_3d::{Camera3dDepthLoadOp, Camera3dDepthTextureUsage};
+use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::ecs::event::ManualEventReader;
+use bevy::gltf::GltfPlugin;
+use bevy::pbr::{
+    CascadeShadowConfig, Cascades, CascadesVisibleEntities, ClusterConfig, ClusterFarZMode,
+    ClusterZConfig, CubemapVisibleEntities, NotShadowCaster, NotShadowReceiver,
+    ParallaxMappingMethod,
+};
+use bevy::prelude::*;
+use bevy::render::camera::{CameraRenderGraph, RenderTarget, ScalingMode, Viewport};
+use bevy::render::mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes};
+use bevy::render::primitives::{Aabb, CascadesFrusta, CubemapFrusta, Frustum};
+use bevy::render::view::{ColorGrading, NoFrustumCulling, RenderLayers, VisibleEntities};
+use bevy::scene::ScenePlugin;
+use uuid::Uuid;
+
+use crate::project::migration::migrate_project_manifest;
+use crate::project::scene_diff::diff_scene_file;
+use crate::project::scene_merge::merge_scene_files;
+use crate::project::{
+    root_scenes, unused_project_items, validate_project, ProjectError, ProjectEvent, ProjectItem,
+    ProjectItemRegistry, ProjectPlugin, ProjectSearch, SourceLoading,
+};
+
+const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+const SEARCH_LIMIT: usize = 50;
+
+const USAGE: &str = "\
+Usage:
+    makeshift validate <project>
+    makeshift ls <project>
+    makeshift find <project> <query>
+    makeshift export-scene <project> <scene uuid> <path>
+    makeshift diff-scene <project> <scene uuid> <path>
+    makeshift migrate <project> [--dry-run]
+    makeshift unused <project> [<root scene uuid>...]
+    makeshift merge-scene <base> <ours> <theirs> <output>
+
+Sources are read from the `assets` directory next to <project>.
+Without root scenes, `unused` starts from the scenes tagged \"root\".
+`merge-scene` writes the merged scene to <output>, which can be <ours> for a git merge driver.";
+
+enum Command {
+    Validate,
+    List,
+    Find { query: String },
+    ExportScene { scene_uuid: Uuid, path: PathBuf },
+    DiffScene { scene_uuid: Uuid, path: PathBuf },
+    Migrate { dry_run: bool },
+    Unused { root_scenes: Vec<Uuid> },
+}
+
+fn parse_args(args: &[String]) -> Result<(Command, PathBuf), String> {
+    match args {
+        [command, project] if command == "validate" => {
+            Ok((Command::Validate, PathBuf::from(project)))
+        }
+        [command, project] if command == "ls" => Ok((Command::List, PathBuf::from(project))),
+        [command, project, query @ ..] if command == "find" && !query.is_empty() => Ok((
+            Command::Find {
+                query: query.join(" "),
+            },
+            PathBuf::from(project),
+        )),
+        [command, project, scene_uuid, path] if command == "export-scene" => {
+            let scene_uuid = Uuid::parse_str(scene_uuid)
+                .map_err(|err| format!("invalid scene uuid {}: {}", scene_uuid, err))?;
+            Ok((
+                Command::ExportScene {
+                    scene_uuid,
+                    path: PathBuf::from(path),
+                },
+                PathBuf::from(project),
+            ))
+        }
+        [command, project, scene_uuid, path] if command == "diff-scene" => {
+            let scene_uuid = Uuid::parse_str(scene_uuid)
+                .map_err(|err| format!("invalid scene uuid {}: {}", scene_uuid, err))?;
+            Ok((
+                Command::DiffScene {
+                    scene_uuid,
+                    path: PathBuf::from(path),
+                },
+                PathBuf::from(project),
+            ))
+        }
+        [command, project] if command == "migrate" => {
+            Ok((Command::Migrate { dry_run: false }, PathBuf::from(project)))
+        }
+        [command, project, flag] if command == "migrate" && flag == "--dry-run" => {
+            Ok((Command::Migrate { dry_run: true }, PathBuf::from(project)))
+        }
+        [command, project, root_scenes @ ..] if command == "unused" => {
+            let root_scenes = root_scenes
+                .iter()
+                .map(|uuid| {
+                    Uuid::parse_str(uuid)
+                        .map_err(|err| format!("invalid scene uuid {}: {}", uuid, err))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((Command::Unused { root_scenes }, PathBuf::from(project)))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Registers the types of the components that the editor stores in scenes, which the rendering
+/// plugins would register otherwise.
+fn register_scene_types(app: &mut App) {
+    app.register_type::<Visibility>()
+        .register_type::<ComputedVisibility>()
+        .register_type::<VisibleEntities>()
+        .register_type::<NoFrustumCulling>()
+        .register_type::<RenderLayers>()
+        .register_type::<Aabb>()
+        .register_type::<Frustum>()
+        .register_type::<CubemapFrusta>()
+        .register_type::<CascadesFrusta>()
+        .register_type::<Color>()
+        .register_type::<SkinnedMesh>()
+        .register_type::<Vec<Entity>>()
+        .register_type::<Camera>()
+        .register_type::<Viewport>()
+        .register_type::<Option<Viewport>>()
+        .register_type::<RenderTarget>()
+        .register_type::<CameraRenderGraph>()
+        .register_type::<Projection>()
+        .register_type::<PerspectiveProjection>()
+        .register_type::<OrthographicProjection>()
+        .register_type::<ScalingMode>()
+        .register_type::<Camera3d>()
+        .register_type::<Camera3dDepthLoadOp>()
+        .register_type::<Camera3dDepthTextureUsage>()
+        .register_type::<ClearColorConfig>()
+        .register_type::<Tonemapping>()
+        .register_type::<DebandDither>()
+        .register_type::<ColorGrading>()
+        .register_type::<PointLight>()
+        .register_type::<SpotLight>()
+        .register_type::<DirectionalLight>()
+        .register_type::<CascadeShadowConfig>()
+        .register_type::<Vec<f32>>()
+        .register_type::<Cascades>()
+        .register_type::<CascadesVisibleEntities>()
+        .register_type::<CubemapVisibleEntities>()
+        .register_type::<ClusterConfig>()
+        .register_type::<ClusterFarZMode>()
+        .register_type::<ClusterZConfig>()
+        .register_type::<NotShadowCaster>()
+        .register_type::<NotShadowReceiver>()
+        .register_type::<AlphaMode>()
+        .register_type::<ParallaxMappingMethod>();
+}
+
+/// Builds an app that can load projects without opening a window or creating a renderer.
+///
+/// Only the assets that project items are made of are added, with the glTF loader for mesh
+/// sources, and the types of the components in scenes are registered.
+fn headless_app(asset_plugin: AssetPlugin) -> App {
+    let mut app = App::new();
+
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(asset_plugin)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(ImagePlugin::default())
+        .add_plugin(ScenePlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<SkinnedMeshInverseBindposes>()
+        .add_asset::<StandardMaterial>()
+        .register_asset_reflect::<StandardMaterial>()
+        // Loaded along with meshes from glTF files
+        .add_asset::<AnimationClip>()
+        .add_plugin(GltfPlugin::default());
+
+    register_scene_types(&mut app);
+
+    app.add_plugin(ProjectPlugin::default());
+
+    app.finish();
+    app.cleanup();
+
+    app
+}
+
+/// Sends `event` and returns the errors it caused.
+fn apply_event(
+    app: &mut App,
+    errors: &mut ManualEventReader<ProjectError>,
+    event: ProjectEvent,
+) -> Vec<String> {
+    app.world.send_event(event);
+    app.update();
+
+    errors
+        .iter(app.world.resource::<Events<ProjectError>>())
+        .map(|project_error| project_error.kind.to_string())
+        .collect()
+}
+
+fn wait_for_sources(app: &mut App) -> Result<(), String> {
+    let started = Instant::now();
+
+    loop {
+        app.update();
+
+        let loading = app
+            .world
+            .query_filtered::<(), With<SourceLoading>>()
+            .iter(&app.world)
+            .count();
+
+        if loading == 0 {
+            return Ok(());
+        }
+
+        if started.elapsed() > LOAD_TIMEOUT {
+            return Err(format!("timed out waiting for {} sources to load", loading));
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn print_item_tree(world: &World, entities: &[Entity], depth: usize) {
+    let mut items: Vec<(&ProjectItem, Entity)> = entities
+        .iter()
+        .filter_map(|entity| Some((world.get::<ProjectItem>(*entity)?, *entity)))
+        .collect();
+    items.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+    for (project_item, entity) in items {
+        println!(
+            "{}{} [{}] {}",
+            "  ".repeat(depth),
+            project_item.name,
+            project_item.kind.name(),
+            project_item.uuid
+        );
+
+        if let Some(children) = world.get::<Children>(entity) {
+            print_item_tree(world, children, depth + 1);
+        }
+    }
+}
+
+fn list_project(world: &World) {
+    let roots: Vec<Entity> = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .values()
+        .copied()
+        .filter(|entity| world.get::<Parent>(*entity).is_none())
+        .collect();
+
+    print_item_tree(world, &roots, 0);
+}
+
+fn find_items(world: &World, query: &str) {
+    let registry = world.resource::<ProjectItemRegistry>();
+
+    for uuid in world
+        .resource::<ProjectSearch>()
+        .search(query, SEARCH_LIMIT)
+    {
+        let Some(project_item) = registry
+            .items
+            .get(&uuid)
+            .and_then(|entity| world.get::<ProjectItem>(*entity))
+        else {
+            continue;
+        };
+
+        println!(
+            "{} [{}] {}",
+            project_item.name,
+            project_item.kind.name(),
+            project_item.uuid
+        );
+    }
+}
+
+fn list_unused_items(world: &mut World, mut roots: Vec<Uuid>) -> Result<(), Vec<String>> {
+    if roots.is_empty() {
+        roots = root_scenes(world);
+    }
+    if roots.is_empty() {
+        return Err(vec!["no root scenes given or tagged \"root\"".to_string()]);
+    }
+
+    let unused = unused_project_items(world, &roots).map_err(|err| vec![err.to_string()])?;
+
+    let registry = world.resource::<ProjectItemRegistry>();
+    for uuid in unused {
+        let project_item = world.get::<ProjectItem>(registry.items[&uuid]).unwrap();
+
+        println!(
+            "{} [{}] {}",
+            project_item.name,
+            project_item.kind.name(),
+            project_item.uuid
+        );
+    }
+
+    Ok(())
+}
+
+fn print_scene_diff(world: &mut World, scene_uuid: Uuid, path: &Path) -> Result<(), Vec<String>> {
+    let scene_diff =
+        diff_scene_file(world, scene_uuid, path).map_err(|err| vec![err.to_string()])?;
+
+    if scene_diff.is_empty() {
+        println!("no differences");
+        return Ok(());
+    }
+
+    for entity in &scene_diff.added {
+        println!("+ {:?}", entity);
+    }
+    for entity in &scene_diff.removed {
+        println!("- {:?}", entity);
+    }
+    for entity_diff in &scene_diff.changed {
+        println!("~ {:?}", entity_diff.entity);
+        for component in &entity_diff.added_components {
+            println!("    + {}", component);
+        }
+        for component in &entity_diff.removed_components {
+            println!("    - {}", component);
+        }
+        for field in &entity_diff.changed_fields {
+            println!("    ~ {}", field);
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_project(world: &World, project: &Path, dry_run: bool) -> Result<(), Vec<String>> {
+    let report =
+        migrate_project_manifest(world, project, dry_run).map_err(|err| vec![err.to_string()])?;
+
+    let Some(report) = report else {
+        println!("{} is up to date", project.display());
+        return Ok(());
+    };
+
+    println!(
+        "{} {} from version {} to {}",
+        if dry_run { "would upgrade" } else { "upgraded" },
+        project.display(),
+        report.from_version,
+        report.to_version
+    );
+    for change in &report.changes {
+        println!("  {}", change);
+    }
+    if let Some(backup) = &report.backup {
+        println!("original kept in {}", backup.display());
+    }
+
+    Ok(())
+}
+
+/// Sources are relative to the `assets` directory next to the project manifest.
+fn project_asset_plugin(project: &Path) -> AssetPlugin {
+    let project = std::env::current_dir()
+        .map(|current_dir| current_dir.join(project))
+        .unwrap_or_else(|_| project.to_path_buf());
+    let asset_folder = project
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join("assets");
+
+    AssetPlugin {
+        asset_folder: asset_folder.to_string_lossy().into_owned(),
+        ..default()
+    }
+}
+
+fn run_command(command: Command, project: &Path) -> Result<(), Vec<String>> {
+    let mut app = headless_app(project_asset_plugin(project));
+
+    if let Command::Migrate { dry_run } = command {
+        // Loading would only upgrade the project in memory, so the manifest is migrated on its own
+        return migrate_project(&app.world, project, dry_run);
+    }
+
+    let mut errors = app.world.resource::<Events<ProjectError>>().get_reader();
+
+    let load_errors = apply_event(
+        &mut app,
+        &mut errors,
+        ProjectEvent::LoadProject {
+            path: project.to_path_buf(),
+        },
+    );
+    if !load_errors.is_empty() {
+        return Err(load_errors);
+    }
+
+    wait_for_sources(&mut app).map_err(|err| vec![err])?;
+
+    match command {
+        Command::Validate => {
+            let issues: Vec<String> = validate_project(&app.world)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+
+            if !issues.is_empty() {
+                return Err(issues);
+            }
+            println!("{} is valid", project.display());
+        }
+        Command::List => list_project(&app.world),
+        Command::Find { query } => find_items(&app.world, &query),
+        Command::ExportScene { scene_uuid, path } => {
+            let export_errors = apply_event(
+                &mut app,
+                &mut errors,
+                ProjectEvent::ExportScene { scene_uuid, path },
+            );
+            if !export_errors.is_empty() {
+                return Err(export_errors);
+            }
+        }
+        Command::DiffScene { scene_uuid, path } => {
+            print_scene_diff(&mut app.world, scene_uuid, &path)?
+        }
+        Command::Unused { root_scenes } => list_unused_items(&mut app.world, root_scenes)?,
+        Command::Migrate { .. } => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Merges scene files into `output`, failing when conflicts are left to resolve.
+fn merge_scenes(base: &Path, ours: &Path, theirs: &Path, output: &Path) -> Result<(), Vec<String>> {
+    let app = headless_app(AssetPlugin::default());
+
+    let scene_merge =
+        merge_scene_files(base, ours, theirs, app.world.resource::<AppTypeRegistry>())
+            .map_err(|err| vec![err.to_string()])?;
+
+    fs::write(output, &scene_merge.output)
+        .map_err(|err| vec![format!("failed to write {}: {}", output.display(), err)])?;
+
+    if !scene_merge.conflicts.is_empty() {
+        return Err(scene_merge
+            .conflicts
+            .iter()
+            .map(|conflict| format!("conflict in {}", conflict))
+            .collect());
+    }
+
+    Ok(())
+}
+
+fn exit_code(result: Result<(), Vec<String>>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("error: {}", error);
+            }
+            1
+        }
+    }
+}
+
+/// Runs the command line `args` (without the program name) and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    // Merging works on scene files alone, without a project
+    if let [command, base, ours, theirs, output] = args {
+        if command == "merge-scene" {
+            return exit_code(merge_scenes(
+                Path::new(base),
+                Path::new(ours),
+                Path::new(theirs),
+                Path::new(output),
+            ));
+        }
+    }
+
+    let (command, project) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 2;
+        }
+    };
+
+    exit_code(run_command(command, &project))
+}
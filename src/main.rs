@@ -5,10 +5,12 @@ use bevy_mod_picking::prelude::*;
 use editor::{EditorItem, EditorPlugin};
 use icon::Icon;
 use nine_slice::{NineSlice, NineSliceBundle, NineSlicePlugin};
-use project::{ProjectEvent, ProjectItem, ProjectPlugin};
-use tree_view::{TreeView, TreeViewBundle, TreeViewItem, TreeViewPlugin};
+use project::recovery::RecoverySnapshot;
+use project::{ProjectEvent, ProjectItem, ProjectPlugin, Thumbnail};
+use tree_view::{TreeView, TreeViewBundle, TreeViewItem, TreeViewItemImage, TreeViewPlugin};
 use uuid::Uuid;
 
+mod cli;
 mod editor;
 mod icon;
 mod nine_slice;
@@ -16,6 +18,11 @@ mod project;
 mod tree_view;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -25,7 +32,10 @@ fn main() {
             ..default()
         }))
         .add_plugins(DefaultPickingPlugins)
-        .add_plugin(ProjectPlugin)
+        .add_plugin(ProjectPlugin {
+            autosave: Some("recovery".into()),
+            ..default()
+        })
         .add_plugin(EditorPlugin)
         .add_plugin(TreeViewPlugin::<ProjectItem>::default())
         .add_plugin(TreeViewPlugin::<EditorItem>::default())
@@ -34,13 +44,18 @@ fn main() {
             Startup,
             (create_tree_view, create_sample_items, create_3d_scene),
         )
+        .add_systems(
+            Update,
+            (
+                show_thumbnails,
+                show_recovery_prompt,
+                handle_recovery_prompt,
+            ),
+        )
         .run()
 }
 
-fn create_sample_items(
-    mut project_events: EventWriter<ProjectEvent>,
-    asset_server: Res<AssetServer>,
-) {
+fn create_sample_items(mut project_events: EventWriter<ProjectEvent>) {
     project_events.send(ProjectEvent::CreateMaterial {
         uuid: Uuid::new_v4(),
         name: "Player Material".into(),
@@ -93,34 +108,197 @@ fn create_sample_items(
         parent_uuid: Some(other_folder_uuid),
     });
 
-    project_events.send(ProjectEvent::CreateMesh {
+    project_events.send(ProjectEvent::ImportMesh {
         uuid: Uuid::new_v4(),
         name: "Player Model".into(),
-        handle: asset_server.load("SomeModel.gltf"),
         parent_uuid: None,
+        source: "SomeModel.gltf#Mesh0/Primitive0".into(),
     });
 
-    project_events.send(ProjectEvent::CreateImage {
+    project_events.send(ProjectEvent::ImportImage {
         uuid: Uuid::new_v4(),
         name: "Player Texture".into(),
-        handle: asset_server.load("SomeImage.png"),
         parent_uuid: None,
+        source: "SomeImage.png".into(),
     });
 }
 
+fn show_thumbnails(
+    mut commands: Commands,
+    thumbnails: Query<(Entity, &Thumbnail), Changed<Thumbnail>>,
+) {
+    for (entity, thumbnail) in &thumbnails {
+        commands
+            .entity(entity)
+            .insert(TreeViewItemImage(thumbnail.0.clone()));
+    }
+}
+
+#[derive(Component)]
+struct RecoveryPrompt;
+
+#[derive(Component, Clone, Copy)]
+enum RecoveryPromptButton {
+    Restore,
+    Discard,
+}
+
+/// Offers to restore the unsaved work of a previous session for as long as it's pending.
+fn show_recovery_prompt(
+    mut commands: Commands,
+    recovery_snapshot: Option<Res<RecoverySnapshot>>,
+    prompts: Query<Entity, With<RecoveryPrompt>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(recovery_snapshot) = recovery_snapshot else {
+        for prompt in &prompts {
+            commands.entity(prompt).despawn_recursive();
+        }
+        return;
+    };
+
+    if !prompts.is_empty() {
+        return;
+    }
+
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Regular.ttf"),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+
+    let message = match &recovery_snapshot.project_path {
+        Some(project_path) => format!(
+            "Unsaved work on {} from a previous session was found.",
+            project_path.display()
+        ),
+        None => "Unsaved work from a previous session was found.".to_string(),
+    };
+
+    let slice = UiRect::all(Val::Px(8.0));
+
+    commands
+        .spawn((
+            RecoveryPrompt,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    bottom: Val::Px(0.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children
+                .spawn(NineSliceBundle {
+                    nine_slice: NineSlice {
+                        image: asset_server.load("nine_slices/Panel@2x.png"),
+                        slice,
+                        width: Val::Px(32.0),
+                        height: Val::Px(32.0),
+                        ..default()
+                    },
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::End,
+                        padding: UiRect::all(Val::Px(12.0)),
+                        row_gap: Val::Px(12.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|children| {
+                    children.spawn(TextBundle::from_section(message, text_style.clone()));
+
+                    children
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|children| {
+                            for (button, image, title) in [
+                                (
+                                    RecoveryPromptButton::Discard,
+                                    "nine_slices/Button@2x.png",
+                                    "Discard",
+                                ),
+                                (
+                                    RecoveryPromptButton::Restore,
+                                    "nine_slices/Button.Primary@2x.png",
+                                    "Restore",
+                                ),
+                            ] {
+                                children
+                                    .spawn((
+                                        button,
+                                        Interaction::None,
+                                        NineSliceBundle {
+                                            nine_slice: NineSlice {
+                                                image: asset_server.load(image),
+                                                slice,
+                                                width: Val::Px(32.0),
+                                                height: Val::Px(32.0),
+                                                ..default()
+                                            },
+                                            style: Style {
+                                                height: Val::Px(24.0),
+                                                align_items: AlignItems::Center,
+                                                padding: UiRect::horizontal(Val::Px(8.0)),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                    ))
+                                    .with_children(|children| {
+                                        children.spawn(TextBundle::from_section(
+                                            title,
+                                            text_style.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+/// Restores or discards the pending work once the prompt is answered, which removes the prompt.
+fn handle_recovery_prompt(
+    buttons: Query<(&Interaction, &RecoveryPromptButton), Changed<Interaction>>,
+    mut project_events: EventWriter<ProjectEvent>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        project_events.send(match button {
+            RecoveryPromptButton::Restore => ProjectEvent::RestoreRecovery,
+            RecoveryPromptButton::Discard => ProjectEvent::DiscardRecovery,
+        });
+    }
+}
+
 impl TreeViewItem for ProjectItem {
     fn title(&self) -> String {
-        self.name.clone()
+        if self.has_unsaved_changes {
+            format!("{} *", self.name)
+        } else {
+            self.name.clone()
+        }
     }
 
     fn icon(&self) -> Icon {
-        match self.data {
-            project::ProjectItemData::Folder => Icon::named("Folder"),
-            project::ProjectItemData::Material { .. } => Icon::named("Material"),
-            project::ProjectItemData::Image { .. } => Icon::named("Image"),
-            project::ProjectItemData::Mesh { .. } => Icon::named("Mesh"),
-            project::ProjectItemData::Scene { .. } => Icon::named("Scene"),
-        }
+        self.kind.icon()
     }
 
     fn is_selected(&self) -> bool {
@@ -130,6 +308,10 @@ impl TreeViewItem for ProjectItem {
     fn is_hovered(&self) -> bool {
         false
     }
+
+    fn has_error(&self) -> bool {
+        self.load_error.is_some()
+    }
 }
 
 impl TreeViewItem for EditorItem {
@@ -1,50 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+use bevy::asset::{Asset, AssetPath, FileAssetIo, HandleId};
 use bevy::ecs::entity::EntityMap;
 use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
-use bevy::reflect::{ParsedPath, ReflectOwned};
-use bevy::utils::HashMap;
+use bevy::reflect::{ParsedPath, ReflectMut, ReflectOwned};
+use bevy::render::renderer::RenderDevice;
+use bevy::utils::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::editor::EditorItem;
 
+pub use self::dependencies::{find_usages, ProjectDependencies};
+pub use self::error::{ProjectError, ProjectErrorKind};
+pub use self::garbage::{root_scenes, unused_project_items};
+pub use self::kind::{
+    asset_item_handle, ProjectItemKind, ProjectItemKinds, RegisterProjectItemKind,
+    SerializedProjectItem,
+};
+pub use self::library::{LibraryItem, ProjectLibrary};
+pub use self::search::ProjectSearch;
+pub use self::source::SourceLoading;
+pub use self::thumbnails::Thumbnail;
+pub use self::validate::validate_project;
+
+use self::asset_sync::{sync_project_assets, AssetSync};
+use self::clipboard::{
+    copy_project_items, finish_clipboard_tasks, start_clipboard_copy, start_clipboard_paste,
+    ClipboardTasks,
+};
+use self::dependencies::{scene_references, update_material_dependencies};
+use self::editor_id::{assign_editor_ids, use_editor_ids, EditorId};
+use self::import_settings::{source_import_settings, ImportSettings};
+use self::kind::{
+    clone_dynamic_scene, FolderKind, ImageKind, MaterialKind, MeshKind, PrefabKind, SceneKind,
+};
+use self::library::{mount_library, unmount_library, writable_item_entity};
+use self::manifest::{ProjectManifest, ProjectManifestItem, ProjectManifestLibrary};
+use self::meta::AssetMeta;
+use self::migration::{read_project_manifest, ManifestMigrations};
+use self::overrides::{apply_asset_overrides, OverridesChanged, SourceAsset};
+use self::prefab::{
+    assign_prefab_entity_ids, create_prefab, instantiate_prefab, is_prefab,
+    propagate_prefab_changes, PrefabEntity, PrefabInstance,
+};
+use self::recovery::{
+    autosave, detect_recovery_snapshot, discard_recovery_snapshot, remove_snapshot_on_exit,
+    restore_recovery_snapshot, track_saved_project, Autosave,
+};
+use self::scene_diff::{diff_scene, update_unsaved_changes, UnsavedChanges};
+use self::scene_file::{
+    extract_stored_scene, insert_computed_components, load_scene_file, save_scene_file,
+};
+use self::search::update_project_search;
+use self::source::update_source_load_states;
+use self::thumbnails::{despawn_thumbnail_stages, queue_thumbnails, render_thumbnails, Thumbnails};
+
+mod asset_sync;
+pub mod clipboard;
+mod dependencies;
+pub mod editor_id;
+mod error;
+mod garbage;
+pub mod import_settings;
+mod kind;
+mod library;
+mod manifest;
+mod meta;
+pub mod migration;
+mod overrides;
+pub mod prefab;
+pub mod recovery;
+pub mod ron_value;
+pub mod scene_diff;
+mod scene_file;
+pub mod scene_merge;
+mod search;
+mod source;
+mod thumbnails;
+mod validate;
+
 #[derive(Resource, Default)]
 pub struct ProjectItemRegistry {
     pub items: HashMap<Uuid, Entity>,
+    /// Read-only libraries mounted into the project, by the uuid of their top-level folder.
+    pub libraries: HashMap<Uuid, ProjectLibrary>,
+}
+
+#[derive(Resource, Default)]
+pub struct OpenScene {
+    pub scene_uuid: Option<Uuid>,
+    /// Live entity spawned from each entity of the stored scene.
+    stored_entities: EntityMap,
 }
 
 #[derive(Component)]
 pub struct ProjectItem {
     pub uuid: Uuid,
     pub name: String,
+    pub kind: Arc<dyn ProjectItemKind>,
     pub data: ProjectItemData,
+    pub tags: BTreeSet<String>,
+    /// Free-form key/value pairs, such as an owner, a status or notes.
+    pub metadata: BTreeMap<String, String>,
+    /// Set on the open scene while its live entities differ from its stored version.
+    pub has_unsaved_changes: bool,
+    pub load_error: Option<String>,
 }
 
 pub enum ProjectItemData {
     Folder,
-    Material {
-        source: Option<String>,
-        handle: Handle<StandardMaterial>,
-        overrides: HashMap<ParsedPath, ReflectOwned>,
-    },
-    Image {
-        source: Option<String>,
-        handle: Handle<Image>,
-        overrides: HashMap<ParsedPath, ReflectOwned>,
-    },
-    Mesh {
+    /// An asset, such as a material, image or mesh, whose type is up to the item's kind.
+    Asset {
         source: Option<String>,
-        handle: Handle<Mesh>,
+        handle: HandleUntyped,
         overrides: HashMap<ParsedPath, ReflectOwned>,
     },
     Scene {
-        dynamic_scene: Arc<Mutex<DynamicScene>>,
+        source: Option<String>,
+        dynamic_scene: Arc<Mutex<Option<DynamicScene>>>,
     },
+    Custom(Box<dyn Reflect>),
 }
 
 #[derive(Clone)]
 pub enum ProjectEvent {
+    Create {
+        uuid: Uuid,
+        name: String,
+        parent_uuid: Option<Uuid>,
+        kind: String,
+        source: Option<String>,
+    },
     CreateFolder {
         uuid: Uuid,
         name: String,
@@ -72,245 +161,1695 @@ pub enum ProjectEvent {
         parent_uuid: Option<Uuid>,
         handle: Handle<Image>,
     },
+    ImportMaterial {
+        uuid: Uuid,
+        name: String,
+        parent_uuid: Option<Uuid>,
+        source: String,
+    },
+    ImportMesh {
+        uuid: Uuid,
+        name: String,
+        parent_uuid: Option<Uuid>,
+        source: String,
+    },
+    ImportImage {
+        uuid: Uuid,
+        name: String,
+        parent_uuid: Option<Uuid>,
+        source: String,
+    },
+    /// Replaces the live entities with those of a stored scene.
+    ///
+    /// Fails if the open scene has unsaved changes, unless they're to be discarded.
     LoadScene {
         scene_uuid: Uuid,
+        discard_changes: bool,
     },
     StoreScene {
         scene_uuid: Uuid,
     },
+    /// Creates a prefab from the selected entities of the open scene.
+    CreatePrefab {
+        uuid: Uuid,
+        name: String,
+        parent_uuid: Option<Uuid>,
+    },
+    InstantiatePrefab {
+        prefab_uuid: Uuid,
+    },
+    ExportScene {
+        scene_uuid: Uuid,
+        path: PathBuf,
+    },
+    SaveProject {
+        path: PathBuf,
+    },
+    LoadProject {
+        path: PathBuf,
+    },
+    /// Mounts another project manifest, read-only, under a new top-level folder.
+    MountLibrary {
+        uuid: Uuid,
+        name: String,
+        path: PathBuf,
+    },
+    UnmountLibrary {
+        uuid: Uuid,
+    },
+    SetOverride {
+        uuid: Uuid,
+        path: ParsedPath,
+        value: Arc<dyn Reflect>,
+    },
+    RemoveOverride {
+        uuid: Uuid,
+        path: ParsedPath,
+    },
+    Rename {
+        uuid: Uuid,
+        name: String,
+    },
+    AddTag {
+        uuid: Uuid,
+        tag: String,
+    },
+    RemoveTag {
+        uuid: Uuid,
+        tag: String,
+    },
+    SetMetadata {
+        uuid: Uuid,
+        key: String,
+        value: String,
+    },
+    RemoveMetadata {
+        uuid: Uuid,
+        key: String,
+    },
+    Move {
+        uuid: Uuid,
+        new_parent_uuid: Option<Uuid>,
+    },
+    /// Fails if another item still uses the deleted ones.
+    Delete {
+        uuid: Uuid,
+        recursive: bool,
+    },
+    /// Deletes several items at once, such as those found by [`unused_project_items`].
+    ///
+    /// Folders are only deleted along with all of their descendants, and items only if nothing
+    /// else uses them.
+    DeleteItems {
+        uuids: Vec<Uuid>,
+    },
+    Duplicate {
+        uuid: Uuid,
+        new_parent_uuid: Option<Uuid>,
+    },
+    /// Copies items, with their descendants and files, to the system clipboard.
+    CopyItems {
+        uuids: Vec<Uuid>,
+    },
+    /// Pastes items copied by [`ProjectEvent::CopyItems`], possibly from another project, once
+    /// the clipboard has been read.
+    PasteItems {
+        parent_uuid: Option<Uuid>,
+    },
+    /// Restores the unsaved work autosaved by a previous session, see
+    /// [`RecoverySnapshot`](recovery::RecoverySnapshot).
+    RestoreRecovery,
+    DiscardRecovery,
+    SetSource {
+        uuid: Uuid,
+        source: String,
+    },
+    SetImportSettings {
+        uuid: Uuid,
+        settings: ImportSettings,
+    },
 }
 
-fn handle_project_events(world: &mut World) {
-    let mut project_events_system_state = SystemState::<EventReader<ProjectEvent>>::new(world);
-    let project_events: Vec<ProjectEvent> = project_events_system_state
-        .get(world)
+impl ProjectItemData {
+    pub fn source(&self) -> Option<&String> {
+        match self {
+            ProjectItemData::Asset { source, .. } | ProjectItemData::Scene { source, .. } => {
+                source.as_ref()
+            }
+            ProjectItemData::Folder | ProjectItemData::Custom(_) => None,
+        }
+    }
+
+    pub fn source_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            ProjectItemData::Asset { source, .. } | ProjectItemData::Scene { source, .. } => {
+                Some(source)
+            }
+            ProjectItemData::Folder | ProjectItemData::Custom(_) => None,
+        }
+    }
+
+    pub fn handle_id(&self) -> Option<HandleId> {
+        match self {
+            ProjectItemData::Asset { handle, .. } => Some(handle.id()),
+            ProjectItemData::Folder
+            | ProjectItemData::Scene { .. }
+            | ProjectItemData::Custom(_) => None,
+        }
+    }
+
+    pub fn overrides(&self) -> Option<&HashMap<ParsedPath, ReflectOwned>> {
+        match self {
+            ProjectItemData::Asset { overrides, .. } => Some(overrides),
+            ProjectItemData::Folder
+            | ProjectItemData::Scene { .. }
+            | ProjectItemData::Custom(_) => None,
+        }
+    }
+
+    /// Weak handle to the item's asset, if it's a `T`.
+    pub fn asset_handle<T: Asset>(&self) -> Option<Handle<T>> {
+        match self {
+            ProjectItemData::Asset { handle, .. } if matches!(handle.id(), HandleId::Id(type_uuid, _) if type_uuid == T::TYPE_UUID) => {
+                Some(handle.typed_weak())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn overrides_mut(&mut self) -> Option<&mut HashMap<ParsedPath, ReflectOwned>> {
+        match self {
+            ProjectItemData::Asset { overrides, .. } => Some(overrides),
+            ProjectItemData::Folder
+            | ProjectItemData::Scene { .. }
+            | ProjectItemData::Custom(_) => None,
+        }
+    }
+}
+
+pub fn reflect_owned_as_reflect(value: &ReflectOwned) -> &dyn Reflect {
+    match value {
+        ReflectOwned::Struct(value) => value.as_reflect(),
+        ReflectOwned::TupleStruct(value) => value.as_reflect(),
+        ReflectOwned::Tuple(value) => value.as_reflect(),
+        ReflectOwned::List(value) => value.as_reflect(),
+        ReflectOwned::Array(value) => value.as_reflect(),
+        ReflectOwned::Map(value) => value.as_reflect(),
+        ReflectOwned::Enum(value) => value.as_reflect(),
+        ReflectOwned::Value(value) => value.as_reflect(),
+    }
+}
+
+pub fn reflect_owned_as_reflect_mut(value: &mut ReflectOwned) -> &mut dyn Reflect {
+    match value {
+        ReflectOwned::Struct(value) => value.as_reflect_mut(),
+        ReflectOwned::TupleStruct(value) => value.as_reflect_mut(),
+        ReflectOwned::Tuple(value) => value.as_reflect_mut(),
+        ReflectOwned::List(value) => value.as_reflect_mut(),
+        ReflectOwned::Array(value) => value.as_reflect_mut(),
+        ReflectOwned::Map(value) => value.as_reflect_mut(),
+        ReflectOwned::Enum(value) => value.as_reflect_mut(),
+        ReflectOwned::Value(value) => value.as_reflect_mut(),
+    }
+}
+
+/// Calls `visit` on `value` and every value nested in it, skipping what's nested in the values
+/// for which `visit` returns `true`.
+fn visit_reflect_mut(value: &mut dyn Reflect, visit: &mut dyn FnMut(&mut dyn Reflect) -> bool) {
+    if visit(value) {
+        return;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            for index in 0..value.field_len() {
+                visit_reflect_mut(value.field_at_mut(index).unwrap(), visit);
+            }
+        }
+        ReflectMut::TupleStruct(value) => {
+            for index in 0..value.field_len() {
+                visit_reflect_mut(value.field_mut(index).unwrap(), visit);
+            }
+        }
+        ReflectMut::Tuple(value) => {
+            for index in 0..value.field_len() {
+                visit_reflect_mut(value.field_mut(index).unwrap(), visit);
+            }
+        }
+        ReflectMut::List(value) => {
+            for index in 0..value.len() {
+                visit_reflect_mut(value.get_mut(index).unwrap(), visit);
+            }
+        }
+        ReflectMut::Array(value) => {
+            for index in 0..value.len() {
+                visit_reflect_mut(value.get_mut(index).unwrap(), visit);
+            }
+        }
+        // Map keys can't be modified in place, so only values are visited
+        ReflectMut::Map(value) => {
+            for index in 0..value.len() {
+                visit_reflect_mut(value.get_at_mut(index).unwrap().1, visit);
+            }
+        }
+        ReflectMut::Enum(value) => {
+            // Dynamic enums only give the fields of struct variants by name
+            for index in 0..value.field_len() {
+                let field = match value.name_at(index).map(str::to_string) {
+                    Some(name) => value.field_mut(&name),
+                    None => value.field_at_mut(index),
+                };
+                visit_reflect_mut(field.unwrap(), visit);
+            }
+        }
+        ReflectMut::Value(_) => {}
+    }
+}
+
+fn clone_overrides(
+    overrides: &HashMap<ParsedPath, ReflectOwned>,
+) -> HashMap<ParsedPath, ReflectOwned> {
+    overrides
         .iter()
-        .cloned()
+        .map(|(path, value)| {
+            (
+                path.clone(),
+                reflect_owned_as_reflect(value)
+                    .clone_value()
+                    .reflect_owned(),
+            )
+        })
+        .collect()
+}
+
+fn asset_root_path(asset_server: &AssetServer) -> PathBuf {
+    asset_server
+        .asset_io()
+        .downcast_ref::<FileAssetIo>()
+        .map(|asset_io| asset_io.root_path().clone())
+        .unwrap_or_else(|| FileAssetIo::get_base_path().join("assets"))
+}
+
+fn despawn_editor_items(world: &mut World) {
+    let root_editor_items: Vec<Entity> = world
+        .query_filtered::<(Entity, Option<&Parent>), With<EditorItem>>()
+        .iter(world)
+        .filter(|(_, parent)| {
+            parent.map_or(true, |parent| {
+                world.get::<EditorItem>(parent.get()).is_none()
+            })
+        })
+        .map(|(entity, _)| entity)
         .collect();
 
-    for event in project_events {
-        match &event {
-            ProjectEvent::CreateFolder {
-                uuid,
-                name,
-                parent_uuid,
-            } => {
-                let parent = parent_uuid
-                    .map(|uuid| world.resource::<ProjectItemRegistry>().items.get(&uuid))
-                    .flatten()
-                    .cloned();
-
-                let mut entity = world.spawn(ProjectItem {
-                    uuid: *uuid,
-                    name: name.clone(),
-                    data: ProjectItemData::Folder,
-                });
+    for entity in root_editor_items {
+        world.entity_mut(entity).despawn_recursive();
+    }
+}
+
+fn is_descendant_of(world: &World, entity: Entity, ancestor: Entity) -> bool {
+    let mut current = entity;
+    while let Some(parent) = world.get::<Parent>(current) {
+        if parent.get() == ancestor {
+            return true;
+        }
+        current = parent.get();
+    }
+    false
+}
+
+fn collect_item_uuids(world: &World, entity: Entity, uuids: &mut Vec<Uuid>) {
+    let Some(project_item) = world.get::<ProjectItem>(entity) else {
+        return;
+    };
+
+    uuids.push(project_item.uuid);
+
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.iter() {
+            collect_item_uuids(world, *child, uuids);
+        }
+    }
+}
+
+/// Despawns an item and its descendants, forgetting their references.
+fn despawn_project_items(world: &mut World, entity: Entity) {
+    if world.get_entity(entity).is_none() {
+        return;
+    }
+
+    let mut uuids = Vec::new();
+    collect_item_uuids(world, entity, &mut uuids);
+
+    let mut dependencies = world.resource_mut::<ProjectDependencies>();
+    for uuid in &uuids {
+        dependencies.remove(*uuid);
+    }
+
+    let mut registry = world.resource_mut::<ProjectItemRegistry>();
+    for uuid in &uuids {
+        registry.items.remove(uuid);
+    }
+
+    let mut open_scene = world.resource_mut::<OpenScene>();
+    if open_scene
+        .scene_uuid
+        .is_some_and(|scene_uuid| uuids.contains(&scene_uuid))
+    {
+        open_scene.scene_uuid = None;
+    }
+
+    world.entity_mut(entity).despawn_recursive();
+}
+
+/// Fails if any of `uuids` is used by an item that isn't deleted along with it.
+fn ensure_unused(world: &mut World, uuids: &[Uuid]) -> Result<(), ProjectErrorKind> {
+    for uuid in uuids {
+        let usages: Vec<Uuid> = find_usages(world, *uuid)?
+            .into_iter()
+            .filter(|usage| !uuids.contains(usage))
+            .collect();
+
+        if !usages.is_empty() {
+            return Err(ProjectErrorKind::ItemInUse {
+                uuid: *uuid,
+                usages,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_item_tree(
+    world: &World,
+    entity: Entity,
+    parent_index: Option<usize>,
+    items: &mut Vec<(Entity, Option<usize>)>,
+) {
+    if world.get::<ProjectItem>(entity).is_none() {
+        return;
+    }
+
+    let index = items.len();
+    items.push((entity, parent_index));
+
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.iter() {
+            collect_item_tree(world, *child, Some(index), items);
+        }
+    }
+}
+
+fn sibling_names(world: &World, parent: Option<Entity>) -> HashSet<String> {
+    world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .values()
+        .filter(|entity| world.get::<Parent>(**entity).map(Parent::get) == parent)
+        .filter_map(|entity| world.get::<ProjectItem>(*entity))
+        .map(|project_item| project_item.name.clone())
+        .collect()
+}
+
+fn duplicate_name(world: &World, name: &str, parent: Option<Entity>) -> String {
+    unused_copy_name(&sibling_names(world, parent), name)
+}
+
+fn unused_copy_name(sibling_names: &HashSet<String>, name: &str) -> String {
+    let mut duplicate_name = format!("{} copy", name);
+    let mut counter = 2;
+    while sibling_names.contains(&duplicate_name) {
+        duplicate_name = format!("{} copy {}", name, counter);
+        counter += 1;
+    }
+
+    duplicate_name
+}
+
+fn duplicate_project_items(
+    world: &mut World,
+    entity: Entity,
+    new_parent_uuid: Option<Uuid>,
+) -> Result<Entity, ProjectErrorKind> {
+    let new_parent = match new_parent_uuid {
+        Some(new_parent_uuid) => Some(folder_entity(world, new_parent_uuid)?),
+        None => None,
+    };
+
+    // Snapshot the whole tree first, so duplicating a folder into itself doesn't recurse forever
+    let mut originals = Vec::new();
+    collect_item_tree(world, entity, None, &mut originals);
+
+    let mut duplicates: Vec<(Uuid, Entity)> = Vec::with_capacity(originals.len());
+
+    for (original, parent_index) in originals {
+        let project_item = world.get::<ProjectItem>(original).unwrap();
+        let original_uuid = project_item.uuid;
+        let kind = project_item.kind.clone();
+        let tags = project_item.tags.clone();
+        let metadata = project_item.metadata.clone();
+
+        let (name, parent_uuid) = match parent_index {
+            Some(parent_index) => (project_item.name.clone(), Some(duplicates[parent_index].0)),
+            None => (
+                duplicate_name(world, &project_item.name, new_parent),
+                new_parent_uuid,
+            ),
+        };
+
+        let uuid = Uuid::new_v4();
+
+        match spawn_project_item(
+            world,
+            uuid,
+            name,
+            parent_uuid,
+            kind.clone(),
+            |world, entity| kind.duplicate(world, original, entity, uuid),
+        ) {
+            Ok(duplicate) => {
+                let mut project_item = world.get_mut::<ProjectItem>(duplicate).unwrap();
+                project_item.tags = tags;
+                project_item.metadata = metadata;
 
-                if let Some(parent) = parent {
-                    entity.set_parent(parent);
+                let mut dependencies = world.resource_mut::<ProjectDependencies>();
+                let references = dependencies.references(original_uuid).collect();
+                dependencies.set_references(uuid, references);
+
+                duplicates.push((uuid, duplicate));
+            }
+            Err(err) => {
+                if let Some((_, root)) = duplicates.first() {
+                    world.entity_mut(*root).despawn_recursive();
                 }
 
-                let entity = entity.id();
+                for (uuid, _) in &duplicates {
+                    world
+                        .resource_mut::<ProjectItemRegistry>()
+                        .items
+                        .remove(uuid);
+                    world.resource_mut::<ProjectDependencies>().remove(*uuid);
+                }
 
-                world
-                    .resource_mut::<ProjectItemRegistry>()
-                    .items
-                    .insert(*uuid, entity);
+                return Err(err);
             }
+        }
+    }
+
+    Ok(duplicates[0].1)
+}
+
+/// Loads the new source of an asset item, which keeps its derived handle.
+fn set_asset_source<T: Asset>(world: &mut World, entity: Entity, source: &str) {
+    let handle = world.resource::<AssetServer>().load(source);
+    let import_settings = source_import_settings(world, source);
 
-            ProjectEvent::CreateScene {
+    let mut entity = world.entity_mut(entity);
+    entity.insert((SourceAsset::<T> { handle }, SourceLoading));
+
+    match import_settings {
+        Some(import_settings) => entity.insert(import_settings),
+        None => entity.remove::<ImportSettings>(),
+    };
+}
+
+fn set_import_settings(
+    world: &mut World,
+    uuid: Uuid,
+    settings: &ImportSettings,
+) -> Result<(), ProjectErrorKind> {
+    let entity = writable_item_entity(world, uuid)?;
+    let project_item = world.get::<ProjectItem>(entity).unwrap();
+
+    let data = &project_item.data;
+    match settings {
+        ImportSettings::Image(_) if data.asset_handle::<Image>().is_some() => {}
+        ImportSettings::Mesh(_) if data.asset_handle::<Mesh>().is_some() => {}
+        ImportSettings::Image(_) => {
+            return Err(ProjectErrorKind::WrongItemKind {
                 uuid,
-                name,
-                parent_uuid,
-            } => {
-                let parent = parent_uuid
-                    .map(|uuid| world.resource::<ProjectItemRegistry>().items.get(&uuid))
-                    .flatten()
-                    .cloned();
-
-                let mut entity = world.spawn(ProjectItem {
-                    uuid: *uuid,
-                    name: name.clone(),
-                    data: ProjectItemData::Scene {
-                        dynamic_scene: Arc::new(Mutex::from(DynamicScene::default())),
-                    },
-                });
-
-                if let Some(parent) = parent {
-                    entity.set_parent(parent);
+                expected: "image",
+            })
+        }
+        ImportSettings::Mesh(_) => {
+            return Err(ProjectErrorKind::WrongItemKind {
+                uuid,
+                expected: "mesh",
+            })
+        }
+    }
+
+    // Settings are kept beside the source file, so they apply again whenever it's imported
+    if let Some(source) = project_item.data.source() {
+        let asset_path = AssetPath::from(source.as_str());
+        let path = asset_root_path(world.resource::<AssetServer>()).join(asset_path.path());
+
+        let mut meta = AssetMeta::load(&path).unwrap_or_else(|| AssetMeta::new(uuid));
+        meta.import_settings = Some(settings.clone());
+        meta.save(&path)
+            .map_err(|err| ProjectErrorKind::MetaWrite(AssetMeta::path(&path), err))?;
+    }
+
+    // Changing the settings rebuilds the item's asset from its source
+    world.entity_mut(entity).insert(settings.clone());
+
+    Ok(())
+}
+
+fn folder_entity(world: &World, uuid: Uuid) -> Result<Entity, ProjectErrorKind> {
+    let entity = item_entity(world, uuid)?;
+
+    match world.get::<ProjectItem>(entity).unwrap().data {
+        ProjectItemData::Folder => Ok(entity),
+        _ => Err(ProjectErrorKind::WrongItemKind {
+            uuid,
+            expected: "folder",
+        }),
+    }
+}
+
+fn project_item_kind(
+    world: &World,
+    kind: &str,
+) -> Result<Arc<dyn ProjectItemKind>, ProjectErrorKind> {
+    world
+        .resource::<ProjectItemKinds>()
+        .get(kind)
+        .ok_or_else(|| ProjectErrorKind::UnknownKind(kind.to_string()))
+}
+
+fn spawn_project_item(
+    world: &mut World,
+    uuid: Uuid,
+    name: String,
+    parent_uuid: Option<Uuid>,
+    kind: Arc<dyn ProjectItemKind>,
+    data: impl FnOnce(&mut World, Entity) -> Result<ProjectItemData, ProjectErrorKind>,
+) -> Result<Entity, ProjectErrorKind> {
+    if world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .contains_key(&uuid)
+    {
+        return Err(ProjectErrorKind::DuplicateUuid(uuid));
+    }
+
+    let parent = match parent_uuid {
+        Some(parent_uuid) => Some(folder_entity(world, parent_uuid).map_err(|err| match err {
+            ProjectErrorKind::UnknownItem(uuid) => ProjectErrorKind::UnknownParent(uuid),
+            err => err,
+        })?),
+        None => None,
+    };
+
+    if let (Some(parent), Some(parent_uuid)) = (parent, parent_uuid) {
+        if world.get::<LibraryItem>(parent).is_some() {
+            return Err(ProjectErrorKind::ReadOnlyItem(parent_uuid));
+        }
+    }
+
+    // The entity exists before its data, so that kinds can attach their own components to it
+    let entity = world.spawn_empty().id();
+
+    let data = match data(world, entity) {
+        Ok(data) => data,
+        Err(err) => {
+            world.despawn(entity);
+            return Err(err);
+        }
+    };
+
+    let mut entity = world.entity_mut(entity);
+
+    entity.insert(ProjectItem {
+        uuid,
+        name,
+        kind,
+        data,
+        tags: default(),
+        metadata: default(),
+        has_unsaved_changes: false,
+        load_error: None,
+    });
+
+    if let Some(parent) = parent {
+        entity.set_parent(parent);
+    }
+
+    let entity = entity.id();
+
+    world
+        .resource_mut::<ProjectItemRegistry>()
+        .items
+        .insert(uuid, entity);
+
+    Ok(entity)
+}
+
+fn create_project_item(
+    world: &mut World,
+    uuid: Uuid,
+    name: String,
+    parent_uuid: Option<Uuid>,
+    kind: &str,
+    source: Option<String>,
+) -> Result<Entity, ProjectErrorKind> {
+    let kind = project_item_kind(world, kind)?;
+
+    spawn_project_item(
+        world,
+        uuid,
+        name,
+        parent_uuid,
+        kind.clone(),
+        |world, entity| Ok(kind.create(world, entity, uuid, source)),
+    )
+}
+
+fn collect_manifest_items(
+    world: &World,
+    entity: Entity,
+    parent_uuid: Option<Uuid>,
+    items: &mut Vec<ProjectManifestItem>,
+) {
+    let Some(project_item) = world.get::<ProjectItem>(entity) else {
+        return;
+    };
+
+    let serialized = project_item.kind.serialize(&project_item.data);
+    let import_settings = match serialized.source {
+        Some(_) => None,
+        None => world.get::<ImportSettings>(entity).cloned(),
+    };
+
+    items.push(ProjectManifestItem {
+        uuid: project_item.uuid,
+        name: project_item.name.clone(),
+        parent_uuid,
+        kind: project_item.kind.name().to_string(),
+        source: serialized.source,
+        tags: project_item.tags.clone(),
+        metadata: project_item.metadata.clone(),
+        import_settings,
+        overrides: serialized.overrides,
+        properties: serialized.properties,
+    });
+
+    let uuid = project_item.uuid;
+
+    if let Some(children) = world.get::<Children>(entity) {
+        let mut children: Vec<_> = children
+            .iter()
+            .filter_map(|child| Some((*child, world.get::<ProjectItem>(*child)?)))
+            .collect();
+        children.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name).then(a.uuid.cmp(&b.uuid)));
+
+        for (child, _) in children {
+            collect_manifest_items(world, child, Some(uuid), items);
+        }
+    }
+}
+
+fn build_project_manifest(world: &mut World) -> ProjectManifest {
+    let mut roots: Vec<_> = world
+        .query::<(Entity, &ProjectItem, Option<&Parent>)>()
+        .iter(world)
+        .filter(|(_, _, parent)| {
+            parent.map_or(true, |parent| {
+                world.get::<ProjectItem>(parent.get()).is_none()
+            })
+        })
+        .filter(|(entity, _, _)| world.get::<LibraryItem>(*entity).is_none())
+        .map(|(entity, project_item, _)| (entity, project_item.name.clone(), project_item.uuid))
+        .collect();
+    roots.sort_by(|(_, a_name, a_uuid), (_, b_name, b_uuid)| {
+        a_name.cmp(b_name).then(a_uuid.cmp(b_uuid))
+    });
+
+    let mut items = Vec::new();
+    for (entity, _, _) in roots {
+        collect_manifest_items(world, entity, None, &mut items);
+    }
+
+    let mut libraries: Vec<ProjectManifestLibrary> = world
+        .resource::<ProjectItemRegistry>()
+        .libraries
+        .iter()
+        .map(|(uuid, library)| ProjectManifestLibrary {
+            uuid: *uuid,
+            name: library.name.clone(),
+            path: library.path.clone(),
+        })
+        .collect();
+    libraries.sort_by(|a, b| a.name.cmp(&b.name).then(a.uuid.cmp(&b.uuid)));
+
+    ProjectManifest {
+        version: manifest::PROJECT_MANIFEST_VERSION,
+        items,
+        libraries,
+    }
+}
+
+fn save_project(world: &mut World, path: &Path) -> Result<(), ProjectErrorKind> {
+    let manifest = build_project_manifest(world);
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+
+    manifest
+        .save(path, &type_registry)
+        .map_err(|err| ProjectErrorKind::ManifestWrite(path.to_path_buf(), err))
+}
+
+fn validate_project_manifest(
+    manifest: &ProjectManifest,
+    kinds: &ProjectItemKinds,
+) -> Result<(), ProjectErrorKind> {
+    let mut folders = HashMap::default();
+
+    for item in &manifest.items {
+        if kinds.get(&item.kind).is_none() {
+            return Err(ProjectErrorKind::InvalidManifest(format!(
+                "item {} has unknown kind {}",
+                item.uuid, item.kind
+            )));
+        }
+
+        let is_folder = item.kind == FolderKind.name();
+        if folders.insert(item.uuid, is_folder).is_some() {
+            return Err(ProjectErrorKind::InvalidManifest(format!(
+                "duplicate item uuid {}",
+                item.uuid
+            )));
+        }
+    }
+
+    for item in &manifest.items {
+        if let Some(parent_uuid) = item.parent_uuid {
+            match folders.get(&parent_uuid) {
+                Some(true) => {}
+                Some(false) => {
+                    return Err(ProjectErrorKind::InvalidManifest(format!(
+                        "parent {} of item {} is not a folder",
+                        parent_uuid, item.uuid
+                    )))
+                }
+                None => {
+                    return Err(ProjectErrorKind::InvalidManifest(format!(
+                        "item {} references missing parent {}",
+                        item.uuid, parent_uuid
+                    )))
                 }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawns the items of a manifest, parenting its top-level items to `root` if given.
+///
+/// Nothing is left spawned when an item fails.
+fn spawn_manifest_items(
+    world: &mut World,
+    items: Vec<ProjectManifestItem>,
+    root: Option<Entity>,
+) -> Result<Vec<Entity>, ProjectErrorKind> {
+    let mut entities = Vec::new();
+
+    let result = spawn_manifest_items_into(world, items, root, &mut entities);
+    if result.is_err() {
+        for entity in &entities {
+            despawn_project_items(world, *entity);
+        }
+    }
+
+    result.map(|()| entities)
+}
+
+fn spawn_manifest_items_into(
+    world: &mut World,
+    items: Vec<ProjectManifestItem>,
+    root: Option<Entity>,
+    entities: &mut Vec<Entity>,
+) -> Result<(), ProjectErrorKind> {
+    // Spawn every item before parenting, so that manifests listing children
+    // ahead of their parents still load correctly.
+    let mut parents = Vec::new();
+
+    for item in items {
+        let kind = project_item_kind(world, &item.kind)?;
+        let serialized = SerializedProjectItem {
+            source: item.source,
+            overrides: item.overrides,
+            properties: item.properties,
+        };
 
-                let entity = entity.id();
+        let entity = spawn_project_item(
+            world,
+            item.uuid,
+            item.name,
+            None,
+            kind.clone(),
+            |world, entity| {
+                kind.deserialize(world, entity, item.uuid, serialized)
+                    .map_err(|message| {
+                        ProjectErrorKind::InvalidManifest(format!(
+                            "item {}: {}",
+                            item.uuid, message
+                        ))
+                    })
+            },
+        )?;
+        entities.push(entity);
 
-                world
-                    .resource_mut::<ProjectItemRegistry>()
-                    .items
-                    .insert(*uuid, entity);
+        let mut project_item = world.get_mut::<ProjectItem>(entity).unwrap();
+        project_item.tags = item.tags;
+        project_item.metadata = item.metadata;
+
+        if let Some(import_settings) = item.import_settings {
+            world.entity_mut(entity).insert(import_settings);
+        }
+
+        match (item.parent_uuid, root) {
+            (Some(parent_uuid), _) => parents.push((entity, parent_uuid)),
+            (None, Some(root)) => {
+                world.entity_mut(entity).set_parent(root);
             }
+            (None, None) => {}
+        }
+    }
 
-            ProjectEvent::CreateMaterial {
-                uuid,
-                name,
-                parent_uuid,
-            } => {
-                let parent = parent_uuid
-                    .map(|uuid| world.resource::<ProjectItemRegistry>().items.get(&uuid))
-                    .flatten()
-                    .cloned();
+    for (entity, parent_uuid) in parents {
+        let parent = item_entity(world, parent_uuid)?;
+        world.entity_mut(entity).set_parent(parent);
+    }
 
-                let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+    Ok(())
+}
 
-                let material = materials.add(StandardMaterial::default());
+fn load_project(world: &mut World, path: &Path) -> Result<(), ProjectErrorKind> {
+    // Older manifests are only upgraded in memory, the `migrate` command rewrites them
+    let (manifest, report) = read_project_manifest(world, path)?;
+    if let Some(report) = report {
+        info!(
+            "upgraded project {} from version {} to {} in memory",
+            path.display(),
+            report.from_version,
+            report.to_version
+        );
+    }
 
-                let mut entity = world.spawn(ProjectItem {
-                    uuid: *uuid,
-                    name: name.clone(),
-                    data: ProjectItemData::Material {
+    validate_project_manifest(&manifest, world.resource::<ProjectItemKinds>())?;
+
+    let mut registry = world.resource_mut::<ProjectItemRegistry>();
+    registry.libraries.clear();
+    let existing_items: Vec<Entity> = registry.items.drain().map(|(_, entity)| entity).collect();
+
+    for entity in existing_items {
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+    }
+
+    world.resource_mut::<OpenScene>().scene_uuid = None;
+    world.resource_mut::<ProjectDependencies>().clear();
+
+    spawn_manifest_items(world, manifest.items, None)?;
+
+    // A library failing to mount doesn't stop the others, nor the project, from loading
+    let mut mount_error = None;
+    for library in manifest.libraries {
+        if let Err(err) = mount_library(world, library.uuid, library.name, &library.path) {
+            mount_error.get_or_insert(err);
+        }
+    }
+
+    match mount_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn item_entity(world: &World, uuid: Uuid) -> Result<Entity, ProjectErrorKind> {
+    world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .get(&uuid)
+        .cloned()
+        .ok_or(ProjectErrorKind::UnknownItem(uuid))
+}
+
+/// The entity of a scene item, its source and its cached scene.
+type SceneItemData = (Entity, Option<String>, Arc<Mutex<Option<DynamicScene>>>);
+
+fn scene_item_data(world: &World, scene_uuid: Uuid) -> Result<SceneItemData, ProjectErrorKind> {
+    let scene_entity = item_entity(world, scene_uuid)?;
+
+    match &world.get::<ProjectItem>(scene_entity).unwrap().data {
+        ProjectItemData::Scene {
+            source,
+            dynamic_scene,
+        } => Ok((scene_entity, source.clone(), dynamic_scene.clone())),
+        _ => Err(ProjectErrorKind::WrongItemKind {
+            uuid: scene_uuid,
+            expected: "scene",
+        }),
+    }
+}
+
+fn read_scene_source(world: &World, source: &str) -> Result<DynamicScene, ProjectErrorKind> {
+    let path = asset_root_path(world.resource::<AssetServer>()).join(source);
+    let type_registry = world.resource::<AppTypeRegistry>();
+
+    load_scene_file(&path, type_registry).map_err(|err| ProjectErrorKind::SceneRead(path, err))
+}
+
+fn read_scene_if_needed(
+    world: &mut World,
+    scene_uuid: Uuid,
+    source: Option<String>,
+    dynamic_scene: &mut Option<DynamicScene>,
+) -> Result<(), ProjectErrorKind> {
+    // Scenes are read from disk the first time they're opened
+    if dynamic_scene.is_none() {
+        *dynamic_scene = Some(match source {
+            Some(source) => read_scene_source(world, &source)?,
+            None => DynamicScene::default(),
+        });
+
+        let references = scene_references(world, dynamic_scene.as_ref().unwrap());
+        world
+            .resource_mut::<ProjectDependencies>()
+            .set_references(scene_uuid, references);
+    }
+
+    Ok(())
+}
+
+fn load_scene(
+    world: &mut World,
+    scene_uuid: Uuid,
+    discard_changes: bool,
+) -> Result<(), ProjectErrorKind> {
+    let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+    if let Some(open_scene_uuid) = world.resource::<OpenScene>().scene_uuid {
+        // Scenes that can't be compared anymore, such as deleted ones, have nothing to keep
+        let has_unsaved_changes =
+            diff_scene(world, open_scene_uuid).is_ok_and(|scene_diff| !scene_diff.is_empty());
+
+        if has_unsaved_changes && !discard_changes {
+            return Err(ProjectErrorKind::UnsavedChanges(open_scene_uuid));
+        }
+    }
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+    read_scene_if_needed(world, scene_uuid, source, &mut dynamic_scene)?;
+
+    despawn_editor_items(world);
+
+    let mut entity_map = EntityMap::default();
+
+    let result = dynamic_scene
+        .as_ref()
+        .unwrap()
+        .write_to_world(world, &mut entity_map);
+
+    // `EditorItem` isn't part of the stored scene, so tag every spawned entity again
+    for entity in entity_map.values() {
+        world.entity_mut(entity).insert(EditorItem::default());
+    }
+    insert_computed_components(world, entity_map.values());
+
+    let mut open_scene = world.resource_mut::<OpenScene>();
+    open_scene.scene_uuid = Some(scene_uuid);
+    open_scene.stored_entities = entity_map;
+
+    result.map_err(ProjectErrorKind::SceneSpawn)
+}
+
+fn store_scene(world: &mut World, scene_uuid: Uuid) -> Result<(), ProjectErrorKind> {
+    writable_item_entity(world, scene_uuid)?;
+    let (scene_entity, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+    let is_prefab = is_prefab(world, scene_uuid);
+
+    // The previous version of a prefab tells which fields its instances have overridden
+    let previous_prefab = if is_prefab {
+        let mut previous_dynamic_scene = dynamic_scene
+            .lock()
+            .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+        read_scene_if_needed(
+            world,
+            scene_uuid,
+            source.clone(),
+            &mut previous_dynamic_scene,
+        )?;
+
+        assign_prefab_entity_ids(world);
+
+        Some(clone_dynamic_scene(
+            previous_dynamic_scene.as_ref().unwrap(),
+        ))
+    } else {
+        None
+    };
+
+    assign_editor_ids(world);
+
+    let mut query = world.query_filtered::<Entity, With<EditorItem>>();
+    let mut updated_dynamic_scene = extract_stored_scene(world, query.iter(world));
+    let stored_ids = use_editor_ids(&mut updated_dynamic_scene);
+
+    let source = source.unwrap_or_else(|| {
+        let directory = if is_prefab { "prefabs" } else { "scenes" };
+        format!("{}/{}.scn.ron", directory, scene_uuid)
+    });
+    let path = asset_root_path(world.resource::<AssetServer>()).join(&source);
+    let type_registry = world.resource::<AppTypeRegistry>();
+    save_scene_file(&path, &updated_dynamic_scene, type_registry)
+        .map_err(|err| ProjectErrorKind::SceneWrite(path, err))?;
+
+    let references = scene_references(world, &updated_dynamic_scene);
+    world
+        .resource_mut::<ProjectDependencies>()
+        .set_references(scene_uuid, references);
+
+    if let ProjectItemData::Scene {
+        source: item_source,
+        ..
+    } = &mut world.get_mut::<ProjectItem>(scene_entity).unwrap().data
+    {
+        if item_source.is_none() {
+            *item_source = Some(source);
+        }
+    }
+
+    let updated_prefab = previous_prefab
+        .as_ref()
+        .map(|_| clone_dynamic_scene(&updated_dynamic_scene));
+
+    // The stored scene was extracted from the live entities, so they now match one to one
+    let mut stored_entities = EntityMap::default();
+    for (entity, stored_id) in stored_ids.iter() {
+        stored_entities.insert(stored_id, entity);
+    }
+
+    let mut arc_dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+    *arc_dynamic_scene = Some(updated_dynamic_scene);
+    drop(arc_dynamic_scene);
+
+    let mut open_scene = world.resource_mut::<OpenScene>();
+    open_scene.scene_uuid = Some(scene_uuid);
+    open_scene.stored_entities = stored_entities;
+
+    if let (Some(previous_prefab), Some(updated_prefab)) = (previous_prefab, updated_prefab) {
+        propagate_prefab_changes(world, scene_uuid, &previous_prefab, &updated_prefab)?;
+    }
+
+    Ok(())
+}
+
+fn export_scene(world: &mut World, scene_uuid: Uuid, path: &Path) -> Result<(), ProjectErrorKind> {
+    let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+    read_scene_if_needed(world, scene_uuid, source, &mut dynamic_scene)?;
+
+    let type_registry = world.resource::<AppTypeRegistry>();
+    save_scene_file(path, dynamic_scene.as_ref().unwrap(), type_registry)
+        .map_err(|err| ProjectErrorKind::SceneWrite(path.to_path_buf(), err))
+}
+
+fn handle_project_event(world: &mut World, event: &ProjectEvent) -> Result<(), ProjectErrorKind> {
+    match event {
+        ProjectEvent::Create {
+            uuid,
+            name,
+            parent_uuid,
+            kind,
+            source,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                kind,
+                source.clone(),
+            )?;
+        }
+
+        ProjectEvent::CreateFolder {
+            uuid,
+            name,
+            parent_uuid,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                FolderKind.name(),
+                None,
+            )?;
+        }
+
+        ProjectEvent::CreateScene {
+            uuid,
+            name,
+            parent_uuid,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                SceneKind.name(),
+                None,
+            )?;
+        }
+
+        ProjectEvent::CreateMaterial {
+            uuid,
+            name,
+            parent_uuid,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                MaterialKind.name(),
+                None,
+            )?;
+        }
+
+        ProjectEvent::CreateMesh {
+            uuid,
+            name,
+            handle,
+            parent_uuid,
+        } => {
+            let kind = project_item_kind(world, MeshKind.name())?;
+
+            spawn_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                kind,
+                |world, entity| {
+                    let handle = asset_item_handle(world, entity, *uuid, None, handle.clone());
+                    Ok(ProjectItemData::Asset {
                         source: None,
-                        handle: material,
+                        handle: handle.clone_untyped(),
                         overrides: default(),
-                    },
-                });
+                    })
+                },
+            )?;
+        }
 
-                if let Some(parent) = parent {
-                    entity.set_parent(parent);
-                }
+        ProjectEvent::CreateImage {
+            uuid,
+            name,
+            handle,
+            parent_uuid,
+        } => {
+            let kind = project_item_kind(world, ImageKind.name())?;
+
+            spawn_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                kind,
+                |world, entity| {
+                    let handle = asset_item_handle(world, entity, *uuid, None, handle.clone());
+                    Ok(ProjectItemData::Asset {
+                        source: None,
+                        handle: handle.clone_untyped(),
+                        overrides: default(),
+                    })
+                },
+            )?;
+        }
 
-                let entity = entity.id();
+        ProjectEvent::ImportMaterial {
+            uuid,
+            name,
+            parent_uuid,
+            source,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                MaterialKind.name(),
+                Some(source.clone()),
+            )?;
+        }
 
-                world
-                    .resource_mut::<ProjectItemRegistry>()
-                    .items
-                    .insert(*uuid, entity);
-            }
+        ProjectEvent::ImportMesh {
+            uuid,
+            name,
+            parent_uuid,
+            source,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                MeshKind.name(),
+                Some(source.clone()),
+            )?;
+        }
 
-            ProjectEvent::CreateMesh {
-                uuid,
-                name,
-                handle,
-                parent_uuid,
-            } => {
-                let parent = parent_uuid
-                    .map(|uuid| world.resource::<ProjectItemRegistry>().items.get(&uuid))
-                    .flatten()
-                    .cloned();
-
-                let mut entity = world.spawn(ProjectItem {
+        ProjectEvent::ImportImage {
+            uuid,
+            name,
+            parent_uuid,
+            source,
+        } => {
+            create_project_item(
+                world,
+                *uuid,
+                name.clone(),
+                *parent_uuid,
+                ImageKind.name(),
+                Some(source.clone()),
+            )?;
+        }
+
+        ProjectEvent::LoadScene {
+            scene_uuid,
+            discard_changes,
+        } => {
+            load_scene(world, *scene_uuid, *discard_changes)?;
+        }
+
+        ProjectEvent::StoreScene { scene_uuid } => {
+            store_scene(world, *scene_uuid)?;
+        }
+
+        ProjectEvent::CreatePrefab {
+            uuid,
+            name,
+            parent_uuid,
+        } => {
+            create_prefab(world, *uuid, name.clone(), *parent_uuid)?;
+        }
+
+        ProjectEvent::InstantiatePrefab { prefab_uuid } => {
+            instantiate_prefab(world, *prefab_uuid)?;
+        }
+
+        ProjectEvent::ExportScene { scene_uuid, path } => {
+            export_scene(world, *scene_uuid, path)?;
+        }
+
+        ProjectEvent::SaveProject { path } => {
+            save_project(world, path)?;
+            track_saved_project(world, path);
+        }
+
+        ProjectEvent::LoadProject { path } => {
+            load_project(world, path)?;
+            track_saved_project(world, path);
+        }
+
+        ProjectEvent::MountLibrary { uuid, name, path } => {
+            mount_library(world, *uuid, name.clone(), path)?;
+        }
+
+        ProjectEvent::UnmountLibrary { uuid } => {
+            unmount_library(world, *uuid)?;
+        }
+
+        ProjectEvent::SetOverride { uuid, path, value } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            let mut project_item = world.get_mut::<ProjectItem>(entity).unwrap();
+
+            project_item
+                .data
+                .overrides_mut()
+                .ok_or(ProjectErrorKind::WrongItemKind {
                     uuid: *uuid,
-                    name: name.clone(),
-                    data: ProjectItemData::Mesh {
-                        source: None,
-                        handle: handle.clone(),
-                        overrides: default(),
-                    },
-                });
+                    expected: "material, image or mesh",
+                })?
+                .insert(path.clone(), value.clone_value().reflect_owned());
 
-                if let Some(parent) = parent {
-                    entity.set_parent(parent);
-                }
+            world.entity_mut(entity).insert(OverridesChanged);
+        }
 
-                let entity = entity.id();
+        ProjectEvent::RemoveOverride { uuid, path } => {
+            let entity = writable_item_entity(world, *uuid)?;
 
-                world
-                    .resource_mut::<ProjectItemRegistry>()
-                    .items
-                    .insert(*uuid, entity);
-            }
+            let mut project_item = world.get_mut::<ProjectItem>(entity).unwrap();
 
-            ProjectEvent::CreateImage {
-                uuid,
-                name,
-                handle,
-                parent_uuid,
-            } => {
-                let parent = parent_uuid
-                    .map(|uuid| world.resource::<ProjectItemRegistry>().items.get(&uuid))
-                    .flatten()
-                    .cloned();
-
-                let mut entity = world.spawn(ProjectItem {
+            project_item
+                .data
+                .overrides_mut()
+                .ok_or(ProjectErrorKind::WrongItemKind {
                     uuid: *uuid,
-                    name: name.clone(),
-                    data: ProjectItemData::Image {
-                        source: None,
-                        handle: handle.clone(),
-                        overrides: default(),
-                    },
-                });
+                    expected: "material, image or mesh",
+                })?
+                .remove(path);
+
+            world.entity_mut(entity).insert(OverridesChanged);
+        }
+
+        ProjectEvent::Rename { uuid, name } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            world.get_mut::<ProjectItem>(entity).unwrap().name = name.clone();
+        }
+
+        ProjectEvent::AddTag { uuid, tag } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            let tag = tag.trim();
+            if tag.is_empty() {
+                return Err(ProjectErrorKind::InvalidItemData(
+                    "tags can't be empty".to_string(),
+                ));
+            }
+
+            world
+                .get_mut::<ProjectItem>(entity)
+                .unwrap()
+                .tags
+                .insert(tag.to_string());
+        }
+
+        ProjectEvent::RemoveTag { uuid, tag } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            world
+                .get_mut::<ProjectItem>(entity)
+                .unwrap()
+                .tags
+                .remove(tag.trim());
+        }
+
+        ProjectEvent::SetMetadata { uuid, key, value } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(ProjectErrorKind::InvalidItemData(
+                    "metadata keys can't be empty".to_string(),
+                ));
+            }
+
+            world
+                .get_mut::<ProjectItem>(entity)
+                .unwrap()
+                .metadata
+                .insert(key.to_string(), value.clone());
+        }
+
+        ProjectEvent::RemoveMetadata { uuid, key } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            world
+                .get_mut::<ProjectItem>(entity)
+                .unwrap()
+                .metadata
+                .remove(key.trim());
+        }
+
+        ProjectEvent::Move {
+            uuid,
+            new_parent_uuid,
+        } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            match new_parent_uuid {
+                Some(new_parent_uuid) => {
+                    let new_parent = folder_entity(world, *new_parent_uuid)?;
+                    writable_item_entity(world, *new_parent_uuid)?;
+
+                    if new_parent == entity || is_descendant_of(world, new_parent, entity) {
+                        return Err(ProjectErrorKind::MoveIntoDescendant {
+                            uuid: *uuid,
+                            new_parent_uuid: *new_parent_uuid,
+                        });
+                    }
 
-                if let Some(parent) = parent {
-                    entity.set_parent(parent);
+                    world.entity_mut(entity).set_parent(new_parent);
                 }
+                None => {
+                    world.entity_mut(entity).remove_parent();
+                }
+            }
+        }
+
+        ProjectEvent::Delete { uuid, recursive } => {
+            let entity = writable_item_entity(world, *uuid)?;
 
-                let entity = entity.id();
+            let mut uuids = Vec::new();
+            collect_item_uuids(world, entity, &mut uuids);
 
-                world
-                    .resource_mut::<ProjectItemRegistry>()
-                    .items
-                    .insert(*uuid, entity);
+            if uuids.len() > 1 && !recursive {
+                return Err(ProjectErrorKind::FolderNotEmpty(*uuid));
             }
 
-            ProjectEvent::LoadScene { scene_uuid } => {
-                let scene_entity = world
-                    .resource::<ProjectItemRegistry>()
-                    .items
-                    .get(scene_uuid)
-                    .unwrap()
-                    .clone();
-
-                let project_item = world
-                    .query::<&ProjectItem>()
-                    .get(world, scene_entity)
-                    .unwrap();
-
-                let dynamic_scene = match &project_item.data {
-                    ProjectItemData::Scene { dynamic_scene } => dynamic_scene.clone(),
-                    _ => panic!(),
-                };
+            ensure_unused(world, &uuids)?;
+
+            despawn_project_items(world, entity);
+        }
+
+        ProjectEvent::DeleteItems { uuids } => {
+            let entities = uuids
+                .iter()
+                .map(|uuid| writable_item_entity(world, *uuid))
+                .collect::<Result<Vec<_>, _>>()?;
 
-                dynamic_scene
-                    .lock()
-                    .unwrap()
-                    .write_to_world(world, &mut EntityMap::default())
-                    .unwrap();
+            // Nothing is deleted unless every item can be
+            let deleted: HashSet<Uuid> = uuids.iter().copied().collect();
+            for (uuid, entity) in uuids.iter().zip(&entities) {
+                let mut descendants = Vec::new();
+                collect_item_uuids(world, *entity, &mut descendants);
+
+                if descendants.iter().any(|uuid| !deleted.contains(uuid)) {
+                    return Err(ProjectErrorKind::FolderNotEmpty(*uuid));
+                }
             }
 
-            ProjectEvent::StoreScene { scene_uuid } => {
-                let scene_entity = world
-                    .resource::<ProjectItemRegistry>()
-                    .items
-                    .get(scene_uuid)
-                    .unwrap()
-                    .clone();
-
-                let mut query = world.query_filtered::<Entity, With<EditorItem>>();
-                let mut dynamic_scene_builder = DynamicSceneBuilder::from_world(world);
-                dynamic_scene_builder.extract_entities(query.iter(world));
-                let updated_dynamic_scene = dynamic_scene_builder.build();
-
-                let project_item = world
-                    .query::<&ProjectItem>()
-                    .get(world, scene_entity)
-                    .unwrap();
-
-                let dynamic_scene = match &project_item.data {
-                    ProjectItemData::Scene { dynamic_scene } => dynamic_scene.clone(),
-                    _ => panic!(),
-                };
+            ensure_unused(world, uuids)?;
 
-                let mut arc_dynamic_scene = dynamic_scene.lock().unwrap();
-                *arc_dynamic_scene = updated_dynamic_scene;
+            for entity in entities {
+                despawn_project_items(world, entity);
             }
         }
+
+        ProjectEvent::Duplicate {
+            uuid,
+            new_parent_uuid,
+        } => {
+            let entity = item_entity(world, *uuid)?;
+
+            duplicate_project_items(world, entity, *new_parent_uuid)?;
+        }
+
+        ProjectEvent::CopyItems { uuids } => {
+            let text = copy_project_items(world, uuids)?;
+            start_clipboard_copy(world, event, text);
+        }
+
+        ProjectEvent::PasteItems { parent_uuid } => {
+            start_clipboard_paste(world, event, *parent_uuid);
+        }
+
+        ProjectEvent::RestoreRecovery => {
+            restore_recovery_snapshot(world)?;
+        }
+
+        ProjectEvent::DiscardRecovery => {
+            discard_recovery_snapshot(world)?;
+        }
+
+        ProjectEvent::SetSource { uuid, source } => {
+            let entity = writable_item_entity(world, *uuid)?;
+
+            let mut project_item = world.get_mut::<ProjectItem>(entity).unwrap();
+
+            *project_item
+                .data
+                .source_mut()
+                .ok_or(ProjectErrorKind::WrongItemKind {
+                    uuid: *uuid,
+                    expected: "asset or scene",
+                })? = Some(source.clone());
+
+            let kind = project_item.kind.clone();
+            kind.reload_source(world, entity, source);
+        }
+
+        ProjectEvent::SetImportSettings { uuid, settings } => {
+            set_import_settings(world, *uuid, settings)?;
+        }
     }
+
+    Ok(())
 }
 
-pub struct ProjectPlugin;
+fn handle_project_events(
+    world: &mut World,
+    project_events_system_state: &mut SystemState<EventReader<ProjectEvent>>,
+) {
+    let project_events: Vec<ProjectEvent> = project_events_system_state
+        .get_mut(world)
+        .iter()
+        .cloned()
+        .collect();
+
+    for event in project_events {
+        if let Err(kind) = handle_project_event(world, &event) {
+            world.send_event(ProjectError { event, kind });
+        }
+    }
+}
+
+fn log_project_errors(mut project_errors: EventReader<ProjectError>) {
+    for project_error in project_errors.iter() {
+        error!("{}", project_error.kind);
+    }
+}
+
+#[derive(Default)]
+pub struct ProjectPlugin {
+    /// Mirrors this directory, relative to the asset root, into the project tree.
+    ///
+    /// Uuids of the mirrored files are kept in `.meta` files next to them, while folders are
+    /// matched with the project's by name.
+    pub sync_assets: Option<PathBuf>,
+    /// Periodically snapshots unsaved work into this directory, offering to restore it after
+    /// the editor crashes.
+    pub autosave: Option<PathBuf>,
+}
 
 impl Plugin for ProjectPlugin {
     fn build(&self, app: &mut App) {
+        if let Some(directory) = &self.sync_assets {
+            app.insert_resource(AssetSync::new(directory.clone()))
+                .add_systems(Update, sync_project_assets.after(handle_project_events));
+        }
+
+        if let Some(directory) = &self.autosave {
+            app.insert_resource(Autosave::new(directory.clone()))
+                .add_systems(Startup, detect_recovery_snapshot)
+                .add_systems(Update, autosave.after(update_unsaved_changes))
+                .add_systems(Last, remove_snapshot_on_exit);
+        }
+
         app.insert_resource(ProjectItemRegistry::default())
+            .insert_resource(OpenScene::default())
+            .insert_resource(ProjectDependencies::default())
+            .insert_resource(ProjectSearch::default())
+            .insert_resource(Thumbnails::default())
+            .insert_resource(ClipboardTasks::default())
+            .insert_resource(UnsavedChanges::default())
+            .init_resource::<ManifestMigrations>()
+            .register_project_item_kind(FolderKind)
+            .register_project_item_kind(MaterialKind)
+            .register_project_item_kind(ImageKind)
+            .register_project_item_kind(MeshKind)
+            .register_project_item_kind(SceneKind)
+            .register_project_item_kind(PrefabKind)
+            .register_type::<Uuid>()
+            .register_type::<EditorId>()
+            .register_type::<PrefabEntity>()
+            .register_type::<PrefabInstance>()
             .add_event::<ProjectEvent>()
-            .add_systems(Update, handle_project_events);
+            .add_event::<ProjectError>()
+            .add_systems(
+                Update,
+                (
+                    handle_project_events,
+                    finish_clipboard_tasks,
+                    (
+                        update_source_load_states::<StandardMaterial>,
+                        update_source_load_states::<Image>,
+                        update_source_load_states::<Mesh>,
+                        apply_asset_overrides::<StandardMaterial>,
+                        apply_asset_overrides::<Image>,
+                        apply_asset_overrides::<Mesh>,
+                    ),
+                    update_material_dependencies,
+                    (
+                        queue_thumbnails,
+                        render_thumbnails,
+                        despawn_thumbnail_stages,
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_project_search.after(handle_project_events),
+                    log_project_errors.after(handle_project_events),
+                    assign_editor_ids.after(handle_project_events),
+                    update_unsaved_changes.after(assign_editor_ids),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The render device is only there once the render plugin is built
+        if app.world.contains_resource::<RenderDevice>() {
+            app.world.resource_mut::<Thumbnails>().render_on_gpu = true;
+        }
+    }
+}
+
+/// Builds an app holding an empty project, which writes its files to a new temporary directory.
+#[cfg(test)]
+fn test_project_app() -> App {
+    let asset_folder = std::env::temp_dir().join(format!("makeshift-test-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&asset_folder).unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: asset_folder.to_string_lossy().into_owned(),
+            ..default()
+        })
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(ImagePlugin::default())
+        .add_plugin(bevy::scene::ScenePlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<StandardMaterial>()
+        .register_asset_reflect::<StandardMaterial>()
+        .add_plugin(ProjectPlugin::default());
+
+    app.finish();
+    app.cleanup();
+
+    app
+}
+
+/// Applies `event` to the project of a [`test_project_app`], panicking if it fails.
+#[cfg(test)]
+fn send_test_event(app: &mut App, event: ProjectEvent) {
+    app.world.send_event(event);
+    app.update();
+
+    let project_errors = app.world.resource::<Events<ProjectError>>();
+    if let Some(project_error) = project_errors.get_reader().iter(project_errors).next() {
+        panic!("{}", project_error.kind);
     }
 }
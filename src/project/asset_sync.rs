@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bevy::asset::LoadState;
+use bevy::gltf::{Gltf, GltfMesh};
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use bevy::utils::{HashMap, HashSet};
+use futures_lite::future;
+use uuid::Uuid;
+
+use super::kind::{FolderKind, MeshKind, ProjectItemKinds};
+use super::meta::AssetMeta;
+use super::{
+    asset_root_path, ProjectEvent, ProjectItem, ProjectItemData, ProjectItemKind,
+    ProjectItemRegistry,
+};
+
+const SYNC_INTERVAL_SECONDS: f32 = 1.0;
+
+#[derive(Resource)]
+pub struct AssetSync {
+    directory: PathBuf,
+    timer: Timer,
+    scanned: bool,
+    /// The directory is walked on the IO task pool, and synced once that's done.
+    scan: Option<Task<BTreeMap<PathBuf, ScannedEntry>>>,
+    entries: HashMap<PathBuf, SyncedEntry>,
+    /// glTF files, kept loaded to list the meshes they hold.
+    models: HashMap<PathBuf, Handle<Gltf>>,
+}
+
+impl AssetSync {
+    pub fn new(directory: PathBuf) -> Self {
+        AssetSync {
+            directory,
+            timer: Timer::from_seconds(SYNC_INTERVAL_SECONDS, TimerMode::Repeating),
+            scanned: false,
+            scan: None,
+            entries: default(),
+            models: default(),
+        }
+    }
+}
+
+struct SyncedEntry {
+    uuid: Uuid,
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+    /// Uuids of the mesh items of a glTF file, by their label.
+    labels: BTreeMap<String, Uuid>,
+}
+
+struct ScannedEntry {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+fn item_kind(kinds: &ProjectItemKinds, path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?;
+
+    kinds.for_extension(extension).map(|kind| kind.name())
+}
+
+fn asset_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn scan_directory(root: &Path, directory: &Path, entries: &mut BTreeMap<PathBuf, ScannedEntry>) {
+    let read_dir = match fs::read_dir(root.join(directory)) {
+        Ok(read_dir) => read_dir,
+        Err(err) => {
+            warn!("Failed to scan {}: {}", root.join(directory).display(), err);
+            return;
+        }
+    };
+
+    for dir_entry in read_dir.flatten() {
+        let file_name = dir_entry.file_name();
+        let path = directory.join(&file_name);
+
+        if file_name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let Ok(metadata) = dir_entry.metadata() else {
+            continue;
+        };
+
+        entries.insert(
+            path.clone(),
+            ScannedEntry {
+                is_dir: metadata.is_dir(),
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            },
+        );
+
+        if metadata.is_dir() {
+            scan_directory(root, &path, entries);
+        }
+    }
+}
+
+fn write_meta(root: &Path, path: &Path, meta: AssetMeta) {
+    if let Err(err) = meta.save(&root.join(path)) {
+        warn!(
+            "Failed to write meta file for {}: {}",
+            root.join(path).display(),
+            err
+        );
+    }
+}
+
+/// Whether the folder at `path` is the one previously at `old_path`, as the meta files of its
+/// contents moved along with it.
+fn is_moved_folder(
+    root: &Path,
+    path: &Path,
+    old_path: &Path,
+    previous_entries: &HashMap<PathBuf, SyncedEntry>,
+) -> bool {
+    previous_entries
+        .iter()
+        .filter(|(child_path, _)| child_path.parent() == Some(old_path))
+        .any(|(child_path, child)| {
+            child_path
+                .file_name()
+                .and_then(|file_name| AssetMeta::load(&root.join(path).join(file_name)))
+                .is_some_and(|meta| meta.uuid == child.uuid)
+        })
+}
+
+/// Finds the uuid of a newly scanned path, and the uuids of its labeled meshes.
+///
+/// Files keep theirs in a meta file, while folders don't have one: they're found from what
+/// disappeared in the same scan (i.e. it was renamed without its meta file), a file with the
+/// same size and modification time or a folder with the same contents, and otherwise folders
+/// are matched with the project's folder of the same name.
+fn resolve_uuid(
+    root: &Path,
+    path: &Path,
+    entry: &ScannedEntry,
+    existing_folder: Option<Uuid>,
+    previous_entries: &HashMap<PathBuf, SyncedEntry>,
+    renamed_candidates: &mut Vec<(&Path, &SyncedEntry)>,
+    seen_uuids: &HashSet<Uuid>,
+) -> (Uuid, BTreeMap<String, Uuid>) {
+    let meta = if entry.is_dir {
+        None
+    } else {
+        AssetMeta::load(&root.join(path))
+    };
+
+    if let Some(meta) = &meta {
+        if !seen_uuids.contains(&meta.uuid) {
+            return (meta.uuid, meta.labels.clone());
+        }
+    } else {
+        let renamed = renamed_candidates.iter().position(|(old_path, candidate)| {
+            let is_same = if entry.is_dir {
+                candidate.is_dir && is_moved_folder(root, path, old_path, previous_entries)
+            } else {
+                !candidate.is_dir
+                    && entry.modified.is_some()
+                    && candidate.len == entry.len
+                    && candidate.modified == entry.modified
+            };
+            is_same && !seen_uuids.contains(&candidate.uuid)
+        });
+
+        if let Some(index) = renamed {
+            let (old_path, candidate) = renamed_candidates.remove(index);
+
+            if !entry.is_dir {
+                let old_meta = AssetMeta::load(&root.join(old_path));
+                write_meta(
+                    root,
+                    path,
+                    AssetMeta {
+                        uuid: candidate.uuid,
+                        import_settings: old_meta.and_then(|old_meta| old_meta.import_settings),
+                        labels: candidate.labels.clone(),
+                    },
+                );
+                let _ = fs::remove_file(AssetMeta::path(&root.join(old_path)));
+            }
+            return (candidate.uuid, candidate.labels.clone());
+        }
+
+        if let Some(uuid) = existing_folder.filter(|uuid| !seen_uuids.contains(uuid)) {
+            return (uuid, BTreeMap::new());
+        }
+    }
+
+    // Either a new asset, or a copy of another one along with its meta file
+    let uuid = Uuid::new_v4();
+    if !entry.is_dir {
+        write_meta(
+            root,
+            path,
+            AssetMeta {
+                uuid,
+                import_settings: meta.and_then(|meta| meta.import_settings),
+                labels: BTreeMap::new(),
+            },
+        );
+    }
+    (uuid, BTreeMap::new())
+}
+
+/// Labels of the meshes of a loaded glTF file, one per primitive, as bevy's glTF loader
+/// names them.
+fn model_mesh_labels(
+    gltfs: &Assets<Gltf>,
+    gltf_meshes: &Assets<GltfMesh>,
+    handle: &Handle<Gltf>,
+) -> Option<Vec<String>> {
+    let gltf = gltfs.get(handle)?;
+    let mut labels = Vec::new();
+
+    for (mesh_index, mesh) in gltf.meshes.iter().enumerate() {
+        let gltf_mesh = gltf_meshes.get(mesh)?;
+
+        for primitive_index in 0..gltf_mesh.primitives.len() {
+            labels.push(format!("Mesh{}/Primitive{}", mesh_index, primitive_index));
+        }
+    }
+
+    Some(labels)
+}
+
+/// The project item mirroring a file, or one of the assets labeled within it.
+struct MirroredItem {
+    uuid: Uuid,
+    name: String,
+    parent_uuid: Option<Uuid>,
+    kind: &'static str,
+    source: Option<String>,
+}
+
+/// Sends the events that create or update the project item mirroring `mirrored`.
+fn sync_item(
+    registry: &ProjectItemRegistry,
+    items: &Query<(&ProjectItem, Option<&Parent>)>,
+    project_events: &mut EventWriter<ProjectEvent>,
+    mirrored: MirroredItem,
+) {
+    let MirroredItem {
+        uuid,
+        name,
+        parent_uuid,
+        kind,
+        source,
+    } = mirrored;
+
+    match registry
+        .items
+        .get(&uuid)
+        .and_then(|entity| items.get(*entity).ok())
+    {
+        Some((project_item, parent)) => {
+            if project_item.name != name {
+                project_events.send(ProjectEvent::Rename { uuid, name });
+            }
+
+            let current_parent_uuid = parent
+                .and_then(|parent| items.get(parent.get()).ok())
+                .map(|(parent_item, _)| parent_item.uuid);
+
+            if current_parent_uuid != parent_uuid {
+                project_events.send(ProjectEvent::Move {
+                    uuid,
+                    new_parent_uuid: parent_uuid,
+                });
+            }
+
+            if let Some(source) = source {
+                if project_item.data.source() != Some(&source) {
+                    project_events.send(ProjectEvent::SetSource { uuid, source });
+                }
+            }
+        }
+        None => {
+            project_events.send(ProjectEvent::Create {
+                uuid,
+                name,
+                parent_uuid,
+                kind: kind.to_string(),
+                source,
+            });
+        }
+    }
+}
+
+/// Mirrors the meshes of the glTF file at `path` as mesh items inside of its own item.
+fn sync_model_meshes(
+    root: &Path,
+    path: &Path,
+    entry: &mut SyncedEntry,
+    labels: &[String],
+    registry: &ProjectItemRegistry,
+    items: &Query<(&ProjectItem, Option<&Parent>)>,
+    project_events: &mut EventWriter<ProjectEvent>,
+) {
+    let mut changed = false;
+
+    for label in labels {
+        let uuid = *entry.labels.entry(label.clone()).or_insert_with(|| {
+            changed = true;
+            Uuid::new_v4()
+        });
+        let source = format!("{}#{}", asset_path(path), label);
+
+        sync_item(
+            registry,
+            items,
+            project_events,
+            MirroredItem {
+                uuid,
+                name: label.clone(),
+                parent_uuid: Some(entry.uuid),
+                kind: MeshKind.name(),
+                source: Some(source),
+            },
+        );
+    }
+
+    entry.labels.retain(|label, uuid| {
+        let exists = labels.contains(label);
+        if !exists {
+            if registry.items.contains_key(uuid) {
+                project_events.send(ProjectEvent::Delete {
+                    uuid: *uuid,
+                    recursive: true,
+                });
+            }
+            changed = true;
+        }
+        exists
+    });
+
+    if changed {
+        let meta = AssetMeta::load(&root.join(path));
+        write_meta(
+            root,
+            path,
+            AssetMeta {
+                uuid: entry.uuid,
+                import_settings: meta.and_then(|meta| meta.import_settings),
+                labels: entry.labels.clone(),
+            },
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sync_project_assets(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    registry: Res<ProjectItemRegistry>,
+    kinds: Res<ProjectItemKinds>,
+    gltfs: Res<Assets<Gltf>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    items: Query<(&ProjectItem, Option<&Parent>)>,
+    mut asset_sync: ResMut<AssetSync>,
+    mut project_events: EventWriter<ProjectEvent>,
+) {
+    let root = asset_root_path(&asset_server);
+
+    if asset_sync.scan.is_none() {
+        if asset_sync.scanned && !asset_sync.timer.tick(time.delta()).just_finished() {
+            return;
+        }
+        asset_sync.scanned = true;
+
+        let scan_root = root.clone();
+        let directory = asset_sync.directory.clone();
+        asset_sync.scan = Some(IoTaskPool::get().spawn(async move {
+            let mut scanned = BTreeMap::new();
+            scan_directory(&scan_root, &directory, &mut scanned);
+            scanned
+        }));
+    }
+
+    let Some(scanned) = asset_sync
+        .scan
+        .as_mut()
+        .and_then(|scan| future::block_on(future::poll_once(scan)))
+    else {
+        return;
+    };
+    asset_sync.scan = None;
+
+    let scanned: BTreeMap<PathBuf, (ScannedEntry, &'static str)> = scanned
+        .into_iter()
+        .filter_map(|(path, entry)| {
+            let kind = if entry.is_dir {
+                FolderKind.name()
+            } else {
+                item_kind(&kinds, &path)?
+            };
+            Some((path, (entry, kind)))
+        })
+        .collect();
+
+    // Folders have no meta file, so the project's own are matched by their name and parent
+    let existing_folders: HashMap<(Option<Uuid>, String), Uuid> = items
+        .iter()
+        .filter(|(project_item, _)| matches!(project_item.data, ProjectItemData::Folder))
+        .map(|(project_item, parent)| {
+            let parent_uuid = parent
+                .and_then(|parent| items.get(parent.get()).ok())
+                .map(|(parent_item, _)| parent_item.uuid);
+            ((parent_uuid, project_item.name.clone()), project_item.uuid)
+        })
+        .collect();
+
+    let previous_entries = std::mem::take(&mut asset_sync.entries);
+
+    let mut renamed_candidates: Vec<_> = previous_entries
+        .iter()
+        .filter(|(path, _)| !scanned.contains_key(*path))
+        .map(|(path, entry)| (path.as_path(), entry))
+        .collect();
+
+    let mut entries: HashMap<PathBuf, SyncedEntry> = HashMap::default();
+    let mut seen_uuids = HashSet::default();
+
+    // Paths are visited in order, so folders are always handled before their contents
+    for (path, (entry, kind)) in scanned {
+        let name = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parent_uuid = path
+            .parent()
+            .and_then(|parent| entries.get(parent))
+            .map(|parent| parent.uuid);
+
+        // A folder whose item is gone, such as after loading another project, is matched by
+        // name again, as it has no meta file to recreate its item from
+        let (uuid, labels) = match previous_entries.get(&path) {
+            Some(previous_entry)
+                if previous_entry.is_dir == entry.is_dir
+                    && !seen_uuids.contains(&previous_entry.uuid)
+                    && (!entry.is_dir || registry.items.contains_key(&previous_entry.uuid)) =>
+            {
+                (previous_entry.uuid, previous_entry.labels.clone())
+            }
+            _ => resolve_uuid(
+                &root,
+                &path,
+                &entry,
+                existing_folders.get(&(parent_uuid, name.clone())).copied(),
+                &previous_entries,
+                &mut renamed_candidates,
+                &seen_uuids,
+            ),
+        };
+
+        // glTF files become a folder of the meshes they hold
+        let (item_kind, source) = if kind == MeshKind.name() {
+            (FolderKind.name(), None)
+        } else if kind == FolderKind.name() {
+            (kind, None)
+        } else {
+            (kind, Some(asset_path(&path)))
+        };
+
+        sync_item(
+            &registry,
+            &items,
+            &mut project_events,
+            MirroredItem {
+                uuid,
+                name,
+                parent_uuid,
+                kind: item_kind,
+                source,
+            },
+        );
+
+        if kind == MeshKind.name() && !asset_sync.models.contains_key(&path) {
+            let handle = asset_server.load(path.as_path());
+            asset_sync.models.insert(path.clone(), handle);
+        }
+
+        seen_uuids.insert(uuid);
+        entries.insert(
+            path,
+            SyncedEntry {
+                uuid,
+                is_dir: entry.is_dir,
+                len: entry.len,
+                modified: entry.modified,
+                labels,
+            },
+        );
+    }
+
+    asset_sync
+        .models
+        .retain(|path, _| entries.contains_key(path));
+
+    for (path, handle) in &asset_sync.models {
+        let Some(entry) = entries.get_mut(path) else {
+            continue;
+        };
+
+        let Some(labels) = model_mesh_labels(&gltfs, &gltf_meshes, handle) else {
+            // The file was already loaded for the sources of its mesh items, and its glTF asset
+            // freed since then
+            if asset_server.get_load_state(handle) == LoadState::Loaded {
+                asset_server.reload_asset(path.as_path());
+            }
+            continue;
+        };
+
+        sync_model_meshes(
+            &root,
+            path,
+            entry,
+            &labels,
+            &registry,
+            &items,
+            &mut project_events,
+        );
+    }
+
+    for (path, previous_entry) in &previous_entries {
+        if seen_uuids.contains(&previous_entry.uuid)
+            || !registry.items.contains_key(&previous_entry.uuid)
+        {
+            continue;
+        }
+
+        // Deleting a folder already takes its contents along with it
+        let parent_removed = path
+            .parent()
+            .and_then(|parent| previous_entries.get(parent))
+            .is_some_and(|parent| !seen_uuids.contains(&parent.uuid));
+
+        if !parent_removed {
+            project_events.send(ProjectEvent::Delete {
+                uuid: previous_entry.uuid,
+                recursive: true,
+            });
+
+            // The meta files of a folder's contents went along with it, but a file leaves its
+            // own behind
+            if !previous_entry.is_dir {
+                let _ = fs::remove_file(AssetMeta::path(&root.join(path)));
+            }
+        }
+    }
+
+    asset_sync.entries = entries;
+}
@@ -0,0 +1,777 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use bevy::asset::HandleId;
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use bevy::utils::HashMap;
+use futures_lite::future;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::library::writable_item_entity;
+use super::manifest::{ProjectManifest, PROJECT_MANIFEST_VERSION};
+use super::meta::AssetMeta;
+use super::migration::parse_project_manifest;
+use super::overrides::derived_handle_id_of_type;
+use super::prefab::is_prefab;
+use super::scene_file::deserialize_scene;
+use super::{
+    asset_root_path, collect_manifest_items, folder_entity, is_descendant_of, item_entity,
+    read_scene_if_needed, reflect_owned_as_reflect_mut, scene_item_data, sibling_names,
+    spawn_manifest_items, unused_copy_name, validate_project_manifest, visit_reflect_mut,
+    ProjectError, ProjectErrorKind, ProjectEvent, ProjectItemKinds,
+};
+
+const CLIPBOARD_FORMAT: &str = "makeshift-project-items";
+
+/// Project items copied to the clipboard, along with the files they're read from.
+#[derive(Serialize, Deserialize)]
+struct ClipboardContents {
+    format: String,
+    /// A project manifest holding only the copied items.
+    manifest: String,
+    /// Files by their path relative to the asset root.
+    #[serde(default)]
+    files: BTreeMap<String, ClipboardFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum ClipboardFile {
+    Scene(String),
+    Asset {
+        /// Base64 encoded contents.
+        data: String,
+        #[serde(default)]
+        meta: Option<AssetMeta>,
+    },
+}
+
+/// Serializes the items `uuids`, their descendants and the files they're read from into text
+/// that [`paste_project_items`] can add to another project.
+///
+/// Only the source file of an asset is copied, so sources that load other files, such as glTF
+/// files with external buffers, need those files to already exist in the other project.
+pub fn copy_project_items(world: &mut World, uuids: &[Uuid]) -> Result<String, ProjectErrorKind> {
+    let mut entities = Vec::new();
+    for uuid in uuids {
+        let entity = item_entity(world, *uuid)?;
+        if !entities.contains(&entity) {
+            entities.push(entity);
+        }
+    }
+
+    // Items inside a copied folder are copied along with it
+    let roots: Vec<Entity> = entities
+        .iter()
+        .copied()
+        .filter(|entity| {
+            !entities
+                .iter()
+                .any(|ancestor| is_descendant_of(world, *entity, *ancestor))
+        })
+        .collect();
+
+    if roots.is_empty() {
+        return Err(ProjectErrorKind::Clipboard(
+            "no project items to copy".to_string(),
+        ));
+    }
+
+    let mut items = Vec::new();
+    for root in roots {
+        collect_manifest_items(world, root, None, &mut items);
+    }
+
+    let asset_root = asset_root_path(world.resource::<AssetServer>());
+    let mut files = BTreeMap::new();
+
+    for item in &mut items {
+        if let Ok((_, source, dynamic_scene)) = scene_item_data(world, item.uuid) {
+            let mut dynamic_scene = dynamic_scene
+                .lock()
+                .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(item.uuid))?;
+            read_scene_if_needed(world, item.uuid, source.clone(), &mut dynamic_scene)?;
+
+            let serialized = dynamic_scene
+                .as_ref()
+                .unwrap()
+                .serialize_ron(world.resource::<AppTypeRegistry>())
+                .map_err(|err| {
+                    ProjectErrorKind::Clipboard(format!(
+                        "failed to serialize scene {}: {}",
+                        item.uuid, err
+                    ))
+                })?;
+
+            // Scenes that were never stored get the path they would have been stored at
+            let source = source.unwrap_or_else(|| {
+                let directory = if is_prefab(world, item.uuid) {
+                    "prefabs"
+                } else {
+                    "scenes"
+                };
+                format!("{}/{}.scn.ron", directory, item.uuid)
+            });
+            item.source = Some(source.clone());
+            files.insert(source, ClipboardFile::Scene(serialized));
+        } else if let Some(source) = &item.source {
+            let file = source_file(source);
+            if files.contains_key(file) {
+                continue;
+            }
+
+            let path = asset_root.join(file);
+            let data =
+                fs::read(&path).map_err(|err| ProjectErrorKind::AssetRead(path.clone(), err))?;
+
+            files.insert(
+                file.to_string(),
+                ClipboardFile::Asset {
+                    data: encode_base64(&data),
+                    meta: AssetMeta::load(&path),
+                },
+            );
+        }
+    }
+
+    let manifest = ProjectManifest {
+        version: PROJECT_MANIFEST_VERSION,
+        items,
+        libraries: Vec::new(),
+    };
+
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+    let manifest = manifest
+        .serialize_ron(&type_registry)
+        .map_err(|err| ProjectErrorKind::Clipboard(err.to_string()))?;
+
+    let contents = ClipboardContents {
+        format: CLIPBOARD_FORMAT.to_string(),
+        manifest,
+        files,
+    };
+
+    ron::ser::to_string_pretty(&contents, PrettyConfig::default())
+        .map_err(|err| ProjectErrorKind::Clipboard(err.to_string()))
+}
+
+/// Adds items copied by [`copy_project_items`] under the folder `parent_uuid`, returning the
+/// uuids of the new top-level items.
+///
+/// Pasted items get new uuids, and references between them, in scenes, overrides and
+/// properties, are updated to match. Files are written next to the original paths when those
+/// are already taken, and top-level items named like an existing sibling are renamed.
+pub fn paste_project_items(
+    world: &mut World,
+    text: &str,
+    parent_uuid: Option<Uuid>,
+) -> Result<Vec<Uuid>, ProjectErrorKind> {
+    let parent = match parent_uuid {
+        Some(parent_uuid) => {
+            writable_item_entity(world, parent_uuid)?;
+            Some(folder_entity(world, parent_uuid)?)
+        }
+        None => None,
+    };
+
+    let contents: ClipboardContents = ron::from_str(text)
+        .ok()
+        .filter(|contents: &ClipboardContents| contents.format == CLIPBOARD_FORMAT)
+        .ok_or_else(|| {
+            ProjectErrorKind::Clipboard("the clipboard doesn't hold project items".to_string())
+        })?;
+
+    let (mut manifest, _) = parse_project_manifest(world, &contents.manifest)
+        .map_err(|err| ProjectErrorKind::Clipboard(err.to_string()))?;
+    validate_project_manifest(&manifest, world.resource::<ProjectItemKinds>())?;
+
+    let kinds = world.resource::<ProjectItemKinds>();
+    let remap = ItemRemap::new(manifest.items.iter().map(|item| {
+        let asset_type_uuid = kinds
+            .get(&item.kind)
+            .and_then(|kind| kind.asset_type_uuid());
+        (item.uuid, asset_type_uuid)
+    }));
+
+    let asset_root = asset_root_path(world.resource::<AssetServer>());
+    let mut written = Vec::new();
+    let written_files =
+        write_pasted_files(world, &asset_root, contents.files, &remap, &mut written);
+    let sources = match written_files {
+        Ok(sources) => sources,
+        Err(err) => {
+            remove_files(&written);
+            return Err(err);
+        }
+    };
+
+    let mut names = sibling_names(world, parent);
+    let mut pasted = Vec::new();
+
+    for item in &mut manifest.items {
+        item.uuid = remap.uuids[&item.uuid];
+        item.parent_uuid = item
+            .parent_uuid
+            .map(|parent_uuid| remap.uuids[&parent_uuid]);
+
+        if let Some(source) = &mut item.source {
+            let (file, label) = match source.split_once('#') {
+                Some((file, label)) => (file, Some(label)),
+                None => (source.as_str(), None),
+            };
+
+            if let Some(file) = sources.get(file) {
+                *source = match label {
+                    Some(label) => format!("{}#{}", file, label),
+                    None => file.clone(),
+                };
+            }
+        }
+
+        for value in item.overrides.values_mut() {
+            remap.apply(reflect_owned_as_reflect_mut(value));
+        }
+
+        if let Some(properties) = &mut item.properties {
+            remap.apply(properties.as_mut());
+        }
+
+        if item.parent_uuid.is_none() {
+            if names.contains(&item.name) {
+                item.name = unused_copy_name(&names, &item.name);
+            }
+            names.insert(item.name.clone());
+            pasted.push(item.uuid);
+        }
+    }
+
+    if let Err(err) = spawn_manifest_items(world, manifest.items, parent) {
+        remove_files(&written);
+        return Err(err);
+    }
+
+    Ok(pasted)
+}
+
+/// Writes the copied files under the asset root, returning where each one ended up.
+fn write_pasted_files(
+    world: &World,
+    asset_root: &Path,
+    files: BTreeMap<String, ClipboardFile>,
+    remap: &ItemRemap,
+    written: &mut Vec<PathBuf>,
+) -> Result<HashMap<String, String>, ProjectErrorKind> {
+    let type_registry = world.resource::<AppTypeRegistry>();
+    let mut sources = HashMap::default();
+
+    for (file, contents) in files {
+        // Scenes are written by the editor, so they never share a file with another item
+        let reuse_identical = matches!(contents, ClipboardFile::Asset { .. });
+
+        let (data, meta) = match contents {
+            ClipboardFile::Scene(serialized) => {
+                let mut dynamic_scene = deserialize_scene(&serialized, type_registry)
+                    .map_err(|err| ProjectErrorKind::Clipboard(format!("{}: {}", file, err)))?;
+                remap.apply_scene(&mut dynamic_scene);
+
+                let serialized = dynamic_scene
+                    .serialize_ron(type_registry)
+                    .map_err(|err| ProjectErrorKind::Clipboard(format!("{}: {}", file, err)))?;
+                (serialized.into_bytes(), None)
+            }
+            ClipboardFile::Asset { data, meta } => {
+                let data = decode_base64(&data).ok_or_else(|| {
+                    ProjectErrorKind::Clipboard(format!("{}: invalid base64 data", file))
+                })?;
+                (data, meta)
+            }
+        };
+
+        let target = paste_path(asset_root, &file, &data, reuse_identical);
+        let path = asset_root.join(&target);
+
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|err| ProjectErrorKind::AssetWrite(path.clone(), err))?;
+            }
+            fs::write(&path, &data)
+                .map_err(|err| ProjectErrorKind::AssetWrite(path.clone(), err))?;
+            written.push(path.clone());
+
+            if let Some(mut meta) = meta {
+                meta.uuid = remap.uuids.get(&meta.uuid).copied().unwrap_or(meta.uuid);
+                for uuid in meta.labels.values_mut() {
+                    *uuid = remap.uuids.get(uuid).copied().unwrap_or(*uuid);
+                }
+                meta.save(&path)
+                    .map_err(|err| ProjectErrorKind::MetaWrite(AssetMeta::path(&path), err))?;
+                written.push(AssetMeta::path(&path));
+            }
+        }
+
+        sources.insert(file, target);
+    }
+
+    Ok(sources)
+}
+
+fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(err) = fs::remove_file(path) {
+            warn!("Failed to remove pasted file {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn source_file(source: &str) -> &str {
+    source.split_once('#').map_or(source, |(file, _)| file)
+}
+
+/// Picks a free path for a pasted file, numbering it when `file` is taken by other contents.
+fn paste_path(asset_root: &Path, file: &str, data: &[u8], reuse_identical: bool) -> String {
+    let name_start = file.rfind('/').map_or(0, |index| index + 1);
+    let extension_start = file[name_start..]
+        .find('.')
+        .map_or(file.len(), |index| name_start + index);
+
+    let mut target = file.to_string();
+    let mut counter = 2;
+
+    loop {
+        match fs::read(asset_root.join(&target)) {
+            Ok(existing) if reuse_identical && existing == data => return target,
+            Ok(_) => {}
+            Err(_) => return target,
+        }
+
+        target = format!(
+            "{}-{}{}",
+            &file[..extension_start],
+            counter,
+            &file[extension_start..]
+        );
+        counter += 1;
+    }
+}
+
+/// Maps the uuids of copied items, and the handles derived from them, to those of the pasted
+/// items.
+struct ItemRemap {
+    uuids: HashMap<Uuid, Uuid>,
+    handle_ids: HashMap<HandleId, HandleId>,
+}
+
+impl ItemRemap {
+    /// `items` are the uuids of the copied items, along with the type uuid of their asset.
+    fn new(items: impl Iterator<Item = (Uuid, Option<Uuid>)>) -> Self {
+        let mut uuids = HashMap::default();
+        let mut handle_ids = HashMap::default();
+
+        for (uuid, asset_type_uuid) in items {
+            let new_uuid = Uuid::new_v4();
+            uuids.insert(uuid, new_uuid);
+
+            if let Some(type_uuid) = asset_type_uuid {
+                handle_ids.insert(
+                    derived_handle_id_of_type(type_uuid, uuid),
+                    derived_handle_id_of_type(type_uuid, new_uuid),
+                );
+            }
+        }
+
+        ItemRemap { uuids, handle_ids }
+    }
+
+    fn apply_scene(&self, dynamic_scene: &mut DynamicScene) {
+        for entity in &mut dynamic_scene.entities {
+            for component in &mut entity.components {
+                self.apply(component.as_mut());
+            }
+        }
+
+        for resource in &mut dynamic_scene.resources {
+            self.apply(resource.as_mut());
+        }
+    }
+
+    fn apply(&self, value: &mut dyn Reflect) {
+        visit_reflect_mut(value, &mut |value| {
+            if let Some(uuid) = value.downcast_mut::<Uuid>() {
+                if let Some(new_uuid) = self.uuids.get(uuid) {
+                    *uuid = *new_uuid;
+                }
+                return true;
+            }
+
+            if let Some(handle_id) = value.downcast_mut::<HandleId>() {
+                if let Some(new_handle_id) = self.handle_ids.get(handle_id) {
+                    *handle_id = *new_handle_id;
+                }
+                return true;
+            }
+
+            false
+        });
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (bits >> (18 - 6 * index)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes padded base64, rejecting anything [`encode_base64`] wouldn't have written.
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let padding = encoded.iter().rev().take_while(|c| **c == b'=').count();
+    if padding > 2 {
+        return None;
+    }
+
+    let encoded = &encoded[..encoded.len() - padding];
+    let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+
+    for byte in encoded {
+        let sextet = BASE64_ALPHABET.iter().position(|c| c == byte)? as u32;
+        bits = bits << 6 | sextet;
+        bit_count += 6;
+
+        if bit_count >= 8 {
+            bit_count -= 8;
+            data.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+
+    // The bits left over before the padding are always zero when encoding
+    if bits != 0 {
+        return None;
+    }
+
+    Some(data)
+}
+
+/// Commands that write the system clipboard from their standard input, in order of preference.
+fn clipboard_write_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-Command",
+                "Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+            ],
+        )]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    }
+}
+
+/// Commands that print the system clipboard, in order of preference.
+fn clipboard_read_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else if cfg!(windows) {
+        &[(
+            "powershell",
+            &["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+        )]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    }
+}
+
+fn set_system_clipboard(text: &str) -> Result<(), ProjectErrorKind> {
+    let mut errors = Vec::new();
+
+    for (program, args) in clipboard_write_commands() {
+        let mut child = match Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) => {
+                errors.push(format!("{}: {}", program, err));
+                continue;
+            }
+        };
+
+        let written = child.stdin.take().unwrap().write_all(text.as_bytes());
+        match (written, child.wait()) {
+            (Ok(()), Ok(status)) if status.success() => return Ok(()),
+            (Err(err), _) | (_, Err(err)) => errors.push(format!("{}: {}", program, err)),
+            (_, Ok(status)) => errors.push(format!("{}: {}", program, status)),
+        }
+    }
+
+    Err(ProjectErrorKind::Clipboard(format!(
+        "failed to copy ({})",
+        errors.join(", ")
+    )))
+}
+
+fn system_clipboard() -> Result<String, ProjectErrorKind> {
+    let mut errors = Vec::new();
+
+    for (program, args) in clipboard_read_commands() {
+        match Command::new(program)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                return String::from_utf8(output.stdout)
+                    .map_err(|err| ProjectErrorKind::Clipboard(err.to_string()));
+            }
+            Ok(output) => errors.push(format!("{}: {}", program, output.status)),
+            Err(err) => errors.push(format!("{}: {}", program, err)),
+        }
+    }
+
+    Err(ProjectErrorKind::Clipboard(format!(
+        "failed to paste ({})",
+        errors.join(", ")
+    )))
+}
+
+enum ClipboardTask {
+    Copy(Task<Result<(), ProjectErrorKind>>),
+    Paste {
+        parent_uuid: Option<Uuid>,
+        task: Task<Result<String, ProjectErrorKind>>,
+    },
+}
+
+/// Reads and writes of the system clipboard, which run on the IO task pool, as the commands
+/// doing them can take a while, or even wait for the user.
+#[derive(Resource, Default)]
+pub struct ClipboardTasks {
+    tasks: Vec<(ProjectEvent, ClipboardTask)>,
+}
+
+/// Writes `text` to the system clipboard in the background, reporting failures for `event`.
+pub(super) fn start_clipboard_copy(world: &mut World, event: &ProjectEvent, text: String) {
+    let task = IoTaskPool::get().spawn(async move { set_system_clipboard(&text) });
+
+    world
+        .resource_mut::<ClipboardTasks>()
+        .tasks
+        .push((event.clone(), ClipboardTask::Copy(task)));
+}
+
+/// Reads the system clipboard in the background, to paste it under `parent_uuid` once read.
+pub(super) fn start_clipboard_paste(
+    world: &mut World,
+    event: &ProjectEvent,
+    parent_uuid: Option<Uuid>,
+) {
+    let task = IoTaskPool::get().spawn(async { system_clipboard() });
+
+    world
+        .resource_mut::<ClipboardTasks>()
+        .tasks
+        .push((event.clone(), ClipboardTask::Paste { parent_uuid, task }));
+}
+
+/// Pastes what was read from the clipboard, and reports clipboard tasks that failed.
+pub(super) fn finish_clipboard_tasks(world: &mut World) {
+    let tasks = std::mem::take(&mut world.resource_mut::<ClipboardTasks>().tasks);
+
+    for (event, mut task) in tasks {
+        let result = match &mut task {
+            ClipboardTask::Copy(task) => future::block_on(future::poll_once(task)),
+            ClipboardTask::Paste { parent_uuid, task } => future::block_on(future::poll_once(task))
+                .map(|text| {
+                    text.and_then(|text| paste_project_items(world, &text, *parent_uuid))
+                        .map(|_| ())
+                }),
+        };
+
+        let Some(result) = result else {
+            world
+                .resource_mut::<ClipboardTasks>()
+                .tasks
+                .push((event, task));
+            continue;
+        };
+
+        if let Err(kind) = result {
+            world.send_event(ProjectError { event, kind });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy::reflect::ParsedPath;
+
+    use super::super::{reflect_owned_as_reflect, send_test_event, test_project_app, ProjectItem};
+    use super::*;
+
+    #[test]
+    fn base64_round_trips() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+        ];
+
+        for (data, encoded) in cases {
+            assert_eq!(encode_base64(data), encoded);
+            assert_eq!(decode_base64(encoded).as_deref(), Some(data));
+        }
+
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&data)), Some(data));
+    }
+
+    #[test]
+    fn base64_rejects_malformed_input() {
+        for encoded in [
+            "Zg", "Zg=", "Zm9vY", "Z===", "====", "Zm9v=", "Zg==Zg==", "Zh==", "Zm9=", "Zm9*",
+            "Zm 9v", "Zm9v\n",
+        ] {
+            assert_eq!(decode_base64(encoded), None, "{:?} was decoded", encoded);
+        }
+    }
+
+    fn project_item(world: &World, uuid: Uuid) -> &ProjectItem {
+        world
+            .get::<ProjectItem>(item_entity(world, uuid).unwrap())
+            .unwrap()
+    }
+
+    fn child_named(world: &World, uuid: Uuid, name: &str) -> Uuid {
+        let entity = item_entity(world, uuid).unwrap();
+
+        world
+            .get::<Children>(entity)
+            .into_iter()
+            .flatten()
+            .filter_map(|child| world.get::<ProjectItem>(*child))
+            .find(|project_item| project_item.name == name)
+            .map(|project_item| project_item.uuid)
+            .unwrap()
+    }
+
+    #[test]
+    fn pasted_folders_keep_their_contents_next_to_taken_names() {
+        let mut app = test_project_app();
+        let [props, rocks, granite] = [(); 3].map(|_| Uuid::new_v4());
+        let roughness = ParsedPath::parse("perceptual_roughness").unwrap();
+
+        for (uuid, name, parent_uuid) in [(props, "Props", None), (rocks, "Rocks", Some(props))] {
+            send_test_event(
+                &mut app,
+                ProjectEvent::CreateFolder {
+                    uuid,
+                    name: name.to_string(),
+                    parent_uuid,
+                },
+            );
+        }
+        send_test_event(
+            &mut app,
+            ProjectEvent::CreateMaterial {
+                uuid: granite,
+                name: "Granite".to_string(),
+                parent_uuid: Some(rocks),
+            },
+        );
+        send_test_event(
+            &mut app,
+            ProjectEvent::SetOverride {
+                uuid: granite,
+                path: roughness.clone(),
+                value: Arc::new(0.2_f32),
+            },
+        );
+
+        let world = &mut app.world;
+        let text = copy_project_items(world, &[props]).unwrap();
+
+        let pasted = paste_project_items(world, &text, None).unwrap();
+        assert_eq!(pasted.len(), 1);
+        assert_eq!(project_item(world, pasted[0]).name, "Props copy");
+
+        let pasted_rocks = child_named(world, pasted[0], "Rocks");
+        let pasted_granite = child_named(world, pasted_rocks, "Granite");
+        assert!(![props, rocks, granite].contains(&pasted_rocks));
+        assert!(![props, rocks, granite].contains(&pasted_granite));
+
+        let overrides = project_item(world, pasted_granite)
+            .data
+            .overrides()
+            .unwrap();
+        assert_eq!(
+            reflect_owned_as_reflect(&overrides[&roughness]).downcast_ref::<f32>(),
+            Some(&0.2)
+        );
+
+        // Pasting again next to the original folder numbers the copies
+        let pasted_again = paste_project_items(world, &text, None).unwrap();
+        assert_eq!(project_item(world, pasted_again[0]).name, "Props copy 2");
+
+        let pasted_inside = paste_project_items(world, &text, Some(props)).unwrap();
+        assert_eq!(project_item(world, pasted_inside[0]).name, "Props");
+        child_named(
+            world,
+            child_named(world, pasted_inside[0], "Rocks"),
+            "Granite",
+        );
+
+        std::fs::remove_dir_all(asset_root_path(world.resource::<AssetServer>())).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::asset::{Asset, HandleId};
+use bevy::prelude::*;
+use bevy::reflect::ReflectRef;
+use bevy::utils::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::prefab::scene_prefab_uuids;
+use super::{
+    read_scene_if_needed, scene_item_data, ProjectErrorKind, ProjectItem, ProjectItemData,
+    ProjectItemRegistry,
+};
+
+/// Records, per scene and per material, which other project items it references.
+///
+/// References are found by matching the asset handles stored in a scene's components or in a
+/// material's fields against the handles of project items.
+#[derive(Resource, Default)]
+pub struct ProjectDependencies {
+    references: HashMap<Uuid, HashSet<Uuid>>,
+}
+
+impl ProjectDependencies {
+    pub fn references(&self, uuid: Uuid) -> impl Iterator<Item = Uuid> + '_ {
+        self.references.get(&uuid).into_iter().flatten().copied()
+    }
+
+    /// Finds the items referencing `uuid`, among the scenes read so far.
+    ///
+    /// [`find_usages`] also reads the scenes that were never opened.
+    pub fn usages(&self, uuid: Uuid) -> Vec<Uuid> {
+        let mut usages: Vec<Uuid> = self
+            .references
+            .iter()
+            .filter(|(_, references)| references.contains(&uuid))
+            .map(|(user, _)| *user)
+            .collect();
+        usages.sort();
+        usages
+    }
+
+    pub(super) fn set_references(&mut self, uuid: Uuid, references: HashSet<Uuid>) {
+        if references.is_empty() {
+            self.references.remove(&uuid);
+        } else {
+            self.references.insert(uuid, references);
+        }
+    }
+
+    pub(super) fn remove(&mut self, uuid: Uuid) {
+        self.references.remove(&uuid);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.references.clear();
+    }
+}
+
+fn collect_handle_ids(value: &dyn Reflect, handle_ids: &mut HashSet<HandleId>) {
+    if let Some(handle_id) = value.downcast_ref::<HandleId>() {
+        handle_ids.insert(*handle_id);
+        return;
+    }
+
+    match value.reflect_ref() {
+        ReflectRef::Struct(value) => {
+            for field in value.iter_fields() {
+                collect_handle_ids(field, handle_ids);
+            }
+        }
+        ReflectRef::TupleStruct(value) => {
+            for field in value.iter_fields() {
+                collect_handle_ids(field, handle_ids);
+            }
+        }
+        ReflectRef::Tuple(value) => {
+            for field in value.iter_fields() {
+                collect_handle_ids(field, handle_ids);
+            }
+        }
+        ReflectRef::List(value) => {
+            for item in value.iter() {
+                collect_handle_ids(item, handle_ids);
+            }
+        }
+        ReflectRef::Array(value) => {
+            for item in value.iter() {
+                collect_handle_ids(item, handle_ids);
+            }
+        }
+        ReflectRef::Map(value) => {
+            for (key, value) in value.iter() {
+                collect_handle_ids(key, handle_ids);
+                collect_handle_ids(value, handle_ids);
+            }
+        }
+        ReflectRef::Enum(value) => {
+            for field in value.iter_fields() {
+                collect_handle_ids(field.value(), handle_ids);
+            }
+        }
+        ReflectRef::Value(_) => {}
+    }
+}
+
+fn item_uuids_by_handle<'a>(
+    items: impl Iterator<Item = &'a ProjectItem>,
+) -> HashMap<HandleId, Uuid> {
+    items
+        .filter_map(|project_item| Some((project_item.data.handle_id()?, project_item.uuid)))
+        .collect()
+}
+
+fn resolve_references(
+    item_uuids: &HashMap<HandleId, Uuid>,
+    handle_ids: &HashSet<HandleId>,
+) -> HashSet<Uuid> {
+    handle_ids
+        .iter()
+        .filter_map(|handle_id| item_uuids.get(handle_id).copied())
+        .collect()
+}
+
+fn scene_handle_ids(dynamic_scene: &DynamicScene) -> HashSet<HandleId> {
+    let mut handle_ids = HashSet::default();
+
+    for resource in &dynamic_scene.resources {
+        collect_handle_ids(resource.as_ref(), &mut handle_ids);
+    }
+
+    for entity in &dynamic_scene.entities {
+        for component in &entity.components {
+            collect_handle_ids(component.as_ref(), &mut handle_ids);
+        }
+    }
+
+    handle_ids
+}
+
+fn world_item_uuids_by_handle(world: &World) -> HashMap<HandleId, Uuid> {
+    item_uuids_by_handle(
+        world
+            .resource::<ProjectItemRegistry>()
+            .items
+            .values()
+            .filter_map(|entity| world.get::<ProjectItem>(*entity)),
+    )
+}
+
+pub fn scene_references(world: &World, dynamic_scene: &DynamicScene) -> HashSet<Uuid> {
+    let mut references = resolve_references(
+        &world_item_uuids_by_handle(world),
+        &scene_handle_ids(dynamic_scene),
+    );
+
+    let registry = world.resource::<ProjectItemRegistry>();
+    references.extend(
+        scene_prefab_uuids(dynamic_scene)
+            .into_iter()
+            .filter(|prefab_uuid| registry.items.contains_key(prefab_uuid)),
+    );
+
+    references
+}
+
+/// Records the references of a scene that was never opened, as it was last stored.
+pub(super) fn read_scene_references(
+    world: &mut World,
+    scene_uuid: Uuid,
+) -> Result<(), ProjectErrorKind> {
+    let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+    read_scene_if_needed(world, scene_uuid, source, &mut dynamic_scene)
+}
+
+/// Finds the items referencing `uuid`, reading the scenes that were never opened first.
+pub fn find_usages(world: &mut World, uuid: Uuid) -> Result<Vec<Uuid>, ProjectErrorKind> {
+    let scene_uuids: Vec<Uuid> = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .values()
+        .filter_map(|entity| world.get::<ProjectItem>(*entity))
+        .filter(|project_item| matches!(project_item.data, ProjectItemData::Scene { .. }))
+        .map(|project_item| project_item.uuid)
+        .collect();
+
+    for scene_uuid in scene_uuids {
+        read_scene_references(world, scene_uuid)?;
+    }
+
+    Ok(world.resource::<ProjectDependencies>().usages(uuid))
+}
+
+fn is_missing_asset<T: Asset>(world: &World, handle_id: HandleId) -> bool {
+    matches!(handle_id, HandleId::Id(type_uuid, _) if type_uuid == T::TYPE_UUID)
+        && !world
+            .resource::<Assets<T>>()
+            .contains(&Handle::weak(handle_id))
+}
+
+/// Finds the material, image and mesh handles of a scene that point neither to a project item
+/// nor to any other existing asset.
+pub fn unresolved_scene_handles(world: &World, dynamic_scene: &DynamicScene) -> Vec<HandleId> {
+    let item_uuids = world_item_uuids_by_handle(world);
+
+    let mut unresolved: Vec<HandleId> = scene_handle_ids(dynamic_scene)
+        .into_iter()
+        .filter(|handle_id| !item_uuids.contains_key(handle_id))
+        .filter(|handle_id| {
+            is_missing_asset::<StandardMaterial>(world, *handle_id)
+                || is_missing_asset::<Image>(world, *handle_id)
+                || is_missing_asset::<Mesh>(world, *handle_id)
+        })
+        .collect();
+    unresolved.sort();
+    unresolved
+}
+
+pub fn update_material_dependencies(
+    mut asset_events: EventReader<AssetEvent<StandardMaterial>>,
+    mut dependencies: ResMut<ProjectDependencies>,
+    materials: Res<Assets<StandardMaterial>>,
+    items: Query<&ProjectItem>,
+) {
+    let updated_materials: HashSet<HandleId> = asset_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+
+    if updated_materials.is_empty() {
+        return;
+    }
+
+    let item_uuids = item_uuids_by_handle(items.iter());
+
+    for project_item in &items {
+        let Some(handle) = project_item.data.asset_handle::<StandardMaterial>() else {
+            continue;
+        };
+
+        if !updated_materials.contains(&handle.id()) {
+            continue;
+        }
+
+        let Some(material) = materials.get(&handle) else {
+            continue;
+        };
+
+        let mut handle_ids = HashSet::default();
+        collect_handle_ids(material.as_reflect(), &mut handle_ids);
+
+        dependencies.set_references(
+            project_item.uuid,
+            resolve_references(&item_uuids, &handle_ids),
+        );
+    }
+}
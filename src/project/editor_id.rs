@@ -0,0 +1,289 @@
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::editor::EditorItem;
+
+use super::prefab::find_component;
+use super::visit_reflect_mut;
+
+/// Identifies an editor entity across sessions.
+///
+/// Stored scenes derive the id of each entity from it, so entities keep their id in the scene
+/// file every time the scene is stored, and can be matched after the scene is loaded again.
+#[derive(Component, Reflect, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct EditorId(pub Uuid);
+
+/// Gives an id to the editor entities that don't have one yet.
+pub(super) fn assign_editor_ids(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, (With<EditorItem>, Without<EditorId>)>()
+        .iter(world)
+        .collect();
+
+    for entity in entities {
+        world.entity_mut(entity).insert(EditorId(Uuid::new_v4()));
+    }
+}
+
+fn stored_entity_index(editor_id: Uuid) -> u32 {
+    let (high, low) = editor_id.as_u64_pair();
+    let bits = high ^ low;
+    (bits ^ (bits >> 32)) as u32
+}
+
+/// Renumbers the entities of a scene about to be stored after their [`EditorId`], updating the
+/// entity references of their components, such as their parents and children.
+///
+/// Returns the new id of each entity.
+pub(super) fn use_editor_ids(dynamic_scene: &mut DynamicScene) -> EntityMap {
+    let mut editor_ids: Vec<(Uuid, Entity)> = dynamic_scene
+        .entities
+        .iter()
+        .filter_map(|dynamic_entity| {
+            let editor_id = find_component::<EditorId>(dynamic_entity)?;
+            Some((editor_id.0, dynamic_entity.entity))
+        })
+        .collect();
+    editor_ids.sort();
+
+    let mut taken = HashSet::default();
+    let mut entities = EntityMap::default();
+
+    // Colliding ids are rare, and resolved the same way as long as the colliding entities stay
+    for (editor_id, entity) in editor_ids {
+        let mut index = stored_entity_index(editor_id);
+        while !taken.insert(index) {
+            index = index.wrapping_add(1);
+        }
+        entities.insert(entity, Entity::from_raw(index));
+    }
+
+    let mut next_index = 0;
+    for dynamic_entity in &dynamic_scene.entities {
+        if entities.get(dynamic_entity.entity).is_none() {
+            while !taken.insert(next_index) {
+                next_index += 1;
+            }
+            entities.insert(dynamic_entity.entity, Entity::from_raw(next_index));
+        }
+    }
+
+    map_scene_entities(dynamic_scene, &entities);
+
+    // Keeps the order of scene files stable too, so that they diff and merge well
+    dynamic_scene
+        .entities
+        .sort_by_key(|dynamic_entity| dynamic_entity.entity);
+
+    entities
+}
+
+/// Renumbers the entities of a scene found in `entities`, updating the entity references of
+/// their components.
+pub(super) fn map_scene_entities(dynamic_scene: &mut DynamicScene, entities: &EntityMap) {
+    for dynamic_entity in &mut dynamic_scene.entities {
+        if let Some(new_entity) = entities.get(dynamic_entity.entity) {
+            dynamic_entity.entity = new_entity;
+        }
+
+        for component in &mut dynamic_entity.components {
+            visit_reflect_mut(component.as_reflect_mut(), &mut |value| {
+                let Some(entity) = value.downcast_mut::<Entity>() else {
+                    return false;
+                };
+                if let Some(new_entity) = entities.get(*entity) {
+                    *entity = new_entity;
+                }
+                true
+            });
+        }
+    }
+}
+
+/// Matches the entities of a stored scene to the live entities with the same [`EditorId`],
+/// adding them to `entities`.
+pub(super) fn match_editor_ids(
+    world: &mut World,
+    dynamic_scene: &DynamicScene,
+    entities: &mut EntityMap,
+) {
+    let live_entities: HashMap<EditorId, Entity> = world
+        .query_filtered::<(Entity, &EditorId), With<EditorItem>>()
+        .iter(world)
+        .map(|(entity, editor_id)| (*editor_id, entity))
+        .collect();
+
+    for dynamic_entity in &dynamic_scene.entities {
+        if let Some(live_entity) = find_component::<EditorId>(dynamic_entity)
+            .and_then(|editor_id| live_entities.get(&editor_id))
+        {
+            entities.insert(dynamic_entity.entity, *live_entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::scene::DynamicEntity;
+
+    use super::super::scene_file::{deserialize_scene, extract_stored_scene};
+    use super::*;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    enum Link {
+        #[default]
+        Unlinked,
+        To {
+            target: Entity,
+        },
+    }
+
+    fn test_world() -> World {
+        let mut app = App::new();
+        app.add_plugins(HierarchyPlugin)
+            .register_type::<Entity>()
+            .register_type::<Uuid>()
+            .register_type::<EditorId>()
+            .register_type::<Link>();
+        std::mem::take(&mut app.world)
+    }
+
+    /// Spawns a parent linked to its only child, returning both.
+    fn spawn_family(world: &mut World) -> (Entity, Entity) {
+        let parent = world
+            .spawn((EditorItem::default(), EditorId(Uuid::new_v4())))
+            .id();
+        let child = world
+            .spawn((
+                EditorItem::default(),
+                EditorId(Uuid::new_v4()),
+                Link::To { target: parent },
+            ))
+            .id();
+        world.entity_mut(parent).add_child(child);
+        (parent, child)
+    }
+
+    fn stored_scene(world: &World, entities: impl Iterator<Item = Entity>) -> String {
+        let mut dynamic_scene = extract_stored_scene(world, entities);
+        use_editor_ids(&mut dynamic_scene);
+        dynamic_scene
+            .serialize_ron(world.resource::<AppTypeRegistry>())
+            .unwrap()
+    }
+
+    fn referenced_entities(dynamic_entity: &DynamicEntity, type_name: &str) -> Vec<Entity> {
+        let mut component = dynamic_entity
+            .components
+            .iter()
+            .find(|component| component.type_name() == type_name)
+            .unwrap()
+            .clone_value();
+
+        let mut entities = Vec::new();
+        visit_reflect_mut(component.as_reflect_mut(), &mut |value| {
+            let Some(entity) = value.downcast_ref::<Entity>() else {
+                return false;
+            };
+            entities.push(*entity);
+            true
+        });
+        entities
+    }
+
+    #[test]
+    fn entity_references_follow_their_editor_ids() {
+        let mut world = test_world();
+        let (parent, child) = spawn_family(&mut world);
+
+        let mut dynamic_scene = extract_stored_scene(&world, [parent, child].into_iter());
+        let entities = use_editor_ids(&mut dynamic_scene);
+        let stored_parent = entities.get(parent).unwrap();
+        let stored_child = entities.get(child).unwrap();
+
+        let find = |entity| {
+            dynamic_scene
+                .entities
+                .iter()
+                .find(|dynamic_entity| dynamic_entity.entity == entity)
+                .unwrap()
+        };
+        assert_eq!(
+            referenced_entities(find(stored_parent), std::any::type_name::<Children>()),
+            [stored_child]
+        );
+        assert_eq!(
+            referenced_entities(find(stored_child), std::any::type_name::<Parent>()),
+            [stored_parent]
+        );
+        assert_eq!(
+            referenced_entities(find(stored_child), std::any::type_name::<Link>()),
+            [stored_parent]
+        );
+    }
+
+    #[test]
+    fn colliding_ids_are_resolved_in_id_order() {
+        // Both ids fold to the entity index 1
+        let first = Uuid::from_u64_pair(0, 1);
+        let second = Uuid::from_u64_pair(1, 0);
+        assert_eq!(stored_entity_index(first), stored_entity_index(second));
+
+        let dynamic_entity = |index, editor_id: Option<Uuid>| DynamicEntity {
+            entity: Entity::from_raw(index),
+            components: editor_id
+                .map(|editor_id| EditorId(editor_id).clone_value())
+                .into_iter()
+                .collect(),
+        };
+
+        for (first_index, second_index) in [(10, 20), (20, 10)] {
+            let mut dynamic_scene = DynamicScene {
+                resources: Vec::new(),
+                entities: vec![
+                    dynamic_entity(first_index, Some(first)),
+                    dynamic_entity(second_index, Some(second)),
+                    dynamic_entity(30, None),
+                ],
+            };
+            let entities = use_editor_ids(&mut dynamic_scene);
+
+            assert_eq!(
+                entities.get(Entity::from_raw(first_index)),
+                Some(Entity::from_raw(1))
+            );
+            assert_eq!(
+                entities.get(Entity::from_raw(second_index)),
+                Some(Entity::from_raw(2))
+            );
+            assert_eq!(
+                entities.get(Entity::from_raw(30)),
+                Some(Entity::from_raw(0))
+            );
+        }
+    }
+
+    #[test]
+    fn stored_scenes_are_the_same_after_loading() {
+        let mut world = test_world();
+        let (parent, child) = spawn_family(&mut world);
+        let stored = stored_scene(&world, [child, parent].into_iter());
+
+        // Other entities take the low indices, so that loaded entities are numbered differently
+        let mut loaded_world = test_world();
+        loaded_world.spawn_batch((0..5).map(|_| EditorItem::default()));
+
+        let dynamic_scene =
+            deserialize_scene(&stored, loaded_world.resource::<AppTypeRegistry>()).unwrap();
+        let mut entity_map = EntityMap::default();
+        dynamic_scene
+            .write_to_world(&mut loaded_world, &mut entity_map)
+            .unwrap();
+
+        assert_eq!(stored_scene(&loaded_world, entity_map.values()), stored);
+    }
+}
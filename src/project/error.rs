@@ -0,0 +1,155 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use bevy::scene::SceneSpawnError;
+use uuid::Uuid;
+
+use super::manifest::ProjectManifestError;
+use super::scene_file::SceneFileError;
+use super::ProjectEvent;
+
+/// Sent whenever a [`ProjectEvent`] could not be applied.
+pub struct ProjectError {
+    pub event: ProjectEvent,
+    pub kind: ProjectErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ProjectErrorKind {
+    UnknownItem(Uuid),
+    UnknownParent(Uuid),
+    DuplicateUuid(Uuid),
+    UnknownKind(String),
+    InvalidItemData(String),
+    WrongItemKind { uuid: Uuid, expected: &'static str },
+    PoisonedSceneMutex(Uuid),
+    SceneRead(PathBuf, SceneFileError),
+    SceneWrite(PathBuf, SceneFileError),
+    SceneMerge(String),
+    SceneSpawn(SceneSpawnError),
+    ManifestRead(PathBuf, ProjectManifestError),
+    ManifestWrite(PathBuf, ProjectManifestError),
+    MetaWrite(PathBuf, io::Error),
+    AssetRead(PathBuf, io::Error),
+    AssetWrite(PathBuf, io::Error),
+    InvalidManifest(String),
+    MoveIntoDescendant { uuid: Uuid, new_parent_uuid: Uuid },
+    FolderNotEmpty(Uuid),
+    ItemInUse { uuid: Uuid, usages: Vec<Uuid> },
+    NoOpenScene,
+    SceneNotOpen(Uuid),
+    UnsavedChanges(Uuid),
+    EmptySelection,
+    NestedPrefab(Uuid),
+    ReadOnlyItem(Uuid),
+    UnknownLibrary(Uuid),
+    LibraryMount(PathBuf, Box<ProjectErrorKind>),
+    Clipboard(String),
+    NoRecoverySnapshot,
+    RecoveryWrite(PathBuf, io::Error),
+}
+
+impl fmt::Display for ProjectErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectErrorKind::UnknownItem(uuid) => write!(f, "unknown project item {}", uuid),
+            ProjectErrorKind::UnknownParent(uuid) => {
+                write!(f, "unknown parent project item {}", uuid)
+            }
+            ProjectErrorKind::DuplicateUuid(uuid) => {
+                write!(f, "a project item with uuid {} already exists", uuid)
+            }
+            ProjectErrorKind::UnknownKind(kind) => write!(f, "unknown project item kind {}", kind),
+            ProjectErrorKind::InvalidItemData(message) => {
+                write!(f, "invalid project item data: {}", message)
+            }
+            ProjectErrorKind::WrongItemKind { uuid, expected } => {
+                write!(f, "project item {} is not a {}", uuid, expected)
+            }
+            ProjectErrorKind::PoisonedSceneMutex(uuid) => {
+                write!(f, "scene {} is poisoned by an earlier panic", uuid)
+            }
+            ProjectErrorKind::SceneRead(path, err) => {
+                write!(f, "failed to read scene {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::SceneWrite(path, err) => {
+                write!(f, "failed to write scene {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::SceneMerge(message) => {
+                write!(f, "failed to merge scenes: {}", message)
+            }
+            ProjectErrorKind::SceneSpawn(err) => write!(f, "failed to spawn scene: {}", err),
+            ProjectErrorKind::ManifestRead(path, err) => {
+                write!(f, "failed to read project {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::ManifestWrite(path, err) => {
+                write!(f, "failed to write project {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::MetaWrite(path, err) => {
+                write!(f, "failed to write meta file {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::AssetRead(path, err) => {
+                write!(f, "failed to read asset {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::AssetWrite(path, err) => {
+                write!(f, "failed to write asset {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::InvalidManifest(message) => {
+                write!(f, "invalid project manifest: {}", message)
+            }
+            ProjectErrorKind::MoveIntoDescendant {
+                uuid,
+                new_parent_uuid,
+            } => write!(
+                f,
+                "cannot move {} into its own descendant {}",
+                uuid, new_parent_uuid
+            ),
+            ProjectErrorKind::FolderNotEmpty(uuid) => write!(
+                f,
+                "folder {} is not empty and the delete is not recursive",
+                uuid
+            ),
+            ProjectErrorKind::ItemInUse { uuid, usages } => {
+                let usages: Vec<String> = usages.iter().map(|usage| usage.to_string()).collect();
+                write!(
+                    f,
+                    "project item {} is still used by {}",
+                    uuid,
+                    usages.join(", ")
+                )
+            }
+            ProjectErrorKind::NoOpenScene => write!(f, "no scene is open"),
+            ProjectErrorKind::SceneNotOpen(uuid) => write!(f, "scene {} is not open", uuid),
+            ProjectErrorKind::UnsavedChanges(uuid) => {
+                write!(f, "scene {} has unsaved changes", uuid)
+            }
+            ProjectErrorKind::EmptySelection => write!(f, "no entities are selected"),
+            ProjectErrorKind::NestedPrefab(uuid) => {
+                write!(f, "prefab {} is open, and prefabs can't be nested", uuid)
+            }
+            ProjectErrorKind::ReadOnlyItem(uuid) => write!(
+                f,
+                "project item {} belongs to a mounted library and can't be modified",
+                uuid
+            ),
+            ProjectErrorKind::UnknownLibrary(uuid) => write!(f, "unknown library {}", uuid),
+            ProjectErrorKind::LibraryMount(path, err) => {
+                write!(f, "failed to mount library {}: {}", path.display(), err)
+            }
+            ProjectErrorKind::Clipboard(message) => write!(f, "clipboard: {}", message),
+            ProjectErrorKind::NoRecoverySnapshot => {
+                write!(f, "no unsaved work from a previous session was found")
+            }
+            ProjectErrorKind::RecoveryWrite(path, err) => {
+                write!(
+                    f,
+                    "failed to write recovery snapshot {}: {}",
+                    path.display(),
+                    err
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use uuid::Uuid;
+
+use super::dependencies::read_scene_references;
+use super::{
+    item_entity, scene_item_data, LibraryItem, ProjectDependencies, ProjectErrorKind, ProjectItem,
+    ProjectItemData, ProjectItemRegistry,
+};
+
+/// Scenes with this tag are the default roots of [`unused_project_items`].
+pub const ROOT_SCENE_TAG: &str = "root";
+
+/// Lists the scenes tagged with [`ROOT_SCENE_TAG`].
+pub fn root_scenes(world: &World) -> Vec<Uuid> {
+    let mut root_scenes: Vec<Uuid> = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .values()
+        .filter_map(|entity| world.get::<ProjectItem>(*entity))
+        .filter(|project_item| matches!(project_item.data, ProjectItemData::Scene { .. }))
+        .filter(|project_item| project_item.tags.contains(ROOT_SCENE_TAG))
+        .map(|project_item| project_item.uuid)
+        .collect();
+    root_scenes.sort();
+    root_scenes
+}
+
+/// Finds the materials, images, meshes, scenes and prefabs that can't be reached by following
+/// references from `root_scenes`, sorted by name.
+///
+/// Scenes are followed as they were last stored, and items of mounted libraries are never
+/// reported, as they can't be deleted anyway.
+pub fn unused_project_items(
+    world: &mut World,
+    root_scenes: &[Uuid],
+) -> Result<Vec<Uuid>, ProjectErrorKind> {
+    for scene_uuid in root_scenes {
+        scene_item_data(world, *scene_uuid)?;
+    }
+
+    let mut reachable = HashSet::default();
+    let mut pending = root_scenes.to_vec();
+
+    while let Some(uuid) = pending.pop() {
+        if !reachable.insert(uuid) {
+            continue;
+        }
+
+        // Missing references are reported by validation, and don't reach anything
+        let Ok(entity) = item_entity(world, uuid) else {
+            continue;
+        };
+
+        let is_scene = matches!(
+            world.get::<ProjectItem>(entity).unwrap().data,
+            ProjectItemData::Scene { .. }
+        );
+        if is_scene {
+            read_scene_references(world, uuid)?;
+        }
+
+        pending.extend(world.resource::<ProjectDependencies>().references(uuid));
+    }
+
+    let mut unused: Vec<&ProjectItem> = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .iter()
+        .filter(|(uuid, _)| !reachable.contains(*uuid))
+        .filter(|(_, entity)| world.get::<LibraryItem>(**entity).is_none())
+        .filter_map(|(_, entity)| world.get::<ProjectItem>(*entity))
+        .filter(|project_item| {
+            matches!(
+                project_item.data,
+                ProjectItemData::Asset { .. } | ProjectItemData::Scene { .. }
+            )
+        })
+        .collect();
+    unused.sort_by(|a, b| a.name.cmp(&b.name).then(a.uuid.cmp(&b.uuid)));
+
+    Ok(unused
+        .into_iter()
+        .map(|project_item| project_item.uuid)
+        .collect())
+}
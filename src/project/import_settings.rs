@@ -0,0 +1,190 @@
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_resource::{TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use serde::{Deserialize, Serialize};
+
+use super::asset_root_path;
+use super::meta::AssetMeta;
+
+/// How the source of an image or mesh item is processed before overrides are applied to it.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImportSettings {
+    Image(ImageImportSettings),
+    Mesh(MeshImportSettings),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageImportSettings {
+    pub filter: ImageFilter,
+    pub color_space: ColorSpace,
+    pub generate_mipmaps: bool,
+}
+
+impl Default for ImageImportSettings {
+    fn default() -> Self {
+        ImageImportSettings {
+            filter: ImageFilter::Default,
+            color_space: ColorSpace::Default,
+            generate_mipmaps: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFilter {
+    Default,
+    Linear,
+    Nearest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// Whichever the image was loaded with.
+    Default,
+    Srgb,
+    Linear,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshImportSettings {
+    pub normals: MeshNormals,
+    pub scale: f32,
+}
+
+impl Default for MeshImportSettings {
+    fn default() -> Self {
+        MeshImportSettings {
+            normals: MeshNormals::Imported,
+            scale: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshNormals {
+    Imported,
+    Flat,
+}
+
+/// Reads the import settings stored in the meta file of `source`, if any.
+pub fn source_import_settings(world: &World, source: &str) -> Option<ImportSettings> {
+    let asset_path = AssetPath::from(source);
+    let path = asset_root_path(world.resource::<AssetServer>()).join(asset_path.path());
+
+    AssetMeta::load(&path)?.import_settings
+}
+
+fn generate_mipmaps(image: &mut Image) -> Result<(), String> {
+    let descriptor = &image.texture_descriptor;
+
+    if !matches!(
+        descriptor.format.remove_srgb_suffix(),
+        TextureFormat::Rgba8Unorm | TextureFormat::Bgra8Unorm
+    ) {
+        return Err(format!(
+            "mipmaps can't be generated for {:?} images",
+            descriptor.format
+        ));
+    }
+
+    if descriptor.dimension != TextureDimension::D2 || descriptor.size.depth_or_array_layers != 1 {
+        return Err("mipmaps can only be generated for 2d images".to_string());
+    }
+
+    if descriptor.mip_level_count > 1 {
+        return Ok(());
+    }
+
+    let mut width = descriptor.size.width as usize;
+    let mut height = descriptor.size.height as usize;
+    let mut level = image.data.clone();
+    let mut mip_level_count = 1;
+
+    while width > 1 || height > 1 {
+        let next_width = (width / 2).max(1);
+        let next_height = (height / 2).max(1);
+        let mut next_level = Vec::with_capacity(next_width * next_height * 4);
+
+        for y in 0..next_height {
+            for x in 0..next_width {
+                for channel in 0..4 {
+                    let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .iter()
+                        .map(|(dx, dy)| {
+                            let sx = (x * 2 + dx).min(width - 1);
+                            let sy = (y * 2 + dy).min(height - 1);
+                            level[(sy * width + sx) * 4 + channel] as u32
+                        })
+                        .sum();
+                    next_level.push(((sum + 2) / 4) as u8);
+                }
+            }
+        }
+
+        image.data.extend_from_slice(&next_level);
+        level = next_level;
+        width = next_width;
+        height = next_height;
+        mip_level_count += 1;
+    }
+
+    image.texture_descriptor.mip_level_count = mip_level_count;
+
+    Ok(())
+}
+
+pub fn apply_image_import_settings(
+    image: &mut Image,
+    settings: &ImageImportSettings,
+) -> Result<(), String> {
+    image.sampler_descriptor = match settings.filter {
+        ImageFilter::Default => ImageSampler::Default,
+        ImageFilter::Linear => ImageSampler::linear(),
+        ImageFilter::Nearest => ImageSampler::nearest(),
+    };
+
+    image.texture_descriptor.format = match settings.color_space {
+        ColorSpace::Default => image.texture_descriptor.format,
+        ColorSpace::Srgb => image.texture_descriptor.format.add_srgb_suffix(),
+        ColorSpace::Linear => image.texture_descriptor.format.remove_srgb_suffix(),
+    };
+
+    if settings.generate_mipmaps {
+        generate_mipmaps(image)?;
+    }
+
+    Ok(())
+}
+
+pub fn apply_mesh_import_settings(
+    mesh: &mut Mesh,
+    settings: &MeshImportSettings,
+) -> Result<(), String> {
+    if settings.scale != 1.0 {
+        match mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                for position in positions {
+                    for component in position {
+                        *component *= settings.scale;
+                    }
+                }
+            }
+            _ => return Err("mesh has no Float32x3 positions to scale".to_string()),
+        }
+    }
+
+    if settings.normals == MeshNormals::Flat {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return Err("flat normals can only be generated for triangle lists".to_string());
+        }
+
+        mesh.duplicate_vertices();
+        mesh.compute_flat_normals();
+    }
+
+    Ok(())
+}
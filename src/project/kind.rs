@@ -0,0 +1,558 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::asset::Asset;
+use bevy::prelude::*;
+use bevy::reflect::{ParsedPath, ReflectOwned, TypeUuid};
+use bevy::scene::DynamicEntity;
+use bevy::utils::HashMap;
+
+use uuid::Uuid;
+
+use crate::icon::Icon;
+
+use super::import_settings::{source_import_settings, ImportSettings};
+use super::overrides::{derived_asset_handle, SourceAsset};
+use super::source::SourceLoading;
+use super::thumbnails::ThumbnailSource;
+use super::{
+    clone_overrides, read_scene_source, set_asset_source, ProjectErrorKind, ProjectItem,
+    ProjectItemData,
+};
+
+/// The persisted form of a project item's data, as written to the project manifest.
+#[derive(Default)]
+pub struct SerializedProjectItem {
+    pub source: Option<String>,
+    pub overrides: HashMap<ParsedPath, ReflectOwned>,
+    pub properties: Option<Box<dyn Reflect>>,
+}
+
+/// Describes one type of project item: how it's displayed, created and persisted.
+///
+/// Kinds are registered on the `App` through [`RegisterProjectItemKind`], and looked up
+/// by [`ProjectItemKind::name`] when items are created or loaded from a manifest.
+pub trait ProjectItemKind: Send + Sync + 'static {
+    /// Stable identifier of this kind, written to project manifests.
+    fn name(&self) -> &'static str;
+
+    fn icon(&self) -> Icon;
+
+    /// Extensions of the asset files that are mirrored as items of this kind.
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Type uuid of the asset of [`ProjectItemData::Asset`] items of this kind.
+    fn asset_type_uuid(&self) -> Option<Uuid> {
+        None
+    }
+
+    /// What the item's thumbnail is rendered from, if it has one.
+    fn thumbnail(&self, _data: &ProjectItemData) -> Option<ThumbnailSource> {
+        None
+    }
+
+    /// Reloads the item after its source was set to `source`.
+    fn reload_source(&self, _world: &mut World, _entity: Entity, _source: &str) {}
+
+    /// Builds the data of a new item, optionally loading it from `source`.
+    ///
+    /// `entity` is the item's entity, to which the kind may add its own components.
+    fn create(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData;
+
+    fn serialize(&self, data: &ProjectItemData) -> SerializedProjectItem {
+        SerializedProjectItem {
+            source: data.source().cloned(),
+            overrides: data.overrides().map(clone_overrides).unwrap_or_default(),
+            properties: match data {
+                ProjectItemData::Custom(properties) => Some(properties.clone_value()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Rebuilds the data of an item loaded from a project manifest.
+    fn deserialize(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        serialized: SerializedProjectItem,
+    ) -> Result<ProjectItemData, String> {
+        let mut data = self.create(world, entity, uuid, serialized.source);
+
+        match data.overrides_mut() {
+            Some(overrides) => *overrides = serialized.overrides,
+            None if !serialized.overrides.is_empty() => {
+                return Err(format!("{} items can't have overrides", self.name()))
+            }
+            None => {}
+        }
+
+        if let Some(properties) = serialized.properties {
+            match &mut data {
+                ProjectItemData::Custom(value) if value.type_name() == properties.type_name() => {
+                    value.apply(&*properties)
+                }
+                _ => {
+                    return Err(format!(
+                        "unexpected {} properties for a {} item",
+                        properties.type_name(),
+                        self.name()
+                    ))
+                }
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Builds the data of a copy of the `original` item, which must not share any assets with it.
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        entity: Entity,
+        uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        let serialized = self.serialize(&world.get::<ProjectItem>(original).unwrap().data);
+
+        self.deserialize(world, entity, uuid, serialized)
+            .map_err(ProjectErrorKind::InvalidItemData)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ProjectItemKinds {
+    kinds: HashMap<&'static str, Arc<dyn ProjectItemKind>>,
+}
+
+impl ProjectItemKinds {
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ProjectItemKind>> {
+        self.kinds.get(name).cloned()
+    }
+
+    pub fn register(&mut self, kind: impl ProjectItemKind) {
+        self.kinds.insert(kind.name(), Arc::new(kind));
+    }
+
+    /// The kind of the items mirroring asset files with `extension`.
+    pub fn for_extension(&self, extension: &str) -> Option<Arc<dyn ProjectItemKind>> {
+        self.kinds
+            .values()
+            .find(|kind| {
+                kind.source_extensions()
+                    .iter()
+                    .any(|source_extension| source_extension.eq_ignore_ascii_case(extension))
+            })
+            .cloned()
+    }
+}
+
+pub trait RegisterProjectItemKind {
+    fn register_project_item_kind(&mut self, kind: impl ProjectItemKind) -> &mut Self;
+}
+
+impl RegisterProjectItemKind for App {
+    fn register_project_item_kind(&mut self, kind: impl ProjectItemKind) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ProjectItemKinds::default)
+            .register(kind);
+        self
+    }
+}
+
+/// Sets up the source and derived handles of an asset-backed item, returning the derived one.
+pub fn asset_item_handle<T: Asset>(
+    world: &mut World,
+    entity: Entity,
+    uuid: Uuid,
+    source: Option<&str>,
+    source_handle: Handle<T>,
+) -> Handle<T> {
+    let handle = derived_asset_handle::<T>(world, uuid);
+    let import_settings = source.and_then(|source| source_import_settings(world, source));
+
+    let mut entity = world.entity_mut(entity);
+    entity.insert(SourceAsset {
+        handle: source_handle,
+    });
+
+    if source.is_some() {
+        entity.insert(SourceLoading);
+    }
+
+    if let Some(import_settings) = import_settings {
+        entity.insert(import_settings);
+    }
+
+    handle
+}
+
+fn duplicate_asset_item<T: Asset + Clone>(
+    world: &mut World,
+    original: Entity,
+    entity: Entity,
+    uuid: Uuid,
+) -> ProjectItemData {
+    let data = &world.get::<ProjectItem>(original).unwrap().data;
+    let source = data.source().cloned();
+    let overrides = data.overrides().map(clone_overrides).unwrap_or_default();
+
+    let source_handle = world
+        .get::<SourceAsset<T>>(original)
+        .unwrap()
+        .handle
+        .clone();
+
+    // Assets loaded from a file can be shared, anything else gets its own copy
+    let source_handle = match source {
+        Some(_) => source_handle,
+        None => {
+            let mut assets = world.resource_mut::<Assets<T>>();
+            match assets.get(&source_handle).cloned() {
+                Some(asset) => assets.add(asset),
+                None => source_handle,
+            }
+        }
+    };
+
+    let handle = asset_item_handle(world, entity, uuid, source.as_deref(), source_handle);
+
+    // Items without a source have nowhere else to keep their import settings
+    if source.is_none() {
+        if let Some(import_settings) = world.get::<ImportSettings>(original).cloned() {
+            world.entity_mut(entity).insert(import_settings);
+        }
+    }
+
+    ProjectItemData::Asset {
+        source,
+        handle: handle.clone_untyped(),
+        overrides,
+    }
+}
+
+pub(super) fn clone_dynamic_scene(dynamic_scene: &DynamicScene) -> DynamicScene {
+    DynamicScene {
+        resources: dynamic_scene
+            .resources
+            .iter()
+            .map(|resource| resource.clone_value())
+            .collect(),
+        entities: dynamic_scene
+            .entities
+            .iter()
+            .map(|entity| DynamicEntity {
+                entity: entity.entity,
+                components: entity
+                    .components
+                    .iter()
+                    .map(|component| component.clone_value())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn load_source<T: Asset>(world: &World, source: Option<&String>) -> Option<Handle<T>> {
+    source.map(|source| world.resource::<AssetServer>().load(source.as_str()))
+}
+
+pub struct FolderKind;
+
+impl ProjectItemKind for FolderKind {
+    fn name(&self) -> &'static str {
+        "folder"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Folder")
+    }
+
+    fn create(
+        &self,
+        _world: &mut World,
+        _entity: Entity,
+        _uuid: Uuid,
+        _source: Option<String>,
+    ) -> ProjectItemData {
+        ProjectItemData::Folder
+    }
+}
+
+pub struct MaterialKind;
+
+impl ProjectItemKind for MaterialKind {
+    fn name(&self) -> &'static str {
+        "material"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Material")
+    }
+
+    fn asset_type_uuid(&self) -> Option<Uuid> {
+        Some(StandardMaterial::TYPE_UUID)
+    }
+
+    fn thumbnail(&self, data: &ProjectItemData) -> Option<ThumbnailSource> {
+        data.asset_handle::<StandardMaterial>()
+            .map(ThumbnailSource::Material)
+    }
+
+    fn reload_source(&self, world: &mut World, entity: Entity, source: &str) {
+        set_asset_source::<StandardMaterial>(world, entity, source);
+    }
+
+    fn create(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData {
+        let source_handle = load_source(world, source.as_ref()).unwrap_or_else(|| {
+            world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(StandardMaterial::default())
+        });
+
+        let handle = asset_item_handle(world, entity, uuid, source.as_deref(), source_handle);
+        ProjectItemData::Asset {
+            source,
+            handle: handle.clone_untyped(),
+            overrides: default(),
+        }
+    }
+
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        entity: Entity,
+        uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        Ok(duplicate_asset_item::<StandardMaterial>(
+            world, original, entity, uuid,
+        ))
+    }
+}
+
+pub struct ImageKind;
+
+impl ProjectItemKind for ImageKind {
+    fn name(&self) -> &'static str {
+        "image"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Image")
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &[
+            "png", "jpg", "jpeg", "bmp", "tga", "hdr", "exr", "dds", "ktx2", "basis", "webp",
+        ]
+    }
+
+    fn asset_type_uuid(&self) -> Option<Uuid> {
+        Some(Image::TYPE_UUID)
+    }
+
+    fn thumbnail(&self, data: &ProjectItemData) -> Option<ThumbnailSource> {
+        data.asset_handle::<Image>().map(ThumbnailSource::Image)
+    }
+
+    fn reload_source(&self, world: &mut World, entity: Entity, source: &str) {
+        set_asset_source::<Image>(world, entity, source);
+    }
+
+    fn create(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData {
+        let source_handle: Handle<Image> = load_source(world, source.as_ref()).unwrap_or_default();
+
+        let handle = asset_item_handle(world, entity, uuid, source.as_deref(), source_handle);
+        ProjectItemData::Asset {
+            source,
+            handle: handle.clone_untyped(),
+            overrides: default(),
+        }
+    }
+
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        entity: Entity,
+        uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        Ok(duplicate_asset_item::<Image>(world, original, entity, uuid))
+    }
+}
+
+pub struct MeshKind;
+
+impl ProjectItemKind for MeshKind {
+    fn name(&self) -> &'static str {
+        "mesh"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Mesh")
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["gltf", "glb"]
+    }
+
+    fn asset_type_uuid(&self) -> Option<Uuid> {
+        Some(Mesh::TYPE_UUID)
+    }
+
+    fn thumbnail(&self, data: &ProjectItemData) -> Option<ThumbnailSource> {
+        data.asset_handle::<Mesh>().map(ThumbnailSource::Mesh)
+    }
+
+    fn reload_source(&self, world: &mut World, entity: Entity, source: &str) {
+        set_asset_source::<Mesh>(world, entity, source);
+    }
+
+    fn create(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData {
+        let source_handle: Handle<Mesh> = load_source(world, source.as_ref()).unwrap_or_default();
+
+        let handle = asset_item_handle(world, entity, uuid, source.as_deref(), source_handle);
+        ProjectItemData::Asset {
+            source,
+            handle: handle.clone_untyped(),
+            overrides: default(),
+        }
+    }
+
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        entity: Entity,
+        uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        Ok(duplicate_asset_item::<Mesh>(world, original, entity, uuid))
+    }
+}
+
+pub struct SceneKind;
+
+impl ProjectItemKind for SceneKind {
+    fn name(&self) -> &'static str {
+        "scene"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Scene")
+    }
+
+    fn create(
+        &self,
+        _world: &mut World,
+        _entity: Entity,
+        _uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData {
+        // Scenes with a source are read from disk the first time they're opened
+        let dynamic_scene = match source {
+            Some(_) => None,
+            None => Some(DynamicScene::default()),
+        };
+
+        ProjectItemData::Scene {
+            source,
+            dynamic_scene: Arc::new(Mutex::new(dynamic_scene)),
+        }
+    }
+
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        _entity: Entity,
+        _uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        let project_item = world.get::<ProjectItem>(original).unwrap();
+        let ProjectItemData::Scene {
+            source,
+            dynamic_scene,
+        } = &project_item.data
+        else {
+            return Err(ProjectErrorKind::WrongItemKind {
+                uuid: project_item.uuid,
+                expected: "scene",
+            });
+        };
+
+        let dynamic_scene = dynamic_scene
+            .lock()
+            .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(project_item.uuid))?;
+
+        let duplicate_scene = match (&*dynamic_scene, source) {
+            (Some(dynamic_scene), _) => clone_dynamic_scene(dynamic_scene),
+            (None, Some(source)) => read_scene_source(world, source)?,
+            (None, None) => DynamicScene::default(),
+        };
+
+        // The copy is written to its own file the first time it's stored
+        Ok(ProjectItemData::Scene {
+            source: None,
+            dynamic_scene: Arc::new(Mutex::new(Some(duplicate_scene))),
+        })
+    }
+}
+
+/// Prefabs are stored like scenes, and can be opened and stored like them to be edited.
+pub struct PrefabKind;
+
+impl ProjectItemKind for PrefabKind {
+    fn name(&self) -> &'static str {
+        "prefab"
+    }
+
+    fn icon(&self) -> Icon {
+        Icon::named("Entity")
+    }
+
+    fn create(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        uuid: Uuid,
+        source: Option<String>,
+    ) -> ProjectItemData {
+        SceneKind.create(world, entity, uuid, source)
+    }
+
+    fn duplicate(
+        &self,
+        world: &mut World,
+        original: Entity,
+        entity: Entity,
+        uuid: Uuid,
+    ) -> Result<ProjectItemData, ProjectErrorKind> {
+        SceneKind.duplicate(world, original, entity, uuid)
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use uuid::Uuid;
+
+use super::kind::FolderKind;
+use super::migration::read_project_manifest;
+use super::{
+    despawn_project_items, item_entity, project_item_kind, spawn_manifest_items,
+    spawn_project_item, validate_project_manifest, ProjectErrorKind, ProjectItem, ProjectItemData,
+    ProjectItemKind, ProjectItemKinds, ProjectItemRegistry,
+};
+
+/// Another project manifest, mounted read-only under a top-level folder of the project.
+pub struct ProjectLibrary {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Marks the items of a mounted library, including its top-level folder.
+///
+/// Library items can be opened and referenced by the project's scenes, but not modified.
+#[derive(Component)]
+pub struct LibraryItem {
+    pub library_uuid: Uuid,
+}
+
+/// Looks up an item that may be modified, which excludes the items of mounted libraries.
+pub(super) fn writable_item_entity(world: &World, uuid: Uuid) -> Result<Entity, ProjectErrorKind> {
+    let entity = item_entity(world, uuid)?;
+
+    if world.get::<LibraryItem>(entity).is_some() {
+        return Err(ProjectErrorKind::ReadOnlyItem(uuid));
+    }
+
+    Ok(entity)
+}
+
+fn spawn_library_items(
+    world: &mut World,
+    library_uuid: Uuid,
+    root: Entity,
+    path: &Path,
+) -> Result<(), ProjectErrorKind> {
+    // Libraries are never written to, so older ones are only upgraded in memory
+    let (manifest, _) = read_project_manifest(world, path)?;
+    validate_project_manifest(&manifest, world.resource::<ProjectItemKinds>())?;
+
+    let registry = world.resource::<ProjectItemRegistry>();
+    if let Some(item) = manifest
+        .items
+        .iter()
+        .find(|item| registry.items.contains_key(&item.uuid))
+    {
+        return Err(ProjectErrorKind::DuplicateUuid(item.uuid));
+    }
+
+    // The library's own libraries aren't mounted, so referencing them from the project requires
+    // mounting them too
+    let entities = spawn_manifest_items(world, manifest.items, Some(root))?;
+
+    for entity in entities {
+        world
+            .entity_mut(entity)
+            .insert(LibraryItem { library_uuid });
+    }
+
+    Ok(())
+}
+
+/// Mounts the project manifest at `path` under a new top-level folder `uuid`.
+///
+/// When the library can't be read, its folder is still added, empty and with the failure as its
+/// load error, so that saving the project keeps the library.
+pub(super) fn mount_library(
+    world: &mut World,
+    uuid: Uuid,
+    name: String,
+    path: &Path,
+) -> Result<(), ProjectErrorKind> {
+    let kind = project_item_kind(world, FolderKind.name())?;
+    let root = spawn_project_item(world, uuid, name.clone(), None, kind, |_, _| {
+        Ok(ProjectItemData::Folder)
+    })?;
+
+    world
+        .entity_mut(root)
+        .insert(LibraryItem { library_uuid: uuid });
+
+    world
+        .resource_mut::<ProjectItemRegistry>()
+        .libraries
+        .insert(
+            uuid,
+            ProjectLibrary {
+                name,
+                path: path.to_path_buf(),
+            },
+        );
+
+    spawn_library_items(world, uuid, root, path).map_err(|err| {
+        let err = ProjectErrorKind::LibraryMount(path.to_path_buf(), Box::new(err));
+        world.get_mut::<ProjectItem>(root).unwrap().load_error = Some(err.to_string());
+        err
+    })
+}
+
+pub(super) fn unmount_library(world: &mut World, uuid: Uuid) -> Result<(), ProjectErrorKind> {
+    if world
+        .resource_mut::<ProjectItemRegistry>()
+        .libraries
+        .remove(&uuid)
+        .is_none()
+    {
+        return Err(ProjectErrorKind::UnknownLibrary(uuid));
+    }
+
+    let root = item_entity(world, uuid)?;
+    despawn_project_items(world, root);
+
+    Ok(())
+}
@@ -0,0 +1,675 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
+use bevy::reflect::{ParsedPath, Reflect, ReflectOwned, TypeRegistryInternal};
+use bevy::utils::HashMap;
+use ron::ser::PrettyConfig;
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use super::import_settings::ImportSettings;
+use super::reflect_owned_as_reflect;
+
+pub const PROJECT_MANIFEST_VERSION: u32 = 1;
+
+pub struct ProjectManifest {
+    pub version: u32,
+    pub items: Vec<ProjectManifestItem>,
+    pub libraries: Vec<ProjectManifestLibrary>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProjectManifestItem {
+    pub uuid: Uuid,
+    pub name: String,
+    pub parent_uuid: Option<Uuid>,
+    pub kind: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Import settings of an item without a source file, which has no meta file to keep them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_settings: Option<ImportSettings>,
+    #[serde(skip)]
+    pub overrides: HashMap<ParsedPath, ReflectOwned>,
+    #[serde(skip)]
+    pub properties: Option<Box<dyn Reflect>>,
+}
+
+/// Another project manifest, mounted read-only under a top-level folder.
+#[derive(Serialize, Deserialize)]
+pub struct ProjectManifestLibrary {
+    /// Uuid of the top-level folder.
+    pub uuid: Uuid,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum ProjectManifestError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Deserialize(ron::error::SpannedError),
+    UnsupportedVersion(u32),
+    Migration(String),
+}
+
+impl fmt::Display for ProjectManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectManifestError::Io(err) => write!(f, "{}", err),
+            ProjectManifestError::Serialize(err) => write!(f, "{}", err),
+            ProjectManifestError::Deserialize(err) => write!(f, "{}", err),
+            ProjectManifestError::UnsupportedVersion(version) => write!(
+                f,
+                "manifest version {} is not supported (expected version {})",
+                version, PROJECT_MANIFEST_VERSION
+            ),
+            ProjectManifestError::Migration(message) => {
+                write!(f, "failed to upgrade manifest: {}", message)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ProjectManifestError {
+    fn from(err: io::Error) -> Self {
+        ProjectManifestError::Io(err)
+    }
+}
+
+impl From<ron::Error> for ProjectManifestError {
+    fn from(err: ron::Error) -> Self {
+        ProjectManifestError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ProjectManifestError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ProjectManifestError::Deserialize(err)
+    }
+}
+
+impl ProjectManifest {
+    pub fn serialize_ron(
+        &self,
+        registry: &TypeRegistryInternal,
+    ) -> Result<String, ProjectManifestError> {
+        let serializer = ProjectManifestSerializer {
+            manifest: self,
+            registry,
+        };
+
+        Ok(ron::ser::to_string_pretty(
+            &serializer,
+            PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn deserialize_ron(
+        input: &str,
+        registry: &TypeRegistryInternal,
+    ) -> Result<ProjectManifest, ProjectManifestError> {
+        let mut deserializer = ron::de::Deserializer::from_str(input)?;
+
+        let manifest = ProjectManifestDeserializer { registry }
+            .deserialize(&mut deserializer)
+            .map_err(|err| deserializer.span_error(err))?;
+
+        if manifest.version != PROJECT_MANIFEST_VERSION {
+            return Err(ProjectManifestError::UnsupportedVersion(manifest.version));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+        registry: &TypeRegistryInternal,
+    ) -> Result<(), ProjectManifestError> {
+        let serialized = self.serialize_ron(registry)?;
+        fs::write(path, serialized)?;
+        Ok(())
+    }
+
+    pub fn load(
+        path: &Path,
+        registry: &TypeRegistryInternal,
+    ) -> Result<ProjectManifest, ProjectManifestError> {
+        let input = fs::read_to_string(path)?;
+        ProjectManifest::deserialize_ron(&input, registry)
+    }
+}
+
+// Override values and custom item properties are reflected types, so they can only be
+// (de)serialized with access to the type registry. They're kept in separate `overrides` and
+// `properties` sections keyed by item uuid, which lets the rest of the manifest use plain derived
+// (de)serialization.
+
+struct ProjectManifestSerializer<'a> {
+    manifest: &'a ProjectManifest,
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for ProjectManifestSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ProjectManifest", 5)?;
+        state.serialize_field("version", &self.manifest.version)?;
+        state.serialize_field("items", &self.manifest.items)?;
+        state.serialize_field(
+            "overrides",
+            &ManifestOverridesSerializer {
+                items: &self.manifest.items,
+                registry: self.registry,
+            },
+        )?;
+        state.serialize_field(
+            "properties",
+            &ManifestPropertiesSerializer {
+                items: &self.manifest.items,
+                registry: self.registry,
+            },
+        )?;
+        if self.manifest.libraries.is_empty() {
+            state.skip_field("libraries")?;
+        } else {
+            state.serialize_field("libraries", &self.manifest.libraries)?;
+        }
+        state.end()
+    }
+}
+
+struct ManifestPropertiesSerializer<'a> {
+    items: &'a [ProjectManifestItem],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for ManifestPropertiesSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let items_with_properties: Vec<_> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.uuid, item.properties.as_ref()?)))
+            .collect();
+
+        let mut state = serializer.serialize_map(Some(items_with_properties.len()))?;
+        for (uuid, properties) in items_with_properties {
+            state.serialize_entry(
+                &uuid,
+                &ReflectSerializer::new(properties.as_reflect(), self.registry),
+            )?;
+        }
+        state.end()
+    }
+}
+
+struct ManifestOverridesSerializer<'a> {
+    items: &'a [ProjectManifestItem],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for ManifestOverridesSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let items_with_overrides: Vec<_> = self
+            .items
+            .iter()
+            .filter(|item| !item.overrides.is_empty())
+            .collect();
+
+        let mut state = serializer.serialize_map(Some(items_with_overrides.len()))?;
+        for item in items_with_overrides {
+            state.serialize_entry(
+                &item.uuid,
+                &ItemOverridesSerializer {
+                    overrides: &item.overrides,
+                    registry: self.registry,
+                },
+            )?;
+        }
+        state.end()
+    }
+}
+
+struct ItemOverridesSerializer<'a> {
+    overrides: &'a HashMap<ParsedPath, ReflectOwned>,
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for ItemOverridesSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut overrides: Vec<_> = self
+            .overrides
+            .iter()
+            .map(|(path, value)| (path.to_string(), value))
+            .collect();
+        overrides.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut state = serializer.serialize_map(Some(overrides.len()))?;
+        for (path, value) in overrides {
+            state.serialize_entry(
+                &path,
+                &ReflectSerializer::new(reflect_owned_as_reflect(value), self.registry),
+            )?;
+        }
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum ManifestField {
+    Version,
+    Items,
+    Overrides,
+    Properties,
+    Libraries,
+}
+
+struct ProjectManifestDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ProjectManifestDeserializer<'a> {
+    type Value = ProjectManifest;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct(
+            "ProjectManifest",
+            &["version", "items", "overrides", "properties", "libraries"],
+            ProjectManifestVisitor {
+                registry: self.registry,
+            },
+        )
+    }
+}
+
+struct ProjectManifestVisitor<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> Visitor<'de> for ProjectManifestVisitor<'a> {
+    type Value = ProjectManifest;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("project manifest struct")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut version = None;
+        let mut items: Option<Vec<ProjectManifestItem>> = None;
+        let mut overrides = None;
+        let mut properties = None;
+        let mut libraries = None;
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                ManifestField::Version => {
+                    if version.is_some() {
+                        return Err(de::Error::duplicate_field("version"));
+                    }
+                    version = Some(map.next_value()?);
+                }
+                ManifestField::Items => {
+                    if items.is_some() {
+                        return Err(de::Error::duplicate_field("items"));
+                    }
+                    items = Some(map.next_value()?);
+                }
+                ManifestField::Overrides => {
+                    if overrides.is_some() {
+                        return Err(de::Error::duplicate_field("overrides"));
+                    }
+                    overrides = Some(map.next_value_seed(ManifestOverridesDeserializer {
+                        registry: self.registry,
+                    })?);
+                }
+                ManifestField::Properties => {
+                    if properties.is_some() {
+                        return Err(de::Error::duplicate_field("properties"));
+                    }
+                    properties = Some(map.next_value_seed(ManifestPropertiesDeserializer {
+                        registry: self.registry,
+                    })?);
+                }
+                ManifestField::Libraries => {
+                    if libraries.is_some() {
+                        return Err(de::Error::duplicate_field("libraries"));
+                    }
+                    libraries = Some(map.next_value()?);
+                }
+            }
+        }
+
+        let version = version.ok_or_else(|| de::Error::missing_field("version"))?;
+        let mut items = items.ok_or_else(|| de::Error::missing_field("items"))?;
+        let mut overrides = overrides.unwrap_or_default();
+        let mut properties = properties.unwrap_or_default();
+
+        for item in &mut items {
+            if let Some(item_overrides) = overrides.remove(&item.uuid) {
+                item.overrides = item_overrides;
+            }
+            item.properties = properties.remove(&item.uuid);
+        }
+
+        if let Some(uuid) = overrides.keys().next() {
+            return Err(de::Error::custom(format!(
+                "overrides for unknown item {}",
+                uuid
+            )));
+        }
+
+        if let Some(uuid) = properties.keys().next() {
+            return Err(de::Error::custom(format!(
+                "properties for unknown item {}",
+                uuid
+            )));
+        }
+
+        Ok(ProjectManifest {
+            version,
+            items,
+            libraries: libraries.unwrap_or_default(),
+        })
+    }
+}
+
+struct ManifestPropertiesDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ManifestPropertiesDeserializer<'a> {
+    type Value = HashMap<Uuid, Box<dyn Reflect>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(ManifestPropertiesVisitor {
+            registry: self.registry,
+        })
+    }
+}
+
+struct ManifestPropertiesVisitor<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> Visitor<'de> for ManifestPropertiesVisitor<'a> {
+    type Value = HashMap<Uuid, Box<dyn Reflect>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("map of item uuids to properties")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut properties = HashMap::default();
+
+        while let Some(uuid) = map.next_key::<Uuid>()? {
+            let value = map.next_value_seed(UntypedReflectDeserializer::new(self.registry))?;
+            properties.insert(uuid, value);
+        }
+
+        Ok(properties)
+    }
+}
+
+struct ManifestOverridesDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ManifestOverridesDeserializer<'a> {
+    type Value = HashMap<Uuid, HashMap<ParsedPath, ReflectOwned>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(ManifestOverridesVisitor {
+            registry: self.registry,
+        })
+    }
+}
+
+struct ManifestOverridesVisitor<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> Visitor<'de> for ManifestOverridesVisitor<'a> {
+    type Value = HashMap<Uuid, HashMap<ParsedPath, ReflectOwned>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("map of item uuids to overrides")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut overrides = HashMap::default();
+
+        while let Some(uuid) = map.next_key::<Uuid>()? {
+            let item_overrides = map.next_value_seed(ItemOverridesDeserializer {
+                registry: self.registry,
+            })?;
+            overrides.insert(uuid, item_overrides);
+        }
+
+        Ok(overrides)
+    }
+}
+
+struct ItemOverridesDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ItemOverridesDeserializer<'a> {
+    type Value = HashMap<ParsedPath, ReflectOwned>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(ItemOverridesVisitor {
+            registry: self.registry,
+        })
+    }
+}
+
+struct ItemOverridesVisitor<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> Visitor<'de> for ItemOverridesVisitor<'a> {
+    type Value = HashMap<ParsedPath, ReflectOwned>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("map of reflect paths to override values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut overrides = HashMap::default();
+
+        while let Some(path) = map.next_key::<String>()? {
+            let parsed_path = ParsedPath::parse(&path).map_err(de::Error::custom)?;
+            let value = map.next_value_seed(UntypedReflectDeserializer::new(self.registry))?;
+            overrides.insert(parsed_path, value.reflect_owned());
+        }
+
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Color;
+
+    use crate::project::import_settings::{ColorSpace, ImageFilter, ImageImportSettings};
+
+    use super::*;
+
+    #[derive(Reflect, Default)]
+    struct TestProperties {
+        speed: f32,
+        label: String,
+    }
+
+    fn test_registry() -> TypeRegistryInternal {
+        let mut registry = TypeRegistryInternal::new();
+        registry.register::<Color>();
+        registry.register::<TestProperties>();
+        registry
+    }
+
+    fn test_manifest() -> ProjectManifest {
+        let folder_uuid = Uuid::new_v4();
+
+        let mut overrides = HashMap::default();
+        overrides.insert(
+            ParsedPath::parse("base_color").unwrap(),
+            Color::rgb(0.25, 0.5, 1.0).clone_value().reflect_owned(),
+        );
+
+        ProjectManifest {
+            version: PROJECT_MANIFEST_VERSION,
+            items: vec![
+                ProjectManifestItem {
+                    uuid: folder_uuid,
+                    name: "Levels".to_string(),
+                    parent_uuid: None,
+                    kind: "folder".to_string(),
+                    source: None,
+                    tags: BTreeSet::from(["root".to_string()]),
+                    metadata: BTreeMap::from([("owner".to_string(), "level team".to_string())]),
+                    import_settings: None,
+                    overrides: HashMap::default(),
+                    properties: None,
+                },
+                ProjectManifestItem {
+                    uuid: Uuid::new_v4(),
+                    name: "Grass \"Material\"".to_string(),
+                    parent_uuid: Some(folder_uuid),
+                    kind: "material".to_string(),
+                    source: Some("materials/grass.mat".to_string()),
+                    tags: BTreeSet::new(),
+                    metadata: BTreeMap::new(),
+                    import_settings: None,
+                    overrides,
+                    properties: Some(Box::new(TestProperties {
+                        speed: 2.5,
+                        label: "fast".to_string(),
+                    })),
+                },
+                ProjectManifestItem {
+                    uuid: Uuid::new_v4(),
+                    name: "Noise".to_string(),
+                    parent_uuid: Some(folder_uuid),
+                    kind: "image".to_string(),
+                    source: None,
+                    tags: BTreeSet::new(),
+                    metadata: BTreeMap::new(),
+                    import_settings: Some(ImportSettings::Image(ImageImportSettings {
+                        filter: ImageFilter::Nearest,
+                        color_space: ColorSpace::Linear,
+                        generate_mipmaps: true,
+                    })),
+                    overrides: HashMap::default(),
+                    properties: None,
+                },
+            ],
+            libraries: vec![ProjectManifestLibrary {
+                uuid: Uuid::new_v4(),
+                name: "Shared".to_string(),
+                path: PathBuf::from("../shared/project.ron"),
+            }],
+        }
+    }
+
+    fn assert_same_manifest(expected: &ProjectManifest, actual: &ProjectManifest) {
+        assert_eq!(expected.version, actual.version);
+        assert_eq!(expected.items.len(), actual.items.len());
+
+        for (expected, actual) in expected.items.iter().zip(&actual.items) {
+            assert_eq!(expected.uuid, actual.uuid);
+            assert_eq!(expected.name, actual.name);
+            assert_eq!(expected.parent_uuid, actual.parent_uuid);
+            assert_eq!(expected.kind, actual.kind);
+            assert_eq!(expected.source, actual.source);
+            assert_eq!(expected.tags, actual.tags);
+            assert_eq!(expected.metadata, actual.metadata);
+            assert_eq!(expected.import_settings, actual.import_settings);
+
+            assert_eq!(expected.overrides.len(), actual.overrides.len());
+            for (path, expected_value) in &expected.overrides {
+                let actual_value = actual.overrides.get(path).expect("missing override");
+                // Dynamic values compare field by field, unlike some concrete ones
+                assert_eq!(
+                    reflect_owned_as_reflect(expected_value)
+                        .reflect_partial_eq(reflect_owned_as_reflect(actual_value)),
+                    Some(true),
+                    "override {} changed",
+                    path
+                );
+            }
+
+            match (&expected.properties, &actual.properties) {
+                (Some(expected), Some(actual)) => {
+                    assert_eq!(expected.reflect_partial_eq(actual.as_reflect()), Some(true))
+                }
+                (None, None) => {}
+                _ => panic!("properties of item {} changed", expected.uuid),
+            }
+        }
+
+        assert_eq!(expected.libraries.len(), actual.libraries.len());
+        for (expected, actual) in expected.libraries.iter().zip(&actual.libraries) {
+            assert_eq!(expected.uuid, actual.uuid);
+            assert_eq!(expected.name, actual.name);
+            assert_eq!(expected.path, actual.path);
+        }
+    }
+
+    #[test]
+    fn manifest_round_trips_through_a_file() {
+        let registry = test_registry();
+        let manifest = test_manifest();
+
+        let path = std::env::temp_dir().join(format!("manifest-{}.ron", Uuid::new_v4()));
+        manifest.save(&path, &registry).unwrap();
+        let loaded = ProjectManifest::load(&path, &registry);
+        fs::remove_file(&path).unwrap();
+
+        assert_same_manifest(&manifest, &loaded.unwrap());
+    }
+
+    #[test]
+    fn manifest_serializes_the_same_after_a_round_trip() {
+        let registry = test_registry();
+
+        let serialized = test_manifest().serialize_ron(&registry).unwrap();
+        let deserialized = ProjectManifest::deserialize_ron(&serialized, &registry).unwrap();
+
+        assert_eq!(serialized, deserialized.serialize_ron(&registry).unwrap());
+    }
+
+    #[test]
+    fn manifest_rejects_overrides_for_unknown_items() {
+        let registry = test_registry();
+        let input = format!(
+            "(version: {}, items: [], overrides: {{\"{}\": {{}}}})",
+            PROJECT_MANIFEST_VERSION,
+            Uuid::new_v4()
+        );
+
+        assert!(ProjectManifest::deserialize_ron(&input, &registry).is_err());
+    }
+
+    #[test]
+    fn manifest_rejects_other_versions() {
+        let registry = test_registry();
+        let input = format!("(version: {}, items: [])", PROJECT_MANIFEST_VERSION + 1);
+
+        assert!(matches!(
+            ProjectManifest::deserialize_ron(&input, &registry),
+            Err(ProjectManifestError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::import_settings::ImportSettings;
+
+/// Sidecar file stored next to an asset, keeping its project item uuid stable and holding
+/// its import settings.
+#[derive(Serialize, Deserialize)]
+pub struct AssetMeta {
+    pub uuid: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_settings: Option<ImportSettings>,
+    /// Uuids of the items of the assets labeled within the file, such as the meshes of a glTF
+    /// file.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, Uuid>,
+}
+
+impl AssetMeta {
+    pub fn new(uuid: Uuid) -> Self {
+        AssetMeta {
+            uuid,
+            import_settings: None,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    pub fn path(asset_path: &Path) -> PathBuf {
+        let mut meta_path = asset_path.as_os_str().to_owned();
+        meta_path.push(".meta");
+        PathBuf::from(meta_path)
+    }
+
+    pub fn load(asset_path: &Path) -> Option<AssetMeta> {
+        let meta_path = AssetMeta::path(asset_path);
+        let input = fs::read_to_string(&meta_path).ok()?;
+
+        match ron::from_str(&input) {
+            Ok(meta) => Some(meta),
+            Err(err) => {
+                warn!(
+                    "Ignoring invalid meta file {}: {}",
+                    meta_path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, asset_path: &Path) -> io::Result<()> {
+        let serialized = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(AssetMeta::path(asset_path), serialized)
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::error::ProjectErrorKind;
+use super::manifest::{ProjectManifest, ProjectManifestError, PROJECT_MANIFEST_VERSION};
+use super::ron_value::RonValue;
+use super::{validate_project_manifest, ProjectItemKinds};
+
+/// A step upgrading project manifests from one version to the next.
+///
+/// Steps are registered on the `App` through [`RegisterManifestMigration`]. Manifests older than
+/// [`PROJECT_MANIFEST_VERSION`] go through every step from their version onwards before they're
+/// deserialized, so steps only ever see the untyped [`RonValue`] of the whole manifest.
+pub trait ManifestMigration: Send + Sync + 'static {
+    /// Version of the manifests this step upgrades. They're at the next version afterwards.
+    fn source_version(&self) -> u32;
+
+    /// Rewrites `manifest`, describing each change in `changes`.
+    fn migrate(&self, manifest: &mut RonValue, changes: &mut Vec<String>) -> Result<(), String>;
+}
+
+#[derive(Resource, Default)]
+pub struct ManifestMigrations {
+    steps: Vec<Arc<dyn ManifestMigration>>,
+}
+
+impl ManifestMigrations {
+    pub fn register(&mut self, migration: impl ManifestMigration) {
+        self.steps.push(Arc::new(migration));
+    }
+}
+
+pub trait RegisterManifestMigration {
+    fn register_manifest_migration(&mut self, migration: impl ManifestMigration) -> &mut Self;
+}
+
+impl RegisterManifestMigration for App {
+    fn register_manifest_migration(&mut self, migration: impl ManifestMigration) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ManifestMigrations::default)
+            .register(migration);
+        self
+    }
+}
+
+/// What upgrading a manifest changed, or would change for a dry run.
+pub struct ManifestMigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub changes: Vec<String>,
+    /// Copy of the original manifest, unless this was a dry run.
+    pub backup: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct ManifestVersion {
+    version: u32,
+}
+
+fn migrate_manifest(
+    input: &str,
+    migrations: &ManifestMigrations,
+) -> Result<Option<(String, ManifestMigrationReport)>, ProjectManifestError> {
+    let ManifestVersion { version } = ron::from_str(input)?;
+
+    if version == PROJECT_MANIFEST_VERSION {
+        return Ok(None);
+    }
+    if version > PROJECT_MANIFEST_VERSION {
+        return Err(ProjectManifestError::UnsupportedVersion(version));
+    }
+
+    let mut manifest = RonValue::parse(input).map_err(ProjectManifestError::Migration)?;
+    let mut changes = Vec::new();
+
+    for from_version in version..PROJECT_MANIFEST_VERSION {
+        let mut steps = migrations
+            .steps
+            .iter()
+            .filter(|step| step.source_version() == from_version)
+            .peekable();
+
+        if steps.peek().is_none() {
+            return Err(ProjectManifestError::Migration(format!(
+                "no migration from version {}",
+                from_version
+            )));
+        }
+
+        for step in steps {
+            step.migrate(&mut manifest, &mut changes)
+                .map_err(ProjectManifestError::Migration)?;
+        }
+
+        manifest.set_field("version", RonValue::Number((from_version + 1).to_string()));
+    }
+
+    Ok(Some((
+        manifest.to_string(),
+        ManifestMigrationReport {
+            from_version: version,
+            to_version: PROJECT_MANIFEST_VERSION,
+            changes,
+            backup: None,
+        },
+    )))
+}
+
+/// Finds a path next to `path` for a backup of its version `version`, that isn't taken yet.
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    (0..)
+        .map(|index| match index {
+            0 => path.with_file_name(format!("{}.v{}.bak", file_name, version)),
+            index => path.with_file_name(format!("{}.v{}.{}.bak", file_name, version, index)),
+        })
+        .find(|backup| !backup.exists())
+        .unwrap()
+}
+
+/// Reads the manifest at `path`, upgrading it in memory if it's older than
+/// [`PROJECT_MANIFEST_VERSION`].
+pub(super) fn read_project_manifest(
+    world: &World,
+    path: &Path,
+) -> Result<(ProjectManifest, Option<ManifestMigrationReport>), ProjectErrorKind> {
+    let read_error =
+        |err: ProjectManifestError| ProjectErrorKind::ManifestRead(path.to_path_buf(), err);
+
+    let input = fs::read_to_string(path).map_err(|err| read_error(err.into()))?;
+    parse_project_manifest(world, &input).map_err(read_error)
+}
+
+/// Deserializes a manifest of any supported version, upgrading it in memory.
+pub(super) fn parse_project_manifest(
+    world: &World,
+    input: &str,
+) -> Result<(ProjectManifest, Option<ManifestMigrationReport>), ProjectManifestError> {
+    let migrated = migrate_manifest(input, world.resource::<ManifestMigrations>())?;
+
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+    let manifest = ProjectManifest::deserialize_ron(
+        migrated.as_ref().map_or(input, |(migrated, _)| migrated),
+        &type_registry,
+    )?;
+
+    Ok((manifest, migrated.map(|(_, report)| report)))
+}
+
+/// Upgrades the manifest at `path` to [`PROJECT_MANIFEST_VERSION`], returning what changed, or
+/// `None` if it's already up to date.
+///
+/// The original manifest is kept in a `.bak` file next to it. With `dry_run`, the upgraded
+/// manifest is only checked, and nothing is written.
+pub fn migrate_project_manifest(
+    world: &World,
+    path: &Path,
+    dry_run: bool,
+) -> Result<Option<ManifestMigrationReport>, ProjectErrorKind> {
+    let write_error =
+        |err: ProjectManifestError| ProjectErrorKind::ManifestWrite(path.to_path_buf(), err);
+
+    let (manifest, report) = read_project_manifest(world, path)?;
+    let Some(mut report) = report else {
+        return Ok(None);
+    };
+
+    validate_project_manifest(&manifest, world.resource::<ProjectItemKinds>())?;
+
+    if !dry_run {
+        let backup = backup_path(path, report.from_version);
+        fs::copy(path, &backup).map_err(|err| write_error(err.into()))?;
+
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        manifest.save(path, &type_registry).map_err(write_error)?;
+        report.backup = Some(backup);
+    }
+
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::reflect::TypeRegistryInternal;
+
+    use super::*;
+
+    /// Renames the `label` of items to `name`, as a made-up step from before the first version.
+    struct LabelMigration;
+
+    impl ManifestMigration for LabelMigration {
+        fn source_version(&self) -> u32 {
+            PROJECT_MANIFEST_VERSION - 1
+        }
+
+        fn migrate(
+            &self,
+            manifest: &mut RonValue,
+            changes: &mut Vec<String>,
+        ) -> Result<(), String> {
+            let Some(RonValue::List(items)) = manifest.field_mut("items") else {
+                return Err("missing items".to_string());
+            };
+
+            for item in items {
+                let Some(label) = item.remove_field("label") else {
+                    return Err("item has no label".to_string());
+                };
+
+                changes.push(format!("{}: label renamed to name", label));
+                item.set_field("name", label);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn old_manifest() -> String {
+        format!(
+            r#"(
+    version: {},
+    items: [
+        (
+            uuid: "5b4f4a4c-08a4-4d3b-9c0a-8d9f37f4a1c2",
+            label: "Levels",
+            parent_uuid: None,
+            kind: "folder",
+            source: None,
+        ),
+        (
+            uuid: "0f0e7e59-6c1f-4a53-8f7c-0b2bd3b5e9a1",
+            label: "Grass \"Material\"",
+            parent_uuid: Some("5b4f4a4c-08a4-4d3b-9c0a-8d9f37f4a1c2"),
+            kind: "material",
+            source: Some("materials/grass.mat"),
+        ),
+    ],
+    overrides: {{
+        "0f0e7e59-6c1f-4a53-8f7c-0b2bd3b5e9a1": {{
+            "base_color": {{
+                "bevy_render::color::Color": Rgba(
+                    red: 0.25,
+                    green: 0.5,
+                    blue: 1.0,
+                    alpha: 1.0,
+                ),
+            }},
+        }},
+    }},
+)"#,
+            PROJECT_MANIFEST_VERSION - 1
+        )
+    }
+
+    #[test]
+    fn older_manifests_go_through_registered_steps() {
+        let input = old_manifest();
+
+        let mut migrations = ManifestMigrations::default();
+        migrations.register(LabelMigration);
+
+        let (migrated, report) = migrate_manifest(&input, &migrations).unwrap().unwrap();
+        assert_eq!(
+            (report.from_version, report.to_version),
+            (PROJECT_MANIFEST_VERSION - 1, PROJECT_MANIFEST_VERSION)
+        );
+        assert_eq!(report.changes.len(), 2);
+
+        let mut type_registry = TypeRegistryInternal::new();
+        type_registry.register::<Color>();
+        let manifest = ProjectManifest::deserialize_ron(&migrated, &type_registry).unwrap();
+        assert_eq!(manifest.version, PROJECT_MANIFEST_VERSION);
+        assert_eq!(manifest.items[1].name, "Grass \"Material\"");
+        assert_eq!(manifest.items[1].overrides.len(), 1);
+
+        // Whatever the steps don't touch is kept as it was
+        let original = RonValue::parse(&input).unwrap();
+        let migrated = RonValue::parse(&migrated).unwrap();
+        assert_eq!(migrated.field("overrides"), original.field("overrides"));
+
+        let Some(RonValue::List(items)) = migrated.field("items") else {
+            panic!("items aren't a list");
+        };
+        assert_eq!(items[0].field("label"), None);
+        assert_eq!(
+            items[1].field("source"),
+            Some(&RonValue::Option(Some(Box::new(RonValue::String(
+                "materials/grass.mat".to_string()
+            )))))
+        );
+    }
+
+    #[test]
+    fn manifests_without_a_step_are_rejected() {
+        let missing_step = migrate_manifest(&old_manifest(), &ManifestMigrations::default());
+        assert!(matches!(
+            missing_step,
+            Err(ProjectManifestError::Migration(_))
+        ));
+
+        let current = format!("(version: {}, items: [])", PROJECT_MANIFEST_VERSION);
+        assert!(matches!(
+            migrate_manifest(&current, &ManifestMigrations::default()),
+            Ok(None)
+        ));
+    }
+}
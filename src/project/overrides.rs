@@ -0,0 +1,240 @@
+use bevy::asset::{Asset, HandleId};
+use bevy::prelude::*;
+use bevy::reflect::{ParsedPath, ReflectOwned};
+use bevy::utils::HashSet;
+use uuid::Uuid;
+
+use super::import_settings::{
+    apply_image_import_settings, apply_mesh_import_settings, ImportSettings,
+};
+use super::{reflect_owned_as_reflect, ProjectItem};
+
+/// The unmodified asset that a project item's overrides are applied on top of.
+///
+/// The item's own handle always points to a derived copy, so removing an override
+/// reverts that path to whatever the source asset holds.
+#[derive(Component)]
+pub struct SourceAsset<T: Asset> {
+    pub handle: Handle<T>,
+}
+
+/// Marks project items whose overrides changed since they were last applied to their asset.
+#[derive(Component)]
+pub struct OverridesChanged;
+
+#[derive(Component, Debug)]
+pub struct OverrideErrors {
+    pub errors: Vec<OverrideError>,
+}
+
+#[derive(Debug)]
+pub struct OverrideError {
+    pub path: ParsedPath,
+    pub message: String,
+}
+
+pub trait OverridableAsset: Asset + Clone {
+    fn reflect_root_mut(&mut self) -> Option<&mut dyn Reflect>;
+
+    fn apply_import_settings(&mut self, _settings: &ImportSettings) -> Result<(), String> {
+        Err("this asset type has no import settings".to_string())
+    }
+}
+
+impl OverridableAsset for StandardMaterial {
+    fn reflect_root_mut(&mut self) -> Option<&mut dyn Reflect> {
+        Some(self)
+    }
+}
+
+impl OverridableAsset for Image {
+    fn reflect_root_mut(&mut self) -> Option<&mut dyn Reflect> {
+        Some(self)
+    }
+
+    fn apply_import_settings(&mut self, settings: &ImportSettings) -> Result<(), String> {
+        match settings {
+            ImportSettings::Image(settings) => apply_image_import_settings(self, settings),
+            ImportSettings::Mesh(_) => {
+                Err("mesh import settings can't be applied to an image".to_string())
+            }
+        }
+    }
+}
+
+impl OverridableAsset for Mesh {
+    // `Mesh` doesn't implement `Reflect` yet, so none of its overrides can be resolved
+    fn reflect_root_mut(&mut self) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    fn apply_import_settings(&mut self, settings: &ImportSettings) -> Result<(), String> {
+        match settings {
+            ImportSettings::Mesh(settings) => apply_mesh_import_settings(self, settings),
+            ImportSettings::Image(_) => {
+                Err("image import settings can't be applied to a mesh".to_string())
+            }
+        }
+    }
+}
+
+/// Handle of the asset derived for the project item `uuid`.
+///
+/// The id is derived from the item uuid, so handles stored in scenes or overrides keep pointing
+/// to the same item across editor sessions.
+pub fn derived_asset_handle<T: Asset>(world: &World, uuid: Uuid) -> Handle<T> {
+    world
+        .resource::<Assets<T>>()
+        .get_handle(derived_handle_id::<T>(uuid))
+}
+
+/// Id of the asset derived for the project item `uuid`.
+///
+/// It keeps the asset type uuid, which the asset server frees unused assets by, and folds the
+/// item uuid into the rest.
+pub fn derived_handle_id<T: Asset>(uuid: Uuid) -> HandleId {
+    derived_handle_id_of_type(T::TYPE_UUID, uuid)
+}
+
+/// [`derived_handle_id`] for an asset type only known by its type uuid.
+pub fn derived_handle_id_of_type(type_uuid: Uuid, uuid: Uuid) -> HandleId {
+    let (high, low) = uuid.as_u64_pair();
+    HandleId::new(type_uuid, high ^ low)
+}
+
+fn apply_override(
+    root: &mut dyn Reflect,
+    path: &ParsedPath,
+    value: &ReflectOwned,
+) -> Result<(), String> {
+    let target = path
+        .reflect_element_mut(root)
+        .map_err(|err| err.to_string())?;
+    let value = reflect_owned_as_reflect(value);
+
+    if target.type_name() != value.type_name() {
+        return Err(format!(
+            "expected a value of type {}, found {}",
+            target.type_name(),
+            value.type_name()
+        ));
+    }
+
+    target.apply(value);
+
+    Ok(())
+}
+
+type OverridableItem<'a, T> = (
+    Entity,
+    &'a ProjectItem,
+    Ref<'a, SourceAsset<T>>,
+    Option<Ref<'a, ImportSettings>>,
+    Option<&'a OverridesChanged>,
+);
+
+pub fn apply_asset_overrides<T: OverridableAsset>(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<T>>,
+    mut assets: ResMut<Assets<T>>,
+    items: Query<OverridableItem<T>>,
+    mut waiting_for_source: Local<HashSet<Entity>>,
+) {
+    let mut created_sources: HashSet<Handle<T>> = HashSet::new();
+    let mut modified_sources: HashSet<Handle<T>> = HashSet::new();
+
+    for event in asset_events.iter() {
+        match event {
+            AssetEvent::Created { handle } => {
+                created_sources.insert(handle.clone_weak());
+            }
+            AssetEvent::Modified { handle } => {
+                modified_sources.insert(handle.clone_weak());
+            }
+            AssetEvent::Removed { .. } => {}
+        }
+    }
+
+    for (entity, project_item, source_asset, import_settings, overrides_changed) in &items {
+        // Other changes to the item, such as its name, don't affect the asset, and sources
+        // created before the item was applied, such as those of new items, were already used
+        let needs_update = overrides_changed.is_some()
+            || source_asset.is_changed()
+            || import_settings
+                .as_ref()
+                .is_some_and(|import_settings| import_settings.is_changed())
+            || modified_sources.contains(&source_asset.handle)
+            || (created_sources.contains(&source_asset.handle)
+                && waiting_for_source.contains(&entity));
+
+        if !needs_update {
+            continue;
+        }
+
+        if overrides_changed.is_some() {
+            commands.entity(entity).remove::<OverridesChanged>();
+        }
+
+        let (Some(handle), Some(overrides)) = (
+            project_item.data.asset_handle::<T>(),
+            project_item.data.overrides(),
+        ) else {
+            continue;
+        };
+
+        let Some(mut asset) = assets.get(&source_asset.handle).cloned() else {
+            waiting_for_source.insert(entity);
+            continue;
+        };
+        waiting_for_source.remove(&entity);
+
+        if let Some(import_settings) = &import_settings {
+            if let Err(message) = asset.apply_import_settings(import_settings) {
+                warn!(
+                    "Failed to apply import settings on {}: {}",
+                    project_item.name, message
+                );
+            }
+        }
+
+        let mut errors = Vec::new();
+
+        match asset.reflect_root_mut() {
+            Some(root) => {
+                for (path, value) in overrides {
+                    if let Err(message) = apply_override(root, path, value) {
+                        errors.push(OverrideError {
+                            path: path.clone(),
+                            message,
+                        });
+                    }
+                }
+            }
+            None => {
+                for path in overrides.keys() {
+                    errors.push(OverrideError {
+                        path: path.clone(),
+                        message: format!(
+                            "{} does not support reflection",
+                            std::any::type_name::<T>()
+                        ),
+                    });
+                }
+            }
+        }
+
+        assets.set_untracked(handle, asset);
+
+        if errors.is_empty() {
+            commands.entity(entity).remove::<OverrideErrors>();
+        } else {
+            for error in &errors {
+                warn!(
+                    "Failed to apply override {} on {}: {}",
+                    error.path, project_item.name, error.message
+                );
+            }
+            commands.entity(entity).insert(OverrideErrors { errors });
+        }
+    }
+}
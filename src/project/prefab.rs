@@ -0,0 +1,1133 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::*;
+use bevy::reflect::{ReflectMut, ReflectRef};
+use bevy::scene::DynamicEntity;
+use bevy::utils::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::editor::EditorItem;
+
+use super::dependencies::{find_usages, scene_references};
+use super::editor_id::{assign_editor_ids, use_editor_ids, EditorId};
+use super::kind::PrefabKind;
+use super::scene_file::{extract_stored_scene, insert_computed_components};
+use super::{
+    asset_root_path, folder_entity, project_item_kind, read_scene_if_needed, save_scene_file,
+    scene_item_data, spawn_project_item, OpenScene, ProjectDependencies, ProjectErrorKind,
+    ProjectItem, ProjectItemData, ProjectItemKind, ProjectItemRegistry,
+};
+
+/// Identifies an entity within a prefab, so that instances can be matched against the
+/// prefab's entities when it changes.
+#[derive(Component, Reflect, Clone, Default)]
+#[reflect(Component)]
+pub struct PrefabEntity(pub Uuid);
+
+/// Links an entity of a scene to the prefab it was instanced from.
+///
+/// Every entity of an instance shares the same `instance_uuid`, and keeps the [`PrefabEntity`]
+/// of the prefab entity it was spawned from.
+#[derive(Component, Reflect, Clone, Default)]
+#[reflect(Component)]
+pub struct PrefabInstance {
+    pub prefab_uuid: Uuid,
+    pub instance_uuid: Uuid,
+}
+
+fn is_component<T: Reflect>(component: &dyn Reflect) -> bool {
+    component.type_name() == std::any::type_name::<T>()
+}
+
+fn is_hierarchy_component(component: &dyn Reflect) -> bool {
+    is_component::<Parent>(component) || is_component::<Children>(component)
+}
+
+/// Components that belong to each entity of an instance rather than to the prefab.
+fn is_instance_component(component: &dyn Reflect) -> bool {
+    is_hierarchy_component(component) || is_component::<EditorId>(component)
+}
+
+pub(super) fn find_component<T: Reflect + FromReflect>(
+    dynamic_entity: &DynamicEntity,
+) -> Option<T> {
+    dynamic_entity
+        .components
+        .iter()
+        .find(|component| is_component::<T>(component.as_ref()))
+        .and_then(|component| T::from_reflect(component.as_ref()))
+}
+
+fn is_open_item_prefab(world: &World) -> Option<Uuid> {
+    let scene_uuid = world.resource::<OpenScene>().scene_uuid?;
+    let entity = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .get(&scene_uuid)?;
+    let project_item = world.get::<ProjectItem>(*entity)?;
+
+    (project_item.kind.name() == PrefabKind.name()).then_some(scene_uuid)
+}
+
+pub(super) fn is_prefab(world: &World, uuid: Uuid) -> bool {
+    world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .get(&uuid)
+        .and_then(|entity| world.get::<ProjectItem>(*entity))
+        .is_some_and(|project_item| project_item.kind.name() == PrefabKind.name())
+}
+
+/// Finds the prefabs that a scene has instances of.
+pub(super) fn scene_prefab_uuids(dynamic_scene: &DynamicScene) -> HashSet<Uuid> {
+    dynamic_scene
+        .entities
+        .iter()
+        .filter_map(find_component::<PrefabInstance>)
+        .map(|prefab_instance| prefab_instance.prefab_uuid)
+        .collect()
+}
+
+fn collect_descendants(world: &World, entity: Entity, entities: &mut Vec<Entity>) {
+    entities.push(entity);
+
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.iter() {
+            collect_descendants(world, *child, entities);
+        }
+    }
+}
+
+fn has_selected_ancestor(world: &World, entity: Entity, selected: &HashSet<Entity>) -> bool {
+    let mut current = entity;
+
+    while let Some(parent) = world.get::<Parent>(current) {
+        if selected.contains(&parent.get()) {
+            return true;
+        }
+        current = parent.get();
+    }
+
+    false
+}
+
+/// Turns the selected editor entities, along with their descendants, into a new prefab.
+///
+/// The selected entities become the first instance of the prefab.
+pub(super) fn create_prefab(
+    world: &mut World,
+    uuid: Uuid,
+    name: String,
+    parent_uuid: Option<Uuid>,
+) -> Result<(), ProjectErrorKind> {
+    if let Some(open_prefab_uuid) = is_open_item_prefab(world) {
+        return Err(ProjectErrorKind::NestedPrefab(open_prefab_uuid));
+    }
+
+    if world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .contains_key(&uuid)
+    {
+        return Err(ProjectErrorKind::DuplicateUuid(uuid));
+    }
+
+    if let Some(parent_uuid) = parent_uuid {
+        folder_entity(world, parent_uuid)?;
+    }
+
+    let kind = project_item_kind(world, PrefabKind.name())?;
+
+    let selected: HashSet<Entity> = world
+        .query::<(Entity, &EditorItem)>()
+        .iter(world)
+        .filter(|(_, editor_item)| editor_item.is_selected)
+        .map(|(entity, _)| entity)
+        .collect();
+
+    if selected.is_empty() {
+        return Err(ProjectErrorKind::EmptySelection);
+    }
+
+    let roots: HashSet<Entity> = selected
+        .iter()
+        .copied()
+        .filter(|entity| !has_selected_ancestor(world, *entity, &selected))
+        .collect();
+
+    let mut entities = Vec::new();
+    for root in &roots {
+        collect_descendants(world, *root, &mut entities);
+    }
+
+    assign_editor_ids(world);
+
+    // Entities taken from other instances get new ids, as prefabs aren't nested
+    for entity in &entities {
+        world
+            .entity_mut(*entity)
+            .insert(PrefabEntity(Uuid::new_v4()));
+    }
+
+    let mut dynamic_scene = extract_stored_scene(world, entities.iter().copied());
+
+    for dynamic_entity in &mut dynamic_scene.entities {
+        let is_root = roots.contains(&dynamic_entity.entity);
+        dynamic_entity.components.retain(|component| {
+            let is_root_parent = is_root && is_component::<Parent>(component.as_ref());
+            !is_component::<PrefabInstance>(component.as_ref()) && !is_root_parent
+        });
+    }
+
+    use_editor_ids(&mut dynamic_scene);
+
+    let source = format!("prefabs/{}.scn.ron", uuid);
+    let path = asset_root_path(world.resource::<AssetServer>()).join(&source);
+    save_scene_file(&path, &dynamic_scene, world.resource::<AppTypeRegistry>())
+        .map_err(|err| ProjectErrorKind::SceneWrite(path, err))?;
+
+    let references = scene_references(world, &dynamic_scene);
+
+    spawn_project_item(world, uuid, name, parent_uuid, kind, |_, _| {
+        Ok(ProjectItemData::Scene {
+            source: Some(source),
+            dynamic_scene: Arc::new(Mutex::new(Some(dynamic_scene))),
+        })
+    })?;
+
+    world
+        .resource_mut::<ProjectDependencies>()
+        .set_references(uuid, references);
+
+    let instance_uuid = Uuid::new_v4();
+    for entity in entities {
+        world.entity_mut(entity).insert(PrefabInstance {
+            prefab_uuid: uuid,
+            instance_uuid,
+        });
+    }
+
+    Ok(())
+}
+
+/// Spawns a new instance of a prefab into the open scene.
+pub(super) fn instantiate_prefab(
+    world: &mut World,
+    prefab_uuid: Uuid,
+) -> Result<(), ProjectErrorKind> {
+    if world.resource::<OpenScene>().scene_uuid.is_none() {
+        return Err(ProjectErrorKind::NoOpenScene);
+    }
+
+    if let Some(open_prefab_uuid) = is_open_item_prefab(world) {
+        return Err(ProjectErrorKind::NestedPrefab(open_prefab_uuid));
+    }
+
+    let (_, source, dynamic_scene) = scene_item_data(world, prefab_uuid)?;
+
+    if !is_prefab(world, prefab_uuid) {
+        return Err(ProjectErrorKind::WrongItemKind {
+            uuid: prefab_uuid,
+            expected: "prefab",
+        });
+    }
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(prefab_uuid))?;
+
+    read_scene_if_needed(world, prefab_uuid, source, &mut dynamic_scene)?;
+
+    let mut entity_map = EntityMap::default();
+
+    let result = dynamic_scene
+        .as_ref()
+        .unwrap()
+        .write_to_world(world, &mut entity_map);
+
+    let instance_uuid = Uuid::new_v4();
+    for entity in entity_map.values() {
+        // Every instance is a new set of entities, not the prefab's own
+        world.entity_mut(entity).insert((
+            EditorItem::default(),
+            EditorId(Uuid::new_v4()),
+            PrefabInstance {
+                prefab_uuid,
+                instance_uuid,
+            },
+        ));
+    }
+    insert_computed_components(world, entity_map.values());
+
+    result.map_err(ProjectErrorKind::SceneSpawn)
+}
+
+/// Gives an id to the entities added to a prefab while it's open for editing.
+pub(super) fn assign_prefab_entity_ids(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, (With<EditorItem>, Without<PrefabEntity>)>()
+        .iter(world)
+        .collect();
+
+    for entity in entities {
+        world
+            .entity_mut(entity)
+            .insert(PrefabEntity(Uuid::new_v4()));
+    }
+}
+
+/// Updates `current` to `new` wherever it still matches `old`, so that fields changed in an
+/// instance are kept while the rest follows the prefab.
+///
+/// The fields of structs, tuples and enums and the entries of maps are merged one by one. The
+/// elements of lists are too as long as no version changed their length, and otherwise a list
+/// changed in an instance is kept whole. An enum only follows a change of variant when the
+/// instance didn't change it.
+fn merge_reflect(current: &mut dyn Reflect, old: &dyn Reflect, new: &dyn Reflect) {
+    let unchanged = current.reflect_partial_eq(old).unwrap_or(false);
+
+    let merged = match (current.reflect_mut(), old.reflect_ref(), new.reflect_ref()) {
+        (ReflectMut::Struct(current), ReflectRef::Struct(old), ReflectRef::Struct(new)) => {
+            for index in 0..new.field_len() {
+                let name = new.name_at(index).unwrap();
+                if let (Some(current_field), Some(old_field)) =
+                    (current.field_mut(name), old.field(name))
+                {
+                    merge_reflect(current_field, old_field, new.field_at(index).unwrap());
+                }
+            }
+            true
+        }
+        (
+            ReflectMut::TupleStruct(current),
+            ReflectRef::TupleStruct(old),
+            ReflectRef::TupleStruct(new),
+        ) => {
+            for index in 0..new.field_len() {
+                if let (Some(current_field), Some(old_field)) =
+                    (current.field_mut(index), old.field(index))
+                {
+                    merge_reflect(current_field, old_field, new.field(index).unwrap());
+                }
+            }
+            true
+        }
+        (ReflectMut::Tuple(current), ReflectRef::Tuple(old), ReflectRef::Tuple(new)) => {
+            for index in 0..new.field_len() {
+                if let (Some(current_field), Some(old_field)) =
+                    (current.field_mut(index), old.field(index))
+                {
+                    merge_reflect(current_field, old_field, new.field(index).unwrap());
+                }
+            }
+            true
+        }
+        (ReflectMut::Enum(current), ReflectRef::Enum(old), ReflectRef::Enum(new))
+            if current.variant_name() == old.variant_name()
+                && old.variant_name() == new.variant_name() =>
+        {
+            // Dynamic enums only give the fields of struct variants by name
+            for index in 0..new.field_len() {
+                let fields = match new.name_at(index) {
+                    Some(name) => (current.field_mut(name), old.field(name), new.field(name)),
+                    None => (
+                        current.field_at_mut(index),
+                        old.field_at(index),
+                        new.field_at(index),
+                    ),
+                };
+
+                if let (Some(current_field), Some(old_field), Some(new_field)) = fields {
+                    merge_reflect(current_field, old_field, new_field);
+                }
+            }
+            true
+        }
+        (ReflectMut::List(current), ReflectRef::List(old), ReflectRef::List(new)) => {
+            // `List::apply` never removes elements, so lengths are handled here
+            if !unchanged && (current.len() != old.len() || old.len() != new.len()) {
+                return;
+            }
+
+            for (index, new_element) in new.iter().enumerate() {
+                if index < old.len() {
+                    merge_reflect(
+                        current.get_mut(index).unwrap(),
+                        old.get(index).unwrap(),
+                        new_element,
+                    );
+                } else {
+                    current.push(new_element.clone_value());
+                }
+            }
+
+            while current.len() > new.len() {
+                current.pop();
+            }
+            true
+        }
+        (ReflectMut::Map(current), ReflectRef::Map(old), ReflectRef::Map(new)) => {
+            for (key, new_value) in new.iter() {
+                match old.get(key) {
+                    Some(old_value) => {
+                        // Otherwise removed from the instance
+                        if let Some(current_value) = current.get_mut(key) {
+                            merge_reflect(current_value, old_value, new_value);
+                        }
+                    }
+                    None if current.get(key).is_none() => {
+                        current.insert_boxed(key.clone_value(), new_value.clone_value());
+                    }
+                    None => {}
+                }
+            }
+
+            // Removed from the prefab, unless changed in the instance
+            let removed: Vec<Box<dyn Reflect>> = old
+                .iter()
+                .filter(|(key, _)| new.get(*key).is_none())
+                .filter(|(key, old_value)| {
+                    current
+                        .get(*key)
+                        .and_then(|current_value| current_value.reflect_partial_eq(*old_value))
+                        .unwrap_or(false)
+                })
+                .map(|(key, _)| key.clone_value())
+                .collect();
+
+            for key in removed {
+                current.remove(&*key);
+            }
+            true
+        }
+        _ => false,
+    };
+
+    if !merged && unchanged {
+        current.apply(new);
+    }
+}
+
+struct PrefabEntities<'a> {
+    entities: HashMap<Uuid, &'a DynamicEntity>,
+    parents: HashMap<Uuid, Uuid>,
+}
+
+impl<'a> PrefabEntities<'a> {
+    fn new(dynamic_scene: &'a DynamicScene) -> Self {
+        let ids: HashMap<Entity, Uuid> = dynamic_scene
+            .entities
+            .iter()
+            .filter_map(|dynamic_entity| {
+                Some((
+                    dynamic_entity.entity,
+                    find_component::<PrefabEntity>(dynamic_entity)?.0,
+                ))
+            })
+            .collect();
+
+        let entities = dynamic_scene
+            .entities
+            .iter()
+            .filter_map(|dynamic_entity| Some((*ids.get(&dynamic_entity.entity)?, dynamic_entity)))
+            .collect();
+
+        let parents = dynamic_scene
+            .entities
+            .iter()
+            .filter_map(|dynamic_entity| {
+                let id = ids.get(&dynamic_entity.entity)?;
+                let parent = find_component::<Parent>(dynamic_entity)?;
+                Some((*id, *ids.get(&parent.get())?))
+            })
+            .collect();
+
+        PrefabEntities { entities, parents }
+    }
+}
+
+fn find_dynamic_component<'a>(
+    dynamic_entity: Option<&&'a DynamicEntity>,
+    type_name: &str,
+) -> Option<&'a dyn Reflect> {
+    dynamic_entity?
+        .components
+        .iter()
+        .find(|component| component.type_name() == type_name)
+        .map(|component| component.as_ref())
+}
+
+fn merge_components(
+    world: &mut World,
+    entity: Entity,
+    old_entity: Option<&&DynamicEntity>,
+    new_entity: &DynamicEntity,
+    type_registry: &AppTypeRegistry,
+) {
+    let type_registry = type_registry.read();
+
+    for component in &new_entity.components {
+        if is_instance_component(component.as_ref()) {
+            continue;
+        }
+
+        let Some(reflect_component) = type_registry
+            .get_with_name(component.type_name())
+            .and_then(|registration| registration.data::<ReflectComponent>())
+        else {
+            continue;
+        };
+
+        let old_component = find_dynamic_component(old_entity, component.type_name());
+        let mut entity_mut = world.entity_mut(entity);
+
+        match (
+            reflect_component.reflect_mut(&mut entity_mut),
+            old_component,
+        ) {
+            (Some(mut current), Some(old_component)) => {
+                merge_reflect(&mut *current, old_component, component.as_ref())
+            }
+            // Added to the prefab, or removed from the instance
+            (None, None) => reflect_component.insert(&mut entity_mut, component.as_ref()),
+            (None, Some(_)) | (Some(_), None) => {}
+        }
+    }
+
+    let Some(old_entity) = old_entity else {
+        return;
+    };
+
+    for old_component in &old_entity.components {
+        if is_instance_component(old_component.as_ref())
+            || find_dynamic_component(Some(&new_entity), old_component.type_name()).is_some()
+        {
+            continue;
+        }
+
+        let Some(reflect_component) = type_registry
+            .get_with_name(old_component.type_name())
+            .and_then(|registration| registration.data::<ReflectComponent>())
+        else {
+            continue;
+        };
+
+        let unchanged = reflect_component
+            .reflect(world.entity(entity))
+            .and_then(|current| current.reflect_partial_eq(old_component.as_ref()))
+            .unwrap_or(false);
+
+        if unchanged {
+            reflect_component.remove(&mut world.entity_mut(entity));
+        }
+    }
+}
+
+/// Brings every instance of a prefab in `world` from its `old` to its `new` version.
+fn apply_prefab_changes(
+    world: &mut World,
+    prefab_uuid: Uuid,
+    old: &DynamicScene,
+    new: &DynamicScene,
+    type_registry: &AppTypeRegistry,
+) {
+    let old = PrefabEntities::new(old);
+    let new = PrefabEntities::new(new);
+
+    let mut instances: HashMap<Uuid, HashMap<Uuid, Entity>> = HashMap::default();
+    for (entity, prefab_instance, prefab_entity) in world
+        .query::<(Entity, &PrefabInstance, &PrefabEntity)>()
+        .iter(world)
+    {
+        if prefab_instance.prefab_uuid == prefab_uuid {
+            instances
+                .entry(prefab_instance.instance_uuid)
+                .or_default()
+                .insert(prefab_entity.0, entity);
+        }
+    }
+
+    for (instance_uuid, mut instance) in instances {
+        instance.retain(|id, entity| {
+            if new.entities.contains_key(id) {
+                return true;
+            }
+
+            if let Some(entity) = world.get_entity_mut(*entity) {
+                entity.despawn_recursive();
+            }
+            false
+        });
+
+        let mut added = Vec::new();
+        for id in new.entities.keys() {
+            if !instance.contains_key(id) {
+                let entity = world
+                    .spawn((
+                        EditorItem::default(),
+                        EditorId(Uuid::new_v4()),
+                        PrefabInstance {
+                            prefab_uuid,
+                            instance_uuid,
+                        },
+                    ))
+                    .id();
+                instance.insert(*id, entity);
+                added.push(*id);
+            }
+        }
+
+        for (id, new_entity) in &new.entities {
+            let entity = instance[id];
+
+            merge_components(
+                world,
+                entity,
+                old.entities.get(id),
+                new_entity,
+                type_registry,
+            );
+
+            let old_parent = old.parents.get(id).and_then(|parent| instance.get(parent));
+            let new_parent = new.parents.get(id).and_then(|parent| instance.get(parent));
+            let current_parent = world.get::<Parent>(entity).map(Parent::get);
+
+            // Entities moved within the instance keep their place
+            let follows_prefab = added.contains(id) || current_parent.as_ref() == old_parent;
+
+            if follows_prefab && old_parent != new_parent {
+                match new_parent {
+                    Some(new_parent) => world.entity_mut(entity).set_parent(*new_parent),
+                    None => world.entity_mut(entity).remove_parent(),
+                };
+            }
+        }
+
+        insert_computed_components(world, added.iter().map(|id| instance[id]));
+    }
+}
+
+fn apply_prefab_changes_to_scene(
+    dynamic_scene: &DynamicScene,
+    prefab_uuid: Uuid,
+    old: &DynamicScene,
+    new: &DynamicScene,
+    type_registry: &AppTypeRegistry,
+) -> Result<DynamicScene, ProjectErrorKind> {
+    let mut scene_world = World::new();
+    scene_world.insert_resource(type_registry.clone());
+
+    dynamic_scene
+        .write_to_world_with(&mut scene_world, &mut EntityMap::default(), type_registry)
+        .map_err(ProjectErrorKind::SceneSpawn)?;
+
+    apply_prefab_changes(&mut scene_world, prefab_uuid, old, new, type_registry);
+
+    let entities: Vec<Entity> = scene_world
+        .iter_entities()
+        .map(|entity| entity.id())
+        .collect();
+    let mut updated_dynamic_scene = extract_stored_scene(&scene_world, entities.into_iter());
+    use_editor_ids(&mut updated_dynamic_scene);
+
+    Ok(updated_dynamic_scene)
+}
+
+/// Propagates the changes made to a prefab to its instances, in the open scene as well as in
+/// the stored scenes that use it.
+pub(super) fn propagate_prefab_changes(
+    world: &mut World,
+    prefab_uuid: Uuid,
+    old: &DynamicScene,
+    new: &DynamicScene,
+) -> Result<(), ProjectErrorKind> {
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+
+    // Prefabs can't be nested, so only scenes use them
+    let scene_uuids = find_usages(world, prefab_uuid)?;
+
+    for scene_uuid in scene_uuids {
+        let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+        let mut dynamic_scene = dynamic_scene
+            .lock()
+            .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+        read_scene_if_needed(world, scene_uuid, source.clone(), &mut dynamic_scene)?;
+
+        let updated_dynamic_scene = apply_prefab_changes_to_scene(
+            dynamic_scene.as_ref().unwrap(),
+            prefab_uuid,
+            old,
+            new,
+            &type_registry,
+        )?;
+
+        if let Some(source) = source {
+            let path = asset_root_path(world.resource::<AssetServer>()).join(source);
+            save_scene_file(&path, &updated_dynamic_scene, &type_registry)
+                .map_err(|err| ProjectErrorKind::SceneWrite(path, err))?;
+        }
+
+        let references = scene_references(world, &updated_dynamic_scene);
+        world
+            .resource_mut::<ProjectDependencies>()
+            .set_references(scene_uuid, references);
+
+        *dynamic_scene = Some(updated_dynamic_scene);
+    }
+
+    if world.resource::<OpenScene>().scene_uuid.is_some() && is_open_item_prefab(world).is_none() {
+        apply_prefab_changes(world, prefab_uuid, old, new, &type_registry);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{send_test_event, test_project_app, ProjectEvent};
+
+    #[derive(Reflect, Clone, Default, PartialEq, Debug)]
+    struct Tint(f32, f32);
+
+    #[derive(Reflect, Clone, Default, PartialEq, Debug)]
+    enum Blinking {
+        #[default]
+        Off,
+        On {
+            period: f32,
+            phase: f32,
+        },
+    }
+
+    #[derive(Component, Reflect, Clone, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Lamp {
+        tint: Tint,
+        blinking: Blinking,
+        intensity: f32,
+    }
+
+    fn test_registry() -> AppTypeRegistry {
+        let type_registry = AppTypeRegistry::default();
+        {
+            let mut type_registry = type_registry.write();
+            type_registry.register::<Transform>();
+            type_registry.register::<Lamp>();
+            type_registry.register::<PrefabEntity>();
+            type_registry.register::<PrefabInstance>();
+            type_registry.register::<EditorId>();
+            type_registry.register::<Parent>();
+            type_registry.register::<Children>();
+        }
+        type_registry
+    }
+
+    fn prefab_version(
+        type_registry: &AppTypeRegistry,
+        prefab_entity: Uuid,
+        lamp: Lamp,
+        transform: Transform,
+    ) -> DynamicScene {
+        let mut world = World::new();
+        world.insert_resource(type_registry.clone());
+        let entity = world
+            .spawn((PrefabEntity(prefab_entity), lamp, transform))
+            .id();
+
+        let mut dynamic_scene_builder = DynamicSceneBuilder::from_world(&world);
+        dynamic_scene_builder.extract_entity(entity);
+        dynamic_scene_builder.build()
+    }
+
+    #[test]
+    fn prefab_changes_keep_instance_overrides() {
+        let type_registry = test_registry();
+        let prefab_uuid = Uuid::new_v4();
+        let prefab_entity = Uuid::new_v4();
+
+        let old_lamp = Lamp {
+            tint: Tint(1.0, 1.0),
+            blinking: Blinking::On {
+                period: 1.0,
+                phase: 0.0,
+            },
+            intensity: 1.0,
+        };
+        let old = prefab_version(
+            &type_registry,
+            prefab_entity,
+            old_lamp.clone(),
+            Transform::IDENTITY,
+        );
+
+        let new_lamp = Lamp {
+            tint: Tint(2.0, 2.0),
+            blinking: Blinking::On {
+                period: 2.0,
+                phase: 0.0,
+            },
+            intensity: 4.0,
+        };
+        let new_transform = Transform::from_xyz(0.0, 1.0, 0.0);
+        let new = prefab_version(
+            &type_registry,
+            prefab_entity,
+            new_lamp.clone(),
+            new_transform,
+        );
+
+        let mut world = World::new();
+        let instance = |instance_uuid| PrefabInstance {
+            prefab_uuid,
+            instance_uuid,
+        };
+
+        let overridden = world
+            .spawn((
+                PrefabEntity(prefab_entity),
+                instance(Uuid::new_v4()),
+                Lamp {
+                    tint: Tint(5.0, 1.0),
+                    blinking: Blinking::On {
+                        period: 1.0,
+                        phase: 0.5,
+                    },
+                    intensity: 1.0,
+                },
+                Transform::from_xyz(3.0, 0.0, 0.0),
+            ))
+            .id();
+        let switched_off = world
+            .spawn((
+                PrefabEntity(prefab_entity),
+                instance(Uuid::new_v4()),
+                Lamp {
+                    blinking: Blinking::Off,
+                    ..old_lamp.clone()
+                },
+                Transform::IDENTITY,
+            ))
+            .id();
+        let untouched = world
+            .spawn((
+                PrefabEntity(prefab_entity),
+                instance(Uuid::new_v4()),
+                old_lamp,
+                Transform::IDENTITY,
+            ))
+            .id();
+
+        apply_prefab_changes(&mut world, prefab_uuid, &old, &new, &type_registry);
+
+        assert_eq!(
+            world.get::<Lamp>(overridden),
+            Some(&Lamp {
+                tint: Tint(5.0, 2.0),
+                blinking: Blinking::On {
+                    period: 2.0,
+                    phase: 0.5,
+                },
+                intensity: 4.0,
+            })
+        );
+        assert_eq!(
+            world.get::<Transform>(overridden),
+            Some(&Transform::from_xyz(3.0, 1.0, 0.0))
+        );
+
+        assert_eq!(
+            world.get::<Lamp>(switched_off),
+            Some(&Lamp {
+                blinking: Blinking::Off,
+                ..new_lamp.clone()
+            })
+        );
+
+        assert_eq!(world.get::<Lamp>(untouched), Some(&new_lamp));
+        assert_eq!(world.get::<Transform>(untouched), Some(&new_transform));
+    }
+
+    #[derive(Reflect, Clone, Default, PartialEq, Debug)]
+    struct Sequence {
+        steps: Vec<f32>,
+        marks: HashMap<String, f32>,
+    }
+
+    fn sequence(steps: &[f32], marks: &[(&str, f32)]) -> Sequence {
+        Sequence {
+            steps: steps.to_vec(),
+            marks: marks
+                .iter()
+                .map(|(name, mark)| (name.to_string(), *mark))
+                .collect(),
+        }
+    }
+
+    fn merged(current: &Sequence, old: &Sequence, new: &Sequence) -> Sequence {
+        let mut current = current.clone();
+        merge_reflect(&mut current, old, new);
+        current
+    }
+
+    #[test]
+    fn lists_and_maps_keep_instance_changes() {
+        let old = sequence(&[1.0, 2.0, 3.0], &[]);
+
+        // An unchanged list follows the prefab whichever way its length changes
+        let shorter = sequence(&[1.0], &[]);
+        assert_eq!(merged(&old, &old, &shorter), shorter);
+        let longer = sequence(&[1.0, 2.0, 3.0, 4.0, 5.0], &[]);
+        assert_eq!(merged(&old, &old, &longer), longer);
+
+        // Lists of the same length are merged element by element
+        assert_eq!(
+            merged(
+                &sequence(&[1.0, 5.0, 3.0], &[]),
+                &old,
+                &sequence(&[4.0, 2.0, 3.0], &[])
+            ),
+            sequence(&[4.0, 5.0, 3.0], &[])
+        );
+
+        // A list changed in the instance is kept whole once the lengths differ
+        let changed = sequence(&[1.0, 5.0, 3.0], &[]);
+        assert_eq!(merged(&changed, &old, &shorter), changed);
+        let grown = sequence(&[1.0, 2.0, 3.0, 6.0], &[]);
+        assert_eq!(
+            merged(&grown, &old, &sequence(&[4.0, 2.0, 3.0], &[])),
+            grown
+        );
+
+        assert_eq!(
+            merged(
+                &sequence(&[], &[("a", 1.0), ("b", 5.0), ("c", 3.0)]),
+                &sequence(&[], &[("a", 1.0), ("b", 2.0), ("c", 3.0)]),
+                &sequence(&[], &[("a", 10.0), ("b", 20.0), ("d", 4.0)]),
+            ),
+            sequence(&[], &[("a", 10.0), ("b", 5.0), ("d", 4.0)])
+        );
+
+        // Entries removed from the instance stay removed, and changed ones are kept
+        assert_eq!(
+            merged(
+                &sequence(&[], &[("b", 5.0)]),
+                &sequence(&[], &[("a", 1.0), ("b", 2.0)]),
+                &sequence(&[], &[("a", 10.0)]),
+            ),
+            sequence(&[], &[("b", 5.0)])
+        );
+    }
+
+    fn hierarchy_version(
+        type_registry: &AppTypeRegistry,
+        entities: &[(Uuid, Option<Uuid>)],
+    ) -> DynamicScene {
+        let mut world = World::new();
+        world.insert_resource(type_registry.clone());
+
+        let spawned: HashMap<Uuid, Entity> = entities
+            .iter()
+            .map(|(id, _)| (*id, world.spawn(PrefabEntity(*id)).id()))
+            .collect();
+
+        for (id, parent) in entities {
+            if let Some(parent) = parent {
+                world.entity_mut(spawned[id]).set_parent(spawned[parent]);
+            }
+        }
+
+        let mut dynamic_scene_builder = DynamicSceneBuilder::from_world(&world);
+        dynamic_scene_builder.extract_entities(spawned.values().copied());
+        dynamic_scene_builder.build()
+    }
+
+    fn spawn_instance(
+        world: &mut World,
+        prefab_uuid: Uuid,
+        entities: &[(Uuid, Option<Uuid>)],
+    ) -> HashMap<Uuid, Entity> {
+        let instance_uuid = Uuid::new_v4();
+
+        let spawned: HashMap<Uuid, Entity> = entities
+            .iter()
+            .map(|(id, _)| {
+                let entity = world
+                    .spawn((
+                        PrefabEntity(*id),
+                        PrefabInstance {
+                            prefab_uuid,
+                            instance_uuid,
+                        },
+                    ))
+                    .id();
+                (*id, entity)
+            })
+            .collect();
+
+        for (id, parent) in entities {
+            if let Some(parent) = parent {
+                world.entity_mut(spawned[id]).set_parent(spawned[parent]);
+            }
+        }
+
+        spawned
+    }
+
+    /// Finds the entity `id` of the instance that `member` is part of.
+    fn instance_entity(world: &mut World, member: Entity, id: Uuid) -> Entity {
+        let instance_uuid = world.get::<PrefabInstance>(member).unwrap().instance_uuid;
+
+        world
+            .query::<(Entity, &PrefabEntity, &PrefabInstance)>()
+            .iter(world)
+            .find(|(_, prefab_entity, prefab_instance)| {
+                prefab_entity.0 == id && prefab_instance.instance_uuid == instance_uuid
+            })
+            .map(|(entity, _, _)| entity)
+            .unwrap()
+    }
+
+    #[test]
+    fn prefab_hierarchy_changes_keep_moved_entities() {
+        let type_registry = test_registry();
+        let prefab_uuid = Uuid::new_v4();
+        let [r, a, b, n, x] = [(); 5].map(|_| Uuid::new_v4());
+
+        let old_entities = [(r, None), (a, Some(r)), (b, Some(r)), (x, Some(a))];
+        let old = hierarchy_version(&type_registry, &old_entities);
+        // `x` is removed, and `b` is moved under the new `n`
+        let new = hierarchy_version(
+            &type_registry,
+            &[(r, None), (a, Some(r)), (n, Some(r)), (b, Some(n))],
+        );
+
+        let mut world = World::new();
+        let untouched = spawn_instance(&mut world, prefab_uuid, &old_entities);
+        let moved = spawn_instance(&mut world, prefab_uuid, &old_entities);
+        world.entity_mut(moved[&b]).set_parent(moved[&a]);
+
+        apply_prefab_changes(&mut world, prefab_uuid, &old, &new, &type_registry);
+
+        let parent = |world: &World, entity| world.get::<Parent>(entity).map(Parent::get);
+
+        let untouched_n = instance_entity(&mut world, untouched[&r], n);
+        assert_eq!(parent(&world, untouched_n), Some(untouched[&r]));
+        assert_eq!(parent(&world, untouched[&b]), Some(untouched_n));
+        assert!(world.get_entity(untouched[&x]).is_none());
+
+        let moved_n = instance_entity(&mut world, moved[&r], n);
+        assert_eq!(parent(&world, moved_n), Some(moved[&r]));
+        assert_eq!(parent(&world, moved[&b]), Some(moved[&a]));
+        assert!(world.get_entity(moved[&x]).is_none());
+    }
+
+    fn stored_lamp(world: &World, scene_uuid: Uuid) -> Lamp {
+        let (_, _, dynamic_scene) = scene_item_data(world, scene_uuid).unwrap();
+        let dynamic_scene = dynamic_scene.lock().unwrap();
+
+        dynamic_scene
+            .as_ref()
+            .unwrap()
+            .entities
+            .iter()
+            .find_map(find_component::<Lamp>)
+            .unwrap()
+    }
+
+    #[test]
+    fn prefab_changes_propagate_to_stored_scenes() {
+        let mut app = test_project_app();
+        app.register_type::<Lamp>();
+
+        let [level_uuid, other_uuid, prefab_uuid] = [(); 3].map(|_| Uuid::new_v4());
+        let load_scene = |scene_uuid| ProjectEvent::LoadScene {
+            scene_uuid,
+            discard_changes: true,
+        };
+
+        send_test_event(
+            &mut app,
+            ProjectEvent::CreateScene {
+                uuid: level_uuid,
+                name: "Level".to_string(),
+                parent_uuid: None,
+            },
+        );
+        send_test_event(&mut app, load_scene(level_uuid));
+
+        let lamp = app
+            .world
+            .spawn((
+                EditorItem {
+                    is_selected: true,
+                    ..default()
+                },
+                Lamp::default(),
+                Transform::IDENTITY,
+            ))
+            .id();
+        send_test_event(
+            &mut app,
+            ProjectEvent::CreatePrefab {
+                uuid: prefab_uuid,
+                name: "Lamp".to_string(),
+                parent_uuid: None,
+            },
+        );
+        app.world.get_mut::<Lamp>(lamp).unwrap().tint = Tint(5.0, 1.0);
+        send_test_event(
+            &mut app,
+            ProjectEvent::StoreScene {
+                scene_uuid: level_uuid,
+            },
+        );
+
+        send_test_event(
+            &mut app,
+            ProjectEvent::CreateScene {
+                uuid: other_uuid,
+                name: "Other".to_string(),
+                parent_uuid: None,
+            },
+        );
+        send_test_event(&mut app, load_scene(other_uuid));
+        send_test_event(
+            &mut app,
+            ProjectEvent::StoreScene {
+                scene_uuid: other_uuid,
+            },
+        );
+
+        // Rewriting the scene without instances would bring its file back
+        let asset_root = asset_root_path(app.world.resource::<AssetServer>());
+        let (_, other_source, _) = scene_item_data(&app.world, other_uuid).unwrap();
+        let other_path = asset_root.join(other_source.unwrap());
+        std::fs::remove_file(&other_path).unwrap();
+
+        send_test_event(&mut app, load_scene(prefab_uuid));
+        let mut lamps = app.world.query::<&mut Lamp>();
+        lamps.single_mut(&mut app.world).intensity = 4.0;
+        send_test_event(
+            &mut app,
+            ProjectEvent::StoreScene {
+                scene_uuid: prefab_uuid,
+            },
+        );
+
+        assert_eq!(
+            stored_lamp(&app.world, level_uuid),
+            Lamp {
+                tint: Tint(5.0, 1.0),
+                intensity: 4.0,
+                ..default()
+            }
+        );
+        assert!(!other_path.exists());
+
+        std::fs::remove_dir_all(asset_root).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bevy::app::AppExit;
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::editor::EditorItem;
+
+use super::editor_id::use_editor_ids;
+use super::scene_file::{
+    extract_stored_scene, insert_computed_components, load_scene_file, save_scene_file,
+};
+use super::{
+    asset_root_path, build_project_manifest, despawn_editor_items, load_project, scene_item_data,
+    OpenScene, ProjectErrorKind, ProjectItem, ProjectItemData, ProjectItemRegistry,
+};
+
+const AUTOSAVE_INTERVAL_SECONDS: f32 = 30.0;
+
+const INDEX_FILE: &str = "recovery.ron";
+const PROJECT_FILE: &str = "project.ron";
+const SCENE_FILE: &str = "scene.scn.ron";
+/// Where the snapshot of a previous session is set aside while it's pending, within the
+/// autosave directory.
+const PENDING_DIRECTORY: &str = "pending";
+
+#[derive(Resource)]
+pub struct Autosave {
+    directory: PathBuf,
+    timer: Timer,
+    /// Where the project was last saved to or loaded from.
+    project_path: Option<PathBuf>,
+    /// The project manifest as it was last saved or loaded, to tell when it has changed.
+    saved_manifest: Option<String>,
+}
+
+impl Autosave {
+    pub fn new(directory: PathBuf) -> Self {
+        Autosave {
+            directory,
+            timer: Timer::from_seconds(AUTOSAVE_INTERVAL_SECONDS, TimerMode::Repeating),
+            project_path: None,
+            saved_manifest: None,
+        }
+    }
+}
+
+/// Describes the files of a recovery snapshot, and is written after them.
+#[derive(Serialize, Deserialize)]
+struct RecoveryIndex {
+    project_path: Option<PathBuf>,
+    scene: Option<RecoveredScene>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecoveredScene {
+    uuid: Uuid,
+    source: Option<String>,
+}
+
+/// Unsaved work autosaved by a previous session that didn't exit cleanly, found on startup.
+///
+/// The snapshot is set aside while this resource exists, so that autosave keeps snapshotting the
+/// current session, until the work is restored with
+/// [`ProjectEvent::RestoreRecovery`](super::ProjectEvent::RestoreRecovery) or discarded with
+/// [`ProjectEvent::DiscardRecovery`](super::ProjectEvent::DiscardRecovery). If neither happens,
+/// it's offered again on the next startup.
+#[derive(Resource)]
+pub struct RecoverySnapshot {
+    /// Where the recovered project was saved, if it ever was.
+    pub project_path: Option<PathBuf>,
+    /// The scene that was open with unsaved changes.
+    pub scene_uuid: Option<Uuid>,
+}
+
+fn write_error(path: &Path) -> impl FnOnce(io::Error) -> ProjectErrorKind + '_ {
+    move |err| ProjectErrorKind::RecoveryWrite(path.to_path_buf(), err)
+}
+
+/// Remembers where the project was saved to or loaded from, and what it looked like then.
+pub(super) fn track_saved_project(world: &mut World, path: &Path) {
+    if !world.contains_resource::<Autosave>() {
+        return;
+    }
+
+    let manifest = build_project_manifest(world);
+    let serialized = manifest
+        .serialize_ron(&world.resource::<AppTypeRegistry>().read())
+        .ok();
+
+    let mut autosave = world.resource_mut::<Autosave>();
+    autosave.project_path = Some(path.to_path_buf());
+    autosave.saved_manifest = serialized;
+}
+
+fn remove_snapshot(directory: &Path) -> Result<(), ProjectErrorKind> {
+    // The index goes first, so that a partly removed snapshot is never picked up
+    for file in [INDEX_FILE, PROJECT_FILE, SCENE_FILE] {
+        let path = directory.join(file);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(ProjectErrorKind::RecoveryWrite(path, err))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the snapshot in `from` to `to`, replacing the one there.
+fn move_snapshot(from: &Path, to: &Path) -> Result<(), ProjectErrorKind> {
+    remove_snapshot(to)?;
+    fs::create_dir_all(to).map_err(write_error(to))?;
+
+    // The index goes last, so that a partly moved snapshot is never picked up
+    for file in [SCENE_FILE, PROJECT_FILE, INDEX_FILE] {
+        let path = from.join(file);
+        match fs::rename(&path, to.join(file)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(ProjectErrorKind::RecoveryWrite(path, err))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The open scene, if it has unsaved changes.
+fn dirty_scene(world: &World) -> Option<RecoveredScene> {
+    let scene_uuid = world.resource::<OpenScene>().scene_uuid?;
+    let entity = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .get(&scene_uuid)?;
+    let project_item = world.get::<ProjectItem>(*entity)?;
+
+    match &project_item.data {
+        ProjectItemData::Scene { source, .. } if project_item.has_unsaved_changes => {
+            Some(RecoveredScene {
+                uuid: scene_uuid,
+                source: source.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Snapshots the project and the open scene into the recovery directory when either has
+/// unsaved changes, and removes the snapshot otherwise.
+fn write_snapshot(world: &mut World) -> Result<(), ProjectErrorKind> {
+    let directory = world.resource::<Autosave>().directory.clone();
+
+    let manifest = build_project_manifest(world);
+    let project_path = directory.join(PROJECT_FILE);
+    let serialized = manifest
+        .serialize_ron(&world.resource::<AppTypeRegistry>().read())
+        .map_err(|err| ProjectErrorKind::ManifestWrite(project_path.clone(), err))?;
+
+    let scene = dirty_scene(world);
+    let autosave = world.resource::<Autosave>();
+    if scene.is_none() && autosave.saved_manifest.as_ref() == Some(&serialized) {
+        return remove_snapshot(&directory);
+    }
+
+    let index = RecoveryIndex {
+        project_path: autosave.project_path.clone(),
+        scene,
+    };
+
+    fs::create_dir_all(&directory).map_err(write_error(&directory))?;
+
+    // The project is snapshotted along with the scene, so that it can be restored on its own
+    fs::write(&project_path, serialized).map_err(write_error(&project_path))?;
+
+    let scene_path = directory.join(SCENE_FILE);
+    if index.scene.is_some() {
+        let mut query = world.query_filtered::<Entity, With<EditorItem>>();
+        let mut dynamic_scene = extract_stored_scene(world, query.iter(world));
+        use_editor_ids(&mut dynamic_scene);
+
+        save_scene_file(
+            &scene_path,
+            &dynamic_scene,
+            world.resource::<AppTypeRegistry>(),
+        )
+        .map_err(|err| ProjectErrorKind::SceneWrite(scene_path, err))?;
+    } else if scene_path.exists() {
+        fs::remove_file(&scene_path).map_err(write_error(&scene_path))?;
+    }
+
+    let index_path = directory.join(INDEX_FILE);
+    let serialized_index =
+        ron::ser::to_string_pretty(&index, PrettyConfig::default()).map_err(|err| {
+            ProjectErrorKind::RecoveryWrite(
+                index_path.clone(),
+                io::Error::new(io::ErrorKind::InvalidData, err),
+            )
+        })?;
+    fs::write(&index_path, serialized_index).map_err(write_error(&index_path))
+}
+
+/// Periodically snapshots unsaved work.
+pub(super) fn autosave(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    if !world
+        .resource_mut::<Autosave>()
+        .timer
+        .tick(delta)
+        .just_finished()
+    {
+        return;
+    }
+
+    if let Err(err) = write_snapshot(world) {
+        warn!("Failed to autosave: {}", err);
+    }
+}
+
+/// Removes the snapshot of the current session when the editor exits normally, as only crashes
+/// should leave one.
+pub(super) fn remove_snapshot_on_exit(
+    mut app_exit_events: EventReader<AppExit>,
+    autosave: Res<Autosave>,
+) {
+    if app_exit_events.iter().next().is_none() {
+        return;
+    }
+
+    if let Err(err) = remove_snapshot(&autosave.directory) {
+        warn!("Failed to remove the recovery snapshot: {}", err);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn read_index(path: &Path) -> Result<RecoveryIndex, String> {
+    let input = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    ron::from_str(&input).map_err(|err| err.to_string())
+}
+
+/// Reads the snapshot in `directory`, removing it if it's older than the files it was taken
+/// from.
+fn read_fresh_snapshot(world: &World, directory: &Path) -> Option<RecoveryIndex> {
+    let index_path = directory.join(INDEX_FILE);
+    let snapshot_modified = modified(&index_path)?;
+
+    let index = match read_index(&index_path) {
+        Ok(index) => index,
+        Err(message) => {
+            warn!(
+                "Ignoring unreadable recovery snapshot {}: {}",
+                index_path.display(),
+                message
+            );
+            return None;
+        }
+    };
+
+    let asset_root = asset_root_path(world.resource::<AssetServer>());
+    let scene_source = index
+        .scene
+        .as_ref()
+        .and_then(|scene| scene.source.as_ref())
+        .map(|source| asset_root.join(source));
+
+    let is_stale = index
+        .project_path
+        .iter()
+        .chain(scene_source.iter())
+        .filter_map(|path| modified(path))
+        .any(|saved_modified| saved_modified >= snapshot_modified);
+
+    if is_stale {
+        info!("Removing recovery snapshot older than the saved project");
+        if let Err(err) = remove_snapshot(directory) {
+            warn!("Failed to remove the recovery snapshot: {}", err);
+        }
+        return None;
+    }
+
+    Some(index)
+}
+
+/// Looks for a snapshot left by a previous session, and offers it for restoring if it's newer
+/// than the files it was taken from.
+///
+/// A snapshot left by a crash replaces one still pending from an earlier session, and is set
+/// aside so that it isn't overwritten by autosave.
+pub(super) fn detect_recovery_snapshot(world: &mut World) {
+    let directory = world.resource::<Autosave>().directory.clone();
+    let pending_directory = directory.join(PENDING_DIRECTORY);
+
+    let index = match read_fresh_snapshot(world, &directory) {
+        Some(index) => {
+            if let Err(err) = move_snapshot(&directory, &pending_directory) {
+                warn!("Failed to set the recovery snapshot aside: {}", err);
+                return;
+            }
+            index
+        }
+        None => match read_fresh_snapshot(world, &pending_directory) {
+            Some(index) => index,
+            None => return,
+        },
+    };
+
+    warn!(
+        "Found unsaved work from a previous session in {}, which can be restored or discarded",
+        pending_directory.display()
+    );
+
+    world.insert_resource(RecoverySnapshot {
+        project_path: index.project_path,
+        scene_uuid: index.scene.map(|scene| scene.uuid),
+    });
+}
+
+/// Replaces the editor entities with those of the snapshotted scene, leaving its changes unsaved.
+fn restore_scene(world: &mut World, scene_uuid: Uuid, path: &Path) -> Result<(), ProjectErrorKind> {
+    scene_item_data(world, scene_uuid)?;
+
+    let dynamic_scene = load_scene_file(path, world.resource::<AppTypeRegistry>())
+        .map_err(|err| ProjectErrorKind::SceneRead(path.to_path_buf(), err))?;
+
+    despawn_editor_items(world);
+
+    let mut entity_map = EntityMap::default();
+    let result = dynamic_scene.write_to_world(world, &mut entity_map);
+
+    for entity in entity_map.values() {
+        world.entity_mut(entity).insert(EditorItem::default());
+    }
+    insert_computed_components(world, entity_map.values());
+
+    // Nothing was spawned from the stored scene, so entities are only matched by their id
+    let mut open_scene = world.resource_mut::<OpenScene>();
+    open_scene.scene_uuid = Some(scene_uuid);
+    open_scene.stored_entities = EntityMap::default();
+
+    result.map_err(ProjectErrorKind::SceneSpawn)
+}
+
+fn restore_snapshot(
+    world: &mut World,
+    recovery_snapshot: &RecoverySnapshot,
+) -> Result<(), ProjectErrorKind> {
+    let directory = world
+        .resource::<Autosave>()
+        .directory
+        .join(PENDING_DIRECTORY);
+
+    load_project(world, &directory.join(PROJECT_FILE))?;
+
+    // Saving still goes to the original project, which doesn't have the restored work yet
+    let mut autosave = world.resource_mut::<Autosave>();
+    autosave.project_path = recovery_snapshot.project_path.clone();
+    autosave.saved_manifest = None;
+
+    if let Some(scene_uuid) = recovery_snapshot.scene_uuid {
+        restore_scene(world, scene_uuid, &directory.join(SCENE_FILE))?;
+    }
+
+    Ok(())
+}
+
+/// Restores the pending snapshot, which is kept pending if that fails.
+///
+/// The restored work is left unsaved, so autosave snapshots it again in its place.
+pub(super) fn restore_recovery_snapshot(world: &mut World) -> Result<(), ProjectErrorKind> {
+    let recovery_snapshot = world
+        .remove_resource::<RecoverySnapshot>()
+        .ok_or(ProjectErrorKind::NoRecoverySnapshot)?;
+
+    if let Err(err) = restore_snapshot(world, &recovery_snapshot) {
+        world.insert_resource(recovery_snapshot);
+        return Err(err);
+    }
+
+    remove_snapshot(
+        &world
+            .resource::<Autosave>()
+            .directory
+            .join(PENDING_DIRECTORY),
+    )
+}
+
+/// Discards the pending snapshot.
+pub(super) fn discard_recovery_snapshot(world: &mut World) -> Result<(), ProjectErrorKind> {
+    world
+        .remove_resource::<RecoverySnapshot>()
+        .ok_or(ProjectErrorKind::NoRecoverySnapshot)?;
+
+    remove_snapshot(
+        &world
+            .resource::<Autosave>()
+            .directory
+            .join(PENDING_DIRECTORY),
+    )
+}
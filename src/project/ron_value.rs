@@ -0,0 +1,624 @@
+use std::fmt;
+
+/// Untyped RON value that, unlike `ron::Value`, keeps the names of structs and enum variants, so
+/// manifest migrations and scene merges can tell apart variants of the same shape.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RonValue {
+    Bool(bool),
+    /// A number, kept as written.
+    Number(String),
+    Char(char),
+    String(String),
+    Option(Option<Box<RonValue>>),
+    List(Vec<RonValue>),
+    Map(Vec<(RonValue, RonValue)>),
+    /// `()`, or a unit struct or variant such as `Folder`.
+    Unit(Option<String>),
+    Tuple(Option<String>, Vec<RonValue>),
+    Struct(Option<String>, Vec<(String, RonValue)>),
+}
+
+impl RonValue {
+    pub fn parse(input: &str) -> Result<RonValue, String> {
+        let mut parser = RonParser { input, position: 0 };
+        let value = parser.value()?;
+
+        parser.skip_whitespace()?;
+        if parser.position < input.len() {
+            return Err(parser.error("trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&RonValue> {
+        match self {
+            RonValue::Struct(_, fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut RonValue> {
+        match self {
+            RonValue::Struct(_, fields) => fields
+                .iter_mut()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn remove_field(&mut self, name: &str) -> Option<RonValue> {
+        match self {
+            RonValue::Struct(_, fields) => {
+                let index = fields.iter().position(|(field, _)| field == name)?;
+                Some(fields.remove(index).1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Sets the `name` field of a struct, adding it if it's missing.
+    pub fn set_field(&mut self, name: &str, value: RonValue) {
+        if let Some(field) = self.field_mut(name) {
+            *field = value;
+        } else if let RonValue::Struct(_, fields) = self {
+            fields.push((name.to_string(), value));
+        }
+    }
+}
+
+fn write_separated<T>(
+    f: &mut fmt::Formatter<'_>,
+    items: &[T],
+    mut write_item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+        }
+        write_item(f, item)?;
+    }
+    Ok(())
+}
+
+fn write_name(f: &mut fmt::Formatter<'_>, name: &Option<String>) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "{}", name),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for RonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonValue::Bool(value) => write!(f, "{}", value),
+            RonValue::Number(value) => write!(f, "{}", value),
+            RonValue::Char(value) => write!(f, "'{}'", value.escape_default()),
+            RonValue::String(value) => {
+                write!(f, "\"")?;
+                for c in value.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            RonValue::Option(None) => write!(f, "None"),
+            RonValue::Option(Some(value)) => write!(f, "Some({})", value),
+            RonValue::List(values) => {
+                write!(f, "[")?;
+                write_separated(f, values, |f, value| write!(f, "{}", value))?;
+                write!(f, "]")
+            }
+            RonValue::Map(entries) => {
+                write!(f, "{{")?;
+                write_separated(f, entries, |f, (key, value)| write!(f, "{}:{}", key, value))?;
+                write!(f, "}}")
+            }
+            RonValue::Unit(Some(name)) => write!(f, "{}", name),
+            RonValue::Unit(None) => write!(f, "()"),
+            RonValue::Tuple(name, values) => {
+                write_name(f, name)?;
+                write!(f, "(")?;
+                write_separated(f, values, |f, value| write!(f, "{}", value))?;
+                write!(f, ")")
+            }
+            RonValue::Struct(name, fields) => {
+                write_name(f, name)?;
+                write!(f, "(")?;
+                write_separated(f, fields, |f, (field, value)| {
+                    write!(f, "{}:{}", field, value)
+                })?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+struct RonParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> RonParser<'a> {
+    fn error(&self, message: &str) -> String {
+        let line = self.input[..self.position].matches('\n').count() + 1;
+        format!("{} at line {}", message, line)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), String> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.position += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                let end = rest
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated comment"))?;
+                self.position += end + 2;
+            } else if rest.starts_with("#![") {
+                let end = rest
+                    .find(']')
+                    .ok_or_else(|| self.error("unterminated attribute"))?;
+                self.position += end + 1;
+            } else if self.peek().is_some_and(char::is_whitespace) {
+                self.next();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips whitespace and consumes `c` if it's next.
+    fn eat(&mut self, c: char) -> Result<bool, String> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(c) {
+            self.next();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c)? {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let first = rest.chars().next()?;
+        if !(first.is_alphabetic() || first == '_') {
+            return None;
+        }
+
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.position += length;
+        Some(&rest[..length])
+    }
+
+    /// Parses the comma separated values of a list, map, tuple or struct, up to `end`.
+    fn separated(
+        &mut self,
+        end: char,
+        mut parse_item: impl FnMut(&mut Self) -> Result<(), String>,
+    ) -> Result<(), String> {
+        loop {
+            if self.eat(end)? {
+                return Ok(());
+            }
+            parse_item(self)?;
+            if !self.eat(',')? {
+                return self.expect(end);
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        let c = match self.next() {
+            Some('\'') => '\'',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('x') => {
+                let digits = self
+                    .rest()
+                    .get(..2)
+                    .ok_or_else(|| self.error("bad escape"))?;
+                self.position += 2;
+                u8::from_str_radix(digits, 16).map_err(|_| self.error("bad escape"))? as char
+            }
+            Some('u') => {
+                self.expect('{')?;
+                let rest = self.rest();
+                let end = rest.find('}').ok_or_else(|| self.error("bad escape"))?;
+                self.position += end + 1;
+                u32::from_str_radix(&rest[..end], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("bad escape"))?
+            }
+            _ => return Err(self.error("unknown escape")),
+        };
+        Ok(c)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.escape()?),
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn raw_string(&mut self) -> Result<String, String> {
+        let hashes = self.rest().find(|c| c != '#').unwrap_or(0);
+        self.position += hashes;
+        if self.next() != Some('"') {
+            return Err(self.error("expected `\"`"));
+        }
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let rest = self.rest();
+        let end = rest
+            .find(&terminator)
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.position += end + terminator.len();
+        Ok(rest[..end].to_string())
+    }
+
+    /// Parses the parenthesized part of a tuple, struct or unit.
+    fn group(&mut self, name: Option<String>) -> Result<RonValue, String> {
+        self.expect('(')?;
+        if self.eat(')')? {
+            return Ok(RonValue::Unit(name));
+        }
+
+        let start = self.position;
+        let is_struct = self.identifier().is_some() && self.eat(':')?;
+        self.position = start;
+
+        if is_struct {
+            let mut fields = Vec::new();
+            self.separated(')', |parser| {
+                parser.skip_whitespace()?;
+                let field = parser
+                    .identifier()
+                    .ok_or_else(|| parser.error("expected a field name"))?;
+                parser.expect(':')?;
+                fields.push((field.to_string(), parser.value()?));
+                Ok(())
+            })?;
+            Ok(RonValue::Struct(name, fields))
+        } else {
+            let mut values = Vec::new();
+            self.separated(')', |parser| {
+                values.push(parser.value()?);
+                Ok(())
+            })?;
+            Ok(RonValue::Tuple(name, values))
+        }
+    }
+
+    fn value(&mut self) -> Result<RonValue, String> {
+        self.skip_whitespace()?;
+
+        match self.peek() {
+            Some('"') => {
+                self.next();
+                Ok(RonValue::String(self.string()?))
+            }
+            Some('r') if matches!(self.rest().chars().nth(1), Some('"' | '#')) => {
+                self.next();
+                Ok(RonValue::String(self.raw_string()?))
+            }
+            Some('\'') => {
+                self.next();
+                let c = match self.next() {
+                    Some('\\') => self.escape()?,
+                    Some(c) => c,
+                    None => return Err(self.error("unterminated char")),
+                };
+                if self.next() != Some('\'') {
+                    return Err(self.error("expected `'`"));
+                }
+                Ok(RonValue::Char(c))
+            }
+            Some('[') => {
+                self.next();
+                let mut values = Vec::new();
+                self.separated(']', |parser| {
+                    values.push(parser.value()?);
+                    Ok(())
+                })?;
+                Ok(RonValue::List(values))
+            }
+            Some('{') => {
+                self.next();
+                let mut entries = Vec::new();
+                self.separated('}', |parser| {
+                    let key = parser.value()?;
+                    parser.expect(':')?;
+                    entries.push((key, parser.value()?));
+                    Ok(())
+                })?;
+                Ok(RonValue::Map(entries))
+            }
+            Some('(') => self.group(None),
+            Some(c) if c.is_ascii_digit() || matches!(c, '+' | '-' | '.') => {
+                let rest = self.rest();
+                let length = rest
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')))
+                    .unwrap_or(rest.len());
+                self.position += length;
+                Ok(RonValue::Number(rest[..length].to_string()))
+            }
+            _ => {
+                let identifier = self
+                    .identifier()
+                    .ok_or_else(|| self.error("expected a value"))?;
+
+                match identifier {
+                    "true" => Ok(RonValue::Bool(true)),
+                    "false" => Ok(RonValue::Bool(false)),
+                    "inf" | "NaN" => Ok(RonValue::Number(identifier.to_string())),
+                    "None" => Ok(RonValue::Option(None)),
+                    "Some" => {
+                        self.expect('(')?;
+                        let value = self.value()?;
+                        self.eat(',')?;
+                        self.expect(')')?;
+                        Ok(RonValue::Option(Some(Box::new(value))))
+                    }
+                    name => {
+                        self.skip_whitespace()?;
+                        if self.peek() == Some('(') {
+                            self.group(Some(name.to_string()))
+                        } else {
+                            Ok(RonValue::Unit(Some(name.to_string())))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"(
+  resources: {},
+  entities: {
+    4294967296: (
+      components: {
+        "bevy_asset::handle::Handle<bevy_pbr::pbr_material::StandardMaterial>": (
+          id: Id("7494888b-c082-457b-aacf-517228cc0c22", 13122312082630577088),
+        ),
+        "bevy_transform::components::transform::Transform": (
+          translation: (
+            x: -1.5,
+            y: 0.0,
+            z: 2e-3,
+          ),
+          rotation: (0.0, 0.0, 0.0, 1.0),
+          scale: (
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+          ),
+        ),
+        "bevy_render::view::visibility::Visibility": Inherited,
+        "bevy_hierarchy::components::children::Children": ([
+          2,
+        ]),
+        "bevy_render::camera::projection::Projection": Perspective((
+          fov: 0.7853982,
+          aspect_ratio: 1.0,
+          near: 0.1,
+          far: 1000.0,
+        )),
+        "bevy_render::camera::camera::CameraRenderGraph": ("core_3d"),
+        "bevy_core::name::Name": (
+          hash: 16271177081088133783,
+          name: "Lamp // not a comment",
+        ),
+      },
+    ),
+  },
+)"#;
+
+    fn assert_round_trips(input: &str) -> RonValue {
+        let value = RonValue::parse(input).unwrap();
+        let printed = value.to_string();
+
+        assert_eq!(RonValue::parse(&printed).unwrap(), value, "{}", printed);
+        value
+    }
+
+    #[test]
+    fn scenes_round_trip() {
+        let scene = assert_round_trips(SCENE);
+
+        let Some(RonValue::Map(entities)) = scene.field("entities") else {
+            panic!("entities aren't a map");
+        };
+        let (entity, value) = &entities[0];
+        assert_eq!(entity, &RonValue::Number("4294967296".to_string()));
+
+        let Some(RonValue::Map(components)) = value.field("components") else {
+            panic!("components aren't a map");
+        };
+        let component = |name: &str| {
+            components
+                .iter()
+                .find(|(key, _)| key == &RonValue::String(name.to_string()))
+                .map(|(_, value)| value)
+                .unwrap()
+        };
+
+        assert_eq!(
+            component("bevy_asset::handle::Handle<bevy_pbr::pbr_material::StandardMaterial>")
+                .field("id"),
+            Some(&RonValue::Tuple(
+                Some("Id".to_string()),
+                vec![
+                    RonValue::String("7494888b-c082-457b-aacf-517228cc0c22".to_string()),
+                    RonValue::Number("13122312082630577088".to_string()),
+                ]
+            ))
+        );
+        assert_eq!(
+            component("bevy_render::view::visibility::Visibility"),
+            &RonValue::Unit(Some("Inherited".to_string()))
+        );
+        assert_eq!(
+            component("bevy_render::camera::camera::CameraRenderGraph"),
+            &RonValue::Tuple(None, vec![RonValue::String("core_3d".to_string())])
+        );
+        assert_eq!(
+            component("bevy_core::name::Name").field("name"),
+            Some(&RonValue::String("Lamp // not a comment".to_string()))
+        );
+
+        let Some(RonValue::Struct(None, translation)) =
+            component("bevy_transform::components::transform::Transform").field("translation")
+        else {
+            panic!("translation isn't a struct");
+        };
+        assert_eq!(translation[0].1, RonValue::Number("-1.5".to_string()));
+        assert_eq!(translation[2].1, RonValue::Number("2e-3".to_string()));
+    }
+
+    #[test]
+    fn strings_keep_their_escapes() {
+        let value =
+            assert_round_trips(r#""quote \" backslash \\ tab \t line \n \u{e9} \x41 \u{1}""#);
+
+        assert_eq!(
+            value,
+            RonValue::String("quote \" backslash \\ tab \t line \n é A \u{1}".to_string())
+        );
+        assert_eq!(assert_round_trips(r"'\''"), RonValue::Char('\''));
+        assert!(RonValue::parse(r#""\q""#).is_err());
+        assert!(RonValue::parse(r#""unterminated"#).is_err());
+    }
+
+    #[test]
+    fn raw_strings_are_read_verbatim() {
+        assert_eq!(
+            assert_round_trips(r#"r"C:\assets\n""#),
+            RonValue::String(r"C:\assets\n".to_string())
+        );
+        assert_eq!(
+            assert_round_trips(r###"r##"say "#hi"# "##"###),
+            RonValue::String(r##"say "#hi"# "##.to_string())
+        );
+        assert!(RonValue::parse(r##"r#"unterminated""##).is_err());
+    }
+
+    #[test]
+    fn nested_maps_keep_their_order() {
+        let value = assert_round_trips(r#"{"b": {"c": [1, 2,], "a": {}}, "a": None}"#);
+
+        assert_eq!(
+            value,
+            RonValue::Map(vec![
+                (
+                    RonValue::String("b".to_string()),
+                    RonValue::Map(vec![
+                        (
+                            RonValue::String("c".to_string()),
+                            RonValue::List(vec![
+                                RonValue::Number("1".to_string()),
+                                RonValue::Number("2".to_string()),
+                            ])
+                        ),
+                        (RonValue::String("a".to_string()), RonValue::Map(vec![])),
+                    ])
+                ),
+                (RonValue::String("a".to_string()), RonValue::Option(None)),
+            ])
+        );
+    }
+
+    #[test]
+    fn enum_variants_keep_their_names() {
+        assert_eq!(
+            assert_round_trips("Some(Clear(0.0))"),
+            RonValue::Option(Some(Box::new(RonValue::Tuple(
+                Some("Clear".to_string()),
+                vec![RonValue::Number("0.0".to_string())]
+            ))))
+        );
+        assert_eq!(
+            assert_round_trips("Material(source: Some(\"grass.mat\"))"),
+            RonValue::Struct(
+                Some("Material".to_string()),
+                vec![(
+                    "source".to_string(),
+                    RonValue::Option(Some(Box::new(RonValue::String("grass.mat".to_string()))))
+                )]
+            )
+        );
+        assert_eq!(
+            assert_round_trips("Folder"),
+            RonValue::Unit(Some("Folder".to_string()))
+        );
+        assert_eq!(
+            assert_round_trips("Scene()"),
+            RonValue::Unit(Some("Scene".to_string()))
+        );
+        assert_eq!(assert_round_trips("()"), RonValue::Unit(None));
+    }
+
+    #[test]
+    fn comments_and_attributes_are_skipped() {
+        let value = assert_round_trips(
+            "#![enable(implicit_some)]\n// version\n(/* current */ version: 2, items: [])",
+        );
+
+        assert_eq!(
+            value.field("version"),
+            Some(&RonValue::Number("2".to_string()))
+        );
+        assert!(RonValue::parse("(version: 2) trailing").is_err());
+    }
+}
@@ -0,0 +1,636 @@
+use std::fmt;
+use std::path::Path;
+
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::*;
+use bevy::reflect::ReflectRef;
+use bevy::scene::DynamicEntity;
+use bevy::utils::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::editor::EditorItem;
+
+use super::editor_id::match_editor_ids;
+use super::scene_file::{extract_stored_scene, load_scene_file};
+use super::{
+    read_scene_if_needed, scene_item_data, OpenScene, ProjectErrorKind, ProjectItem,
+    ProjectItemRegistry,
+};
+
+const UNSAVED_CHANGES_INTERVAL_SECONDS: f32 = 1.0;
+
+/// Differences between the live entities of the open scene and its stored version.
+#[derive(Default)]
+pub struct SceneDiff {
+    /// Live entities that aren't part of the stored scene.
+    pub added: Vec<Entity>,
+    /// Entities of the stored scene, by their id in it, that no longer exist.
+    pub removed: Vec<Entity>,
+    pub changed: Vec<EntityDiff>,
+}
+
+impl SceneDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Changes to a live entity since the scene was stored.
+pub struct EntityDiff {
+    pub entity: Entity,
+    pub added_components: Vec<String>,
+    pub removed_components: Vec<String>,
+    pub changed_fields: Vec<FieldDiff>,
+}
+
+impl EntityDiff {
+    fn is_empty(&self) -> bool {
+        self.added_components.is_empty()
+            && self.removed_components.is_empty()
+            && self.changed_fields.is_empty()
+    }
+}
+
+pub struct FieldDiff {
+    /// Type name of the component.
+    pub component: String,
+    /// Reflection path of the field within the component, empty if the whole component changed.
+    pub path: String,
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.component, self.path)
+    }
+}
+
+fn field_path(path: &str, field: impl fmt::Display) -> String {
+    format!("{}.{}", path, field)
+}
+
+/// Collects the paths at which `old` and `new` differ, matching stored entities to live ones
+/// through `entities`.
+fn diff_reflect(
+    old: &dyn Reflect,
+    new: &dyn Reflect,
+    path: &str,
+    entities: &EntityMap,
+    paths: &mut Vec<String>,
+) {
+    if let (Some(old), Some(new)) = (old.downcast_ref::<Entity>(), new.downcast_ref::<Entity>()) {
+        if entities.get(*old) != Some(*new) {
+            paths.push(path.to_string());
+        }
+        return;
+    }
+
+    match (old.reflect_ref(), new.reflect_ref()) {
+        (ReflectRef::Struct(old), ReflectRef::Struct(new)) => {
+            for (index, new_field) in new.iter_fields().enumerate() {
+                let name = new.name_at(index).unwrap();
+                match old.field(name) {
+                    Some(old_field) => diff_reflect(
+                        old_field,
+                        new_field,
+                        &field_path(path, name),
+                        entities,
+                        paths,
+                    ),
+                    None => paths.push(field_path(path, name)),
+                }
+            }
+
+            for index in 0..old.field_len() {
+                let name = old.name_at(index).unwrap();
+                if new.field(name).is_none() {
+                    paths.push(field_path(path, name));
+                }
+            }
+        }
+        (ReflectRef::TupleStruct(old), ReflectRef::TupleStruct(new))
+            if old.field_len() == new.field_len() =>
+        {
+            for (index, (old_field, new_field)) in
+                old.iter_fields().zip(new.iter_fields()).enumerate()
+            {
+                diff_reflect(
+                    old_field,
+                    new_field,
+                    &field_path(path, index),
+                    entities,
+                    paths,
+                );
+            }
+        }
+        (ReflectRef::Tuple(old), ReflectRef::Tuple(new)) if old.field_len() == new.field_len() => {
+            for (index, (old_field, new_field)) in
+                old.iter_fields().zip(new.iter_fields()).enumerate()
+            {
+                diff_reflect(
+                    old_field,
+                    new_field,
+                    &field_path(path, index),
+                    entities,
+                    paths,
+                );
+            }
+        }
+        (ReflectRef::List(old), ReflectRef::List(new)) if old.len() == new.len() => {
+            for (index, (old_item, new_item)) in old.iter().zip(new.iter()).enumerate() {
+                let item_path = format!("{}[{}]", path, index);
+                diff_reflect(old_item, new_item, &item_path, entities, paths);
+            }
+        }
+        (ReflectRef::Array(old), ReflectRef::Array(new)) if old.len() == new.len() => {
+            for (index, (old_item, new_item)) in old.iter().zip(new.iter()).enumerate() {
+                let item_path = format!("{}[{}]", path, index);
+                diff_reflect(old_item, new_item, &item_path, entities, paths);
+            }
+        }
+        (ReflectRef::Enum(old), ReflectRef::Enum(new))
+            if old.variant_name() == new.variant_name() && old.field_len() == new.field_len() =>
+        {
+            // Dynamic enums only give the fields of struct variants by name
+            for (index, new_field) in new.iter_fields().enumerate() {
+                let (field_path, old_field) = match new_field.name() {
+                    Some(name) => (field_path(path, name), old.field(name)),
+                    None => (field_path(path, index), old.field_at(index)),
+                };
+                match old_field {
+                    Some(old_field) => {
+                        diff_reflect(old_field, new_field.value(), &field_path, entities, paths)
+                    }
+                    None => paths.push(field_path),
+                }
+            }
+        }
+        // Maps are compared as a whole, as their keys can't be part of a reflection path
+        _ => {
+            if old.reflect_partial_eq(new) != Some(true) {
+                paths.push(path.to_string());
+            }
+        }
+    }
+}
+
+fn diff_entity(old: &DynamicEntity, new: &DynamicEntity, entities: &EntityMap) -> EntityDiff {
+    let mut entity_diff = EntityDiff {
+        entity: new.entity,
+        added_components: Vec::new(),
+        removed_components: Vec::new(),
+        changed_fields: Vec::new(),
+    };
+
+    for new_component in &new.components {
+        let component = new_component.type_name();
+
+        let Some(old_component) = old
+            .components
+            .iter()
+            .find(|old_component| old_component.type_name() == component)
+        else {
+            entity_diff.added_components.push(component.to_string());
+            continue;
+        };
+
+        let mut paths = Vec::new();
+        diff_reflect(
+            old_component.as_ref(),
+            new_component.as_ref(),
+            "",
+            entities,
+            &mut paths,
+        );
+
+        entity_diff
+            .changed_fields
+            .extend(paths.into_iter().map(|path| FieldDiff {
+                component: component.to_string(),
+                path,
+            }));
+    }
+
+    for old_component in &old.components {
+        let component = old_component.type_name();
+        if !new
+            .components
+            .iter()
+            .any(|new_component| new_component.type_name() == component)
+        {
+            entity_diff.removed_components.push(component.to_string());
+        }
+    }
+
+    entity_diff
+}
+
+/// Compares two versions of a scene, matching the entities of `old` to those of `new` through
+/// `entities`.
+pub fn diff_dynamic_scenes(
+    old: &DynamicScene,
+    new: &DynamicScene,
+    entities: &EntityMap,
+) -> SceneDiff {
+    let new_entities: HashMap<Entity, &DynamicEntity> = new
+        .entities
+        .iter()
+        .map(|dynamic_entity| (dynamic_entity.entity, dynamic_entity))
+        .collect();
+
+    let mut scene_diff = SceneDiff::default();
+    let mut matched = HashSet::default();
+
+    for old_entity in &old.entities {
+        let Some(new_entity) = entities
+            .get(old_entity.entity)
+            .and_then(|entity| new_entities.get(&entity))
+        else {
+            scene_diff.removed.push(old_entity.entity);
+            continue;
+        };
+
+        matched.insert(new_entity.entity);
+
+        let entity_diff = diff_entity(old_entity, new_entity, entities);
+        if !entity_diff.is_empty() {
+            scene_diff.changed.push(entity_diff);
+        }
+    }
+
+    scene_diff.added = new
+        .entities
+        .iter()
+        .map(|dynamic_entity| dynamic_entity.entity)
+        .filter(|entity| !matched.contains(entity))
+        .collect();
+
+    scene_diff.added.sort();
+    scene_diff.removed.sort();
+    scene_diff
+        .changed
+        .sort_by_key(|entity_diff| entity_diff.entity);
+
+    scene_diff
+}
+
+/// Compares the live entities of the open scene `scene_uuid` with the version it was last
+/// loaded from or stored as.
+///
+/// Entities are matched to the stored ones with the same
+/// [`EditorId`](super::editor_id::EditorId), or else to the ones they were spawned from.
+pub fn diff_scene(world: &mut World, scene_uuid: Uuid) -> Result<SceneDiff, ProjectErrorKind> {
+    let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+    if world.resource::<OpenScene>().scene_uuid != Some(scene_uuid) {
+        return Err(ProjectErrorKind::SceneNotOpen(scene_uuid));
+    }
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+    read_scene_if_needed(world, scene_uuid, source, &mut dynamic_scene)?;
+
+    let mut query = world.query_filtered::<Entity, With<EditorItem>>();
+    let live_dynamic_scene = extract_stored_scene(world, query.iter(world));
+
+    let dynamic_scene = dynamic_scene.as_ref().unwrap();
+    let mut entities = EntityMap::default();
+    for (stored_entity, entity) in world.resource::<OpenScene>().stored_entities.iter() {
+        entities.insert(stored_entity, entity);
+    }
+    match_editor_ids(world, dynamic_scene, &mut entities);
+
+    Ok(diff_dynamic_scenes(
+        dynamic_scene,
+        &live_dynamic_scene,
+        &entities,
+    ))
+}
+
+/// Compares the stored version of `scene_uuid` with the scene file at `path`, such as one
+/// exported earlier, matching entities by their id.
+pub fn diff_scene_file(
+    world: &mut World,
+    scene_uuid: Uuid,
+    path: &Path,
+) -> Result<SceneDiff, ProjectErrorKind> {
+    let (_, source, dynamic_scene) = scene_item_data(world, scene_uuid)?;
+
+    let mut dynamic_scene = dynamic_scene
+        .lock()
+        .map_err(|_| ProjectErrorKind::PoisonedSceneMutex(scene_uuid))?;
+
+    read_scene_if_needed(world, scene_uuid, source, &mut dynamic_scene)?;
+
+    let file_dynamic_scene = load_scene_file(path, world.resource::<AppTypeRegistry>())
+        .map_err(|err| ProjectErrorKind::SceneRead(path.to_path_buf(), err))?;
+
+    let dynamic_scene = dynamic_scene.as_ref().unwrap();
+    let mut entities = EntityMap::default();
+    for dynamic_entity in &dynamic_scene.entities {
+        entities.insert(dynamic_entity.entity, dynamic_entity.entity);
+    }
+
+    Ok(diff_dynamic_scenes(
+        dynamic_scene,
+        &file_dynamic_scene,
+        &entities,
+    ))
+}
+
+#[derive(Resource)]
+pub(super) struct UnsavedChanges {
+    timer: Timer,
+}
+
+impl Default for UnsavedChanges {
+    fn default() -> Self {
+        UnsavedChanges {
+            timer: Timer::from_seconds(UNSAVED_CHANGES_INTERVAL_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Periodically diffs the open scene, to flag it when it has unsaved changes.
+pub(super) fn update_unsaved_changes(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    if !world
+        .resource_mut::<UnsavedChanges>()
+        .timer
+        .tick(delta)
+        .just_finished()
+    {
+        return;
+    }
+
+    let open_scene_uuid = world.resource::<OpenScene>().scene_uuid;
+    let has_unsaved_changes = match open_scene_uuid {
+        Some(scene_uuid) => match diff_scene(world, scene_uuid) {
+            Ok(scene_diff) => !scene_diff.is_empty(),
+            Err(err) => {
+                warn!("Failed to check scene {} for changes: {}", scene_uuid, err);
+                return;
+            }
+        },
+        None => false,
+    };
+
+    let entities: Vec<Entity> = world
+        .resource::<ProjectItemRegistry>()
+        .items
+        .values()
+        .copied()
+        .collect();
+
+    for entity in entities {
+        let Some(mut project_item) = world.get_mut::<ProjectItem>(entity) else {
+            continue;
+        };
+
+        // Only scenes whose flag flips are touched, to keep change detection meaningful
+        let flagged = has_unsaved_changes && Some(project_item.uuid) == open_scene_uuid;
+        if project_item.has_unsaved_changes != flagged {
+            project_item.has_unsaved_changes = flagged;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_mod_picking::prelude::{PickHighlight, PickSelection};
+
+    use super::super::{asset_root_path, send_test_event, test_project_app, ProjectEvent};
+    use super::*;
+
+    #[derive(Reflect, Clone)]
+    enum Shape {
+        Circle { radius: f32 },
+        Square(f32),
+        Empty,
+    }
+
+    #[derive(Reflect, Clone)]
+    struct Sample {
+        speed: f32,
+        target: Entity,
+        shape: Shape,
+        tags: Vec<String>,
+        weights: HashMap<String, f32>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            speed: 1.0,
+            target: Entity::from_raw(1),
+            shape: Shape::Circle { radius: 1.0 },
+            tags: vec!["a".to_string()],
+            weights: HashMap::default(),
+        }
+    }
+
+    fn identity(entities: &[Entity]) -> EntityMap {
+        let mut entity_map = EntityMap::default();
+        for entity in entities {
+            entity_map.insert(*entity, *entity);
+        }
+        entity_map
+    }
+
+    /// Diffs the dynamic versions of the values, as they're found in a scene.
+    fn diff(old: &Sample, new: &Sample, entities: &EntityMap) -> Vec<String> {
+        let mut paths = Vec::new();
+        diff_reflect(
+            old.clone_value().as_ref(),
+            new.clone_value().as_ref(),
+            "",
+            entities,
+            &mut paths,
+        );
+        paths
+    }
+
+    #[test]
+    fn equal_values_have_no_diff() {
+        let entities = identity(&[Entity::from_raw(1)]);
+        assert!(diff(&sample(), &sample(), &entities).is_empty());
+    }
+
+    #[test]
+    fn changed_fields_are_reported_by_path() {
+        let entities = identity(&[Entity::from_raw(1)]);
+        let mut new = sample();
+        new.speed = 2.0;
+        new.tags[0] = "b".to_string();
+
+        assert_eq!(diff(&sample(), &new, &entities), [".speed", ".tags[0]"]);
+    }
+
+    #[test]
+    fn entities_are_matched_through_the_entity_map() {
+        let mut new = sample();
+        new.target = Entity::from_raw(7);
+
+        let mut entities = EntityMap::default();
+        entities.insert(Entity::from_raw(1), Entity::from_raw(7));
+        assert!(diff(&sample(), &new, &entities).is_empty());
+
+        assert_eq!(diff(&sample(), &new, &identity(&[])), [".target"]);
+        assert_eq!(diff(&sample(), &sample(), &identity(&[])), [".target"]);
+    }
+
+    #[test]
+    fn enum_fields_are_diffed_within_the_same_variant() {
+        let entities = identity(&[Entity::from_raw(1)]);
+
+        let mut new = sample();
+        new.shape = Shape::Circle { radius: 2.0 };
+        assert_eq!(diff(&sample(), &new, &entities), [".shape.radius"]);
+
+        let mut old = sample();
+        old.shape = Shape::Square(1.0);
+        new.shape = Shape::Square(2.0);
+        assert_eq!(diff(&old, &new, &entities), [".shape.0"]);
+    }
+
+    #[test]
+    fn changed_variants_are_reported_whole() {
+        let entities = identity(&[Entity::from_raw(1)]);
+        let mut new = sample();
+        new.shape = Shape::Empty;
+
+        assert_eq!(diff(&sample(), &new, &entities), [".shape"]);
+    }
+
+    #[test]
+    fn resized_lists_and_changed_maps_are_reported_whole() {
+        let entities = identity(&[Entity::from_raw(1)]);
+        let mut new = sample();
+        new.tags.push("b".to_string());
+        new.weights.insert("a".to_string(), 1.0);
+
+        assert_eq!(diff(&sample(), &new, &entities), [".tags", ".weights"]);
+    }
+
+    #[test]
+    fn scene_diff_reports_entities_and_components() {
+        let kept = Entity::from_raw(0);
+        let removed = Entity::from_raw(1);
+        let added = Entity::from_raw(2);
+
+        let old = DynamicScene {
+            resources: Vec::new(),
+            entities: vec![
+                DynamicEntity {
+                    entity: kept,
+                    components: vec![
+                        Transform::default().clone_value(),
+                        Name::new("kept").clone_value(),
+                    ],
+                },
+                DynamicEntity {
+                    entity: removed,
+                    components: Vec::new(),
+                },
+            ],
+        };
+        let new = DynamicScene {
+            resources: Vec::new(),
+            entities: vec![
+                DynamicEntity {
+                    entity: kept,
+                    components: vec![
+                        Transform::from_xyz(1.0, 0.0, 0.0).clone_value(),
+                        Visibility::Hidden.clone_value(),
+                    ],
+                },
+                DynamicEntity {
+                    entity: added,
+                    components: Vec::new(),
+                },
+            ],
+        };
+
+        let scene_diff = diff_dynamic_scenes(&old, &new, &identity(&[kept, removed]));
+        assert_eq!(scene_diff.added, [added]);
+        assert_eq!(scene_diff.removed, [removed]);
+
+        let [entity_diff] = scene_diff.changed.as_slice() else {
+            panic!("expected one changed entity");
+        };
+        assert_eq!(entity_diff.entity, kept);
+        assert_eq!(
+            entity_diff.added_components,
+            [std::any::type_name::<Visibility>()]
+        );
+        assert_eq!(
+            entity_diff.removed_components,
+            [std::any::type_name::<Name>()]
+        );
+        let changed_fields: Vec<String> = entity_diff
+            .changed_fields
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            changed_fields,
+            [format!(
+                "{}.translation.x",
+                std::any::type_name::<Transform>()
+            )]
+        );
+    }
+
+    #[test]
+    fn hover_and_selection_are_not_changes() {
+        let mut app = test_project_app();
+        app.register_type::<Interaction>()
+            .register_type::<PickSelection>()
+            .register_type::<PickHighlight>();
+
+        let scene_uuid = Uuid::new_v4();
+        send_test_event(
+            &mut app,
+            ProjectEvent::CreateScene {
+                uuid: scene_uuid,
+                name: "Level".to_string(),
+                parent_uuid: None,
+            },
+        );
+        send_test_event(
+            &mut app,
+            ProjectEvent::LoadScene {
+                scene_uuid,
+                discard_changes: false,
+            },
+        );
+
+        let entity = app
+            .world
+            .spawn((
+                EditorItem::default(),
+                Transform::IDENTITY,
+                Interaction::None,
+                PickSelection::default(),
+                PickHighlight,
+            ))
+            .id();
+        send_test_event(&mut app, ProjectEvent::StoreScene { scene_uuid });
+
+        *app.world.get_mut::<Interaction>(entity).unwrap() = Interaction::Hovered;
+        app.world
+            .get_mut::<PickSelection>(entity)
+            .unwrap()
+            .is_selected = true;
+        assert!(diff_scene(&mut app.world, scene_uuid).unwrap().is_empty());
+
+        app.world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x = 1.0;
+        assert!(!diff_scene(&mut app.world, scene_uuid).unwrap().is_empty());
+
+        std::fs::remove_dir_all(asset_root_path(app.world.resource::<AssetServer>())).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::pbr::{Cascades, CascadesVisibleEntities, CubemapVisibleEntities};
+use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, CascadesFrusta, CubemapFrusta, Frustum};
+use bevy::render::view::VisibleEntities;
+use bevy::scene::serde::SceneDeserializer;
+use bevy_mod_picking::prelude::{PickHighlight, PickSelection};
+use serde::de::DeserializeSeed;
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Deserialize(ron::error::SpannedError),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(err) => write!(f, "{}", err),
+            SceneFileError::Serialize(err) => write!(f, "{}", err),
+            SceneFileError::Deserialize(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(err: io::Error) -> Self {
+        SceneFileError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SceneFileError {
+    fn from(err: ron::Error) -> Self {
+        SceneFileError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneFileError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        SceneFileError::Deserialize(err)
+    }
+}
+
+pub fn save_scene_file(
+    path: &Path,
+    dynamic_scene: &DynamicScene,
+    type_registry: &AppTypeRegistry,
+) -> Result<(), SceneFileError> {
+    let serialized = dynamic_scene.serialize_ron(type_registry)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, serialized)?;
+    Ok(())
+}
+
+pub fn load_scene_file(
+    path: &Path,
+    type_registry: &AppTypeRegistry,
+) -> Result<DynamicScene, SceneFileError> {
+    let input = fs::read_to_string(path)?;
+    deserialize_scene(&input, type_registry)
+}
+
+pub fn deserialize_scene(
+    input: &str,
+    type_registry: &AppTypeRegistry,
+) -> Result<DynamicScene, SceneFileError> {
+    let mut deserializer = ron::de::Deserializer::from_str(input)?;
+
+    let scene_deserializer = SceneDeserializer {
+        type_registry: &type_registry.read(),
+    };
+
+    let dynamic_scene = scene_deserializer
+        .deserialize(&mut deserializer)
+        .map_err(|err| deserializer.span_error(err))?;
+
+    Ok(dynamic_scene)
+}
+
+/// Extracts `entities` into a scene to store, leaving out the components that the engine
+/// computes from the others, which only change from frame to frame, and the editor's hover and
+/// selection state.
+pub fn extract_stored_scene(world: &World, entities: impl Iterator<Item = Entity>) -> DynamicScene {
+    let mut dynamic_scene_builder = DynamicSceneBuilder::from_world(world);
+    dynamic_scene_builder
+        .deny::<GlobalTransform>()
+        .deny::<ComputedVisibility>()
+        .deny::<Aabb>()
+        .deny::<Frustum>()
+        .deny::<CubemapFrusta>()
+        .deny::<CascadesFrusta>()
+        .deny::<Cascades>()
+        .deny::<VisibleEntities>()
+        .deny::<CubemapVisibleEntities>()
+        .deny::<CascadesVisibleEntities>()
+        .deny::<Interaction>()
+        .deny::<PickSelection>()
+        .deny::<PickHighlight>()
+        .extract_entities(entities);
+    dynamic_scene_builder.build()
+}
+
+/// Adds back the computed components left out of stored scenes, which the engine only updates
+/// and doesn't add.
+pub fn insert_computed_components(world: &mut World, entities: impl Iterator<Item = Entity>) {
+    for entity in entities {
+        let mut entity = world.entity_mut(entity);
+
+        if entity.contains::<Transform>() && !entity.contains::<GlobalTransform>() {
+            entity.insert(GlobalTransform::default());
+        }
+        if entity.contains::<Visibility>() && !entity.contains::<ComputedVisibility>() {
+            entity.insert(ComputedVisibility::default());
+        }
+
+        if (entity.contains::<Camera>() || entity.contains::<SpotLight>())
+            && !entity.contains::<Frustum>()
+        {
+            entity.insert((Frustum::default(), VisibleEntities::default()));
+        }
+        if entity.contains::<PointLight>() && !entity.contains::<CubemapFrusta>() {
+            entity.insert((CubemapFrusta::default(), CubemapVisibleEntities::default()));
+        }
+        if entity.contains::<DirectionalLight>() && !entity.contains::<CascadesFrusta>() {
+            entity.insert((
+                CascadesFrusta::default(),
+                CascadesVisibleEntities::default(),
+                Cascades::default(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,616 @@
+use std::path::Path;
+
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use super::editor_id::{map_scene_entities, EditorId};
+use super::prefab::find_component;
+use super::ron_value::RonValue;
+use super::scene_file::{deserialize_scene, load_scene_file};
+use super::ProjectErrorKind;
+
+/// Result of merging two versions of a scene file with their common ancestor.
+pub struct SceneMerge {
+    /// The merged scene file, with conflict markers around every conflicting entry.
+    pub output: String,
+    /// Paths of the conflicting entries, such as
+    /// `entities[4].components["bevy_transform::components::transform::Transform"].translation.x`.
+    pub conflicts: Vec<String>,
+}
+
+/// A merged value, keeping the structure down to conflicting entries so they can be marked.
+enum Merged {
+    Value(RonValue),
+    Conflict {
+        ours: Option<RonValue>,
+        theirs: Option<RonValue>,
+    },
+    Struct(Option<String>, Vec<(String, Merged)>),
+    Tuple(Option<String>, Vec<Merged>),
+    Map(Vec<(RonValue, Merged)>),
+}
+
+fn struct_fields(value: Option<&RonValue>) -> &[(String, RonValue)] {
+    match value {
+        Some(RonValue::Struct(_, fields)) => fields,
+        _ => &[],
+    }
+}
+
+fn map_entries(value: Option<&RonValue>) -> &[(RonValue, RonValue)] {
+    match value {
+        Some(RonValue::Map(entries)) => entries,
+        _ => &[],
+    }
+}
+
+/// Merges entries by key, keeping the order of `ours` followed by the entries only in `theirs`.
+fn merge_entries<K: Clone + PartialEq>(
+    base: &[(K, RonValue)],
+    ours: &[(K, RonValue)],
+    theirs: &[(K, RonValue)],
+    entry_path: impl Fn(&K) -> String,
+    conflicts: &mut Vec<String>,
+) -> Vec<(K, Merged)> {
+    let find = |entries: &'_ [(K, RonValue)], key: &K| -> Option<RonValue> {
+        entries
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.clone())
+    };
+
+    let mut keys: Vec<&K> = ours.iter().map(|(key, _)| key).collect();
+    for (key, _) in theirs.iter().chain(base) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let merged = merge_values(
+                find(base, key).as_ref(),
+                find(ours, key).as_ref(),
+                find(theirs, key).as_ref(),
+                &entry_path(key),
+                conflicts,
+            )?;
+            Some((key.clone(), merged))
+        })
+        .collect()
+}
+
+/// Three-way merges a value, returning `None` when it's removed.
+///
+/// Changes made on one side only are taken as they are, and values changed on both sides are
+/// merged field by field where their shapes allow it.
+fn merge_values(
+    base: Option<&RonValue>,
+    ours: Option<&RonValue>,
+    theirs: Option<&RonValue>,
+    path: &str,
+    conflicts: &mut Vec<String>,
+) -> Option<Merged> {
+    if ours == theirs || theirs == base {
+        return ours.cloned().map(Merged::Value);
+    }
+    if ours == base {
+        return theirs.cloned().map(Merged::Value);
+    }
+
+    let merged = match (ours, theirs) {
+        (
+            Some(RonValue::Struct(ours_name, ours_fields)),
+            Some(RonValue::Struct(theirs_name, theirs_fields)),
+        ) if ours_name == theirs_name => Merged::Struct(
+            ours_name.clone(),
+            merge_entries(
+                struct_fields(base),
+                ours_fields,
+                theirs_fields,
+                |field| format!("{}.{}", path, field),
+                conflicts,
+            ),
+        ),
+        (Some(RonValue::Map(ours_entries)), Some(RonValue::Map(theirs_entries))) => {
+            Merged::Map(merge_entries(
+                map_entries(base),
+                ours_entries,
+                theirs_entries,
+                |key| format!("{}[{}]", path, key),
+                conflicts,
+            ))
+        }
+        (
+            Some(RonValue::Tuple(ours_name, ours_values)),
+            Some(RonValue::Tuple(theirs_name, theirs_values)),
+        ) if ours_name == theirs_name && ours_values.len() == theirs_values.len() => {
+            let base_values = match base {
+                Some(RonValue::Tuple(base_name, base_values))
+                    if base_name == ours_name && base_values.len() == ours_values.len() =>
+                {
+                    Some(base_values)
+                }
+                _ => None,
+            };
+
+            Merged::Tuple(
+                ours_name.clone(),
+                ours_values
+                    .iter()
+                    .zip(theirs_values)
+                    .enumerate()
+                    .map(|(index, (ours_value, theirs_value))| {
+                        // Both sides have the element, and values are only removed when one
+                        // side is `None`, so this never returns `None`
+                        merge_values(
+                            base_values.map(|base_values| &base_values[index]),
+                            Some(ours_value),
+                            Some(theirs_value),
+                            &format!("{}.{}", path, index),
+                            conflicts,
+                        )
+                        .unwrap()
+                    })
+                    .collect(),
+            )
+        }
+        _ => {
+            conflicts.push(path.trim_start_matches('.').to_string());
+            Merged::Conflict {
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            }
+        }
+    };
+
+    Some(merged)
+}
+
+fn write_key(output: &mut String, key: Option<&str>, indent: usize) {
+    output.push_str(&"  ".repeat(indent));
+    if let Some(key) = key {
+        output.push_str(key);
+        output.push_str(": ");
+    }
+}
+
+/// Writes an entry of a struct, tuple or map on its own lines, surrounded by conflict markers
+/// if it conflicts.
+fn write_entry(output: &mut String, key: Option<&str>, merged: &Merged, indent: usize) {
+    match merged {
+        Merged::Conflict { ours, theirs } => {
+            output.push_str("<<<<<<< ours\n");
+            if let Some(ours) = ours {
+                write_key(output, key, indent);
+                output.push_str(&format!("{},\n", ours));
+            }
+            output.push_str("=======\n");
+            if let Some(theirs) = theirs {
+                write_key(output, key, indent);
+                output.push_str(&format!("{},\n", theirs));
+            }
+            output.push_str(">>>>>>> theirs\n");
+        }
+        merged => {
+            write_key(output, key, indent);
+            write_merged(output, merged, indent);
+            output.push_str(",\n");
+        }
+    }
+}
+
+fn write_merged(output: &mut String, merged: &Merged, indent: usize) {
+    match merged {
+        // Unchanged structs and maps are still spread over several lines, like stored scenes
+        Merged::Value(RonValue::Struct(name, fields)) if !fields.is_empty() => {
+            let fields = fields
+                .iter()
+                .map(|(field, value)| (field.clone(), Merged::Value(value.clone())))
+                .collect();
+            write_merged(output, &Merged::Struct(name.clone(), fields), indent);
+        }
+        Merged::Value(RonValue::Map(entries)) if !entries.is_empty() => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| (key.clone(), Merged::Value(value.clone())))
+                .collect();
+            write_merged(output, &Merged::Map(entries), indent);
+        }
+        Merged::Value(value) => output.push_str(&value.to_string()),
+        Merged::Struct(name, fields) => {
+            output.push_str(name.as_deref().unwrap_or_default());
+            output.push_str("(\n");
+            for (field, merged) in fields {
+                write_entry(output, Some(field), merged, indent + 1);
+            }
+            write_key(output, None, indent);
+            output.push(')');
+        }
+        Merged::Tuple(name, values) => {
+            output.push_str(name.as_deref().unwrap_or_default());
+            output.push_str("(\n");
+            for merged in values {
+                write_entry(output, None, merged, indent + 1);
+            }
+            write_key(output, None, indent);
+            output.push(')');
+        }
+        Merged::Map(entries) => {
+            output.push_str("{\n");
+            for (key, merged) in entries {
+                write_entry(output, Some(&key.to_string()), merged, indent + 1);
+            }
+            write_key(output, None, indent);
+            output.push('}');
+        }
+        // Conflicts are only ever entries, written by `write_entry`
+        Merged::Conflict { .. } => unreachable!(),
+    }
+}
+
+/// Renumbers the entities of `base` and `theirs` like the entities of `ours` with the same
+/// [`EditorId`], so that they're matched even when colliding ids were numbered differently on
+/// each side. Entities without an id keep their number, and are matched by it.
+fn match_editor_ids(base: &mut DynamicScene, ours: &DynamicScene, theirs: &mut DynamicScene) {
+    let mut numbers: HashMap<EditorId, Entity> = ours
+        .entities
+        .iter()
+        .filter_map(|dynamic_entity| {
+            Some((
+                find_component::<EditorId>(dynamic_entity)?,
+                dynamic_entity.entity,
+            ))
+        })
+        .collect();
+
+    let mut taken: HashSet<Entity> = ours
+        .entities
+        .iter()
+        .chain(
+            base.entities
+                .iter()
+                .chain(&theirs.entities)
+                .filter(|dynamic_entity| find_component::<EditorId>(dynamic_entity).is_none()),
+        )
+        .map(|dynamic_entity| dynamic_entity.entity)
+        .collect();
+
+    for dynamic_scene in [theirs, base] {
+        let mut entities = EntityMap::default();
+
+        for dynamic_entity in &dynamic_scene.entities {
+            let Some(editor_id) = find_component::<EditorId>(dynamic_entity) else {
+                continue;
+            };
+
+            let entity = *numbers.entry(editor_id).or_insert_with(|| {
+                let mut entity = dynamic_entity.entity;
+                while !taken.insert(entity) {
+                    entity = Entity::from_raw(entity.index().wrapping_add(1));
+                }
+                entity
+            });
+            entities.insert(dynamic_entity.entity, entity);
+        }
+
+        map_scene_entities(dynamic_scene, &entities);
+    }
+}
+
+fn read_scene(
+    path: &Path,
+    type_registry: &AppTypeRegistry,
+) -> Result<DynamicScene, ProjectErrorKind> {
+    // Only valid scenes are merged, even though merging works on their serialized form
+    load_scene_file(path, type_registry)
+        .map_err(|err| ProjectErrorKind::SceneRead(path.to_path_buf(), err))
+}
+
+fn scene_value(
+    dynamic_scene: &DynamicScene,
+    type_registry: &AppTypeRegistry,
+) -> Result<RonValue, ProjectErrorKind> {
+    let serialized = dynamic_scene.serialize_ron(type_registry).map_err(|err| {
+        ProjectErrorKind::SceneMerge(format!("failed to serialize a scene to merge: {}", err))
+    })?;
+
+    RonValue::parse(&serialized).map_err(ProjectErrorKind::SceneMerge)
+}
+
+/// Three-way merges the scene files `ours` and `theirs` with their common ancestor `base`.
+///
+/// Entities are matched by their [`EditorId`], or by their id in the files when they don't
+/// have one, components by type, and fields by name, so only entries changed differently on
+/// both sides conflict. Entities keep their id in `ours`. Without conflicts, the output is
+/// written the way the editor stores scenes.
+pub fn merge_scene_files(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    type_registry: &AppTypeRegistry,
+) -> Result<SceneMerge, ProjectErrorKind> {
+    let mut base = read_scene(base, type_registry)?;
+    let ours = read_scene(ours, type_registry)?;
+    let mut theirs = read_scene(theirs, type_registry)?;
+
+    match_editor_ids(&mut base, &ours, &mut theirs);
+
+    let base = scene_value(&base, type_registry)?;
+    let ours = scene_value(&ours, type_registry)?;
+    let theirs = scene_value(&theirs, type_registry)?;
+
+    let mut conflicts = Vec::new();
+    let merged = merge_values(Some(&base), Some(&ours), Some(&theirs), "", &mut conflicts)
+        .ok_or_else(|| ProjectErrorKind::SceneMerge("both sides removed the scene".to_string()))?;
+
+    let mut output = String::new();
+    write_merged(&mut output, &merged, 0);
+    output.push('\n');
+
+    if conflicts.is_empty() {
+        let dynamic_scene = deserialize_scene(&output, type_registry).map_err(|err| {
+            ProjectErrorKind::SceneMerge(format!("the merged scene is invalid: {}", err))
+        })?;
+
+        output = dynamic_scene.serialize_ron(type_registry).map_err(|err| {
+            ProjectErrorKind::SceneMerge(format!("failed to serialize the merged scene: {}", err))
+        })?;
+    }
+
+    Ok(SceneMerge { output, conflicts })
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::super::scene_file::save_scene_file;
+    use super::*;
+
+    /// Written the way merged values are, as [`merge`] doesn't reserialize the output.
+    fn transform(x: &str, y: &str) -> String {
+        format!(
+            r#""bevy_transform::components::transform::Transform": (
+          translation: (
+            x: {},
+            y: {},
+            z: 0.0,
+          ),
+          rotation: (0.0,0.0,0.0,1.0),
+          scale: (
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+          ),
+        ),"#,
+            x, y
+        )
+    }
+
+    fn name(name: &str) -> String {
+        format!(
+            r#""bevy_core::name::Name": (
+          hash: 0,
+          name: "{}",
+        ),"#,
+            name
+        )
+    }
+
+    fn scene(entities: &[(u32, &[String])]) -> String {
+        let mut output = "(\n  resources: {},\n  entities: {\n".to_string();
+        for (entity, components) in entities {
+            output.push_str(&format!("    {}: (\n      components: {{\n", entity));
+            for component in *components {
+                output.push_str(&format!("        {}\n", component));
+            }
+            output.push_str("      },\n    ),\n");
+        }
+        output.push_str("  },\n)\n");
+        output
+    }
+
+    /// Merges scenes as [`merge_scene_files`] does, but without reserializing them.
+    fn merge(base: &str, ours: &str, theirs: &str) -> SceneMerge {
+        let parse = |input| RonValue::parse(input).unwrap();
+        let (base, ours, theirs) = (parse(base), parse(ours), parse(theirs));
+
+        let mut conflicts = Vec::new();
+        let merged =
+            merge_values(Some(&base), Some(&ours), Some(&theirs), "", &mut conflicts).unwrap();
+
+        let mut output = String::new();
+        write_merged(&mut output, &merged, 0);
+        output.push('\n');
+        SceneMerge { output, conflicts }
+    }
+
+    #[test]
+    fn changes_on_one_side_are_taken() {
+        let base = scene(&[(0, &[transform("0.0", "0.0")])]);
+        let ours = scene(&[(0, &[transform("1.0", "0.0")])]);
+
+        for scene_merge in [merge(&base, &ours, &base), merge(&base, &base, &ours)] {
+            assert!(scene_merge.conflicts.is_empty());
+            assert_eq!(scene_merge.output, ours);
+        }
+    }
+
+    #[test]
+    fn same_changes_on_both_sides_are_taken_once() {
+        let base = scene(&[(0, &[transform("0.0", "0.0")])]);
+        let ours = scene(&[(0, &[transform("1.0", "0.0")]), (1, &[name("Lamp")])]);
+
+        let scene_merge = merge(&base, &ours, &ours);
+        assert!(scene_merge.conflicts.is_empty());
+        assert_eq!(scene_merge.output, ours);
+    }
+
+    #[test]
+    fn different_fields_of_a_component_are_merged() {
+        let base = scene(&[(0, &[transform("0.0", "0.0")])]);
+        let ours = scene(&[(0, &[transform("1.0", "0.0")])]);
+        let theirs = scene(&[(0, &[transform("0.0", "2.0")])]);
+
+        let scene_merge = merge(&base, &ours, &theirs);
+        assert!(scene_merge.conflicts.is_empty());
+        assert_eq!(
+            scene_merge.output,
+            scene(&[(0, &[transform("1.0", "2.0")])])
+        );
+    }
+
+    #[test]
+    fn entities_added_on_both_sides_are_kept() {
+        let base = scene(&[(0, &[name("Base")])]);
+        let ours = scene(&[(0, &[name("Base")]), (1, &[name("Ours")])]);
+        let theirs = scene(&[(0, &[name("Base")]), (2, &[name("Theirs")])]);
+
+        let scene_merge = merge(&base, &ours, &theirs);
+        assert!(scene_merge.conflicts.is_empty());
+        assert_eq!(
+            scene_merge.output,
+            scene(&[
+                (0, &[name("Base")]),
+                (1, &[name("Ours")]),
+                (2, &[name("Theirs")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn conflicting_fields_are_marked() {
+        let base = scene(&[(0, &[transform("0.0", "0.0")])]);
+        let ours = scene(&[(0, &[transform("1.0", "0.0")])]);
+        let theirs = scene(&[(0, &[transform("2.0", "0.0")])]);
+
+        let scene_merge = merge(&base, &ours, &theirs);
+        assert_eq!(
+            scene_merge.conflicts,
+            [
+                r#"entities[0].components["bevy_transform::components::transform::Transform"].translation.x"#
+            ]
+        );
+
+        let conflict =
+            "<<<<<<< ours\n            x: 1.0,\n=======\n            x: 2.0,\n>>>>>>> theirs\n";
+        // Only the translation is marked, even though the scale has the same `x: 1.0` line
+        let marked = transform("1.0", "0.0").replacen("            x: 1.0,\n", conflict, 1);
+        assert_eq!(scene_merge.output, scene(&[(0, &[marked])]));
+    }
+
+    #[test]
+    fn components_removed_on_one_side_and_changed_on_the_other_conflict() {
+        let base = scene(&[(0, &[transform("0.0", "0.0"), name("Lamp")])]);
+        let ours = scene(&[(0, &[name("Lamp")])]);
+        let theirs = scene(&[(0, &[transform("1.0", "0.0"), name("Lamp")])]);
+
+        let scene_merge = merge(&base, &ours, &theirs);
+        assert_eq!(
+            scene_merge.conflicts,
+            [r#"entities[0].components["bevy_transform::components::transform::Transform"]"#]
+        );
+        // Our side of the conflict is empty, as we removed the component
+        let conflict = concat!(
+            "<<<<<<< ours\n",
+            "=======\n",
+            r#"        "bevy_transform::components::transform::Transform": "#,
+            "(translation:(x:1.0,y:0.0,z:0.0),rotation:(0.0,0.0,0.0,1.0),scale:(x:1.0,y:1.0,z:1.0)),\n",
+            ">>>>>>> theirs\n",
+        );
+        assert!(scene_merge.output.contains(conflict));
+    }
+
+    /// Writes a scene of entities numbered `index`, with a position and an optional parent.
+    fn write_scene(
+        path: &Path,
+        type_registry: &AppTypeRegistry,
+        entities: &[(u32, EditorId, Vec3, Option<u32>)],
+    ) {
+        let mut world = World::new();
+        world.insert_resource(type_registry.clone());
+
+        for (index, editor_id, translation, _) in entities {
+            world
+                .get_or_spawn(Entity::from_raw(*index))
+                .unwrap()
+                .insert((*editor_id, Transform::from_translation(*translation)));
+        }
+        for (index, _, _, parent) in entities {
+            if let Some(parent) = parent {
+                world
+                    .entity_mut(Entity::from_raw(*index))
+                    .set_parent(Entity::from_raw(*parent));
+            }
+        }
+
+        let mut dynamic_scene_builder = DynamicSceneBuilder::from_world(&world);
+        dynamic_scene_builder.extract_entities(world.iter_entities().map(|entity| entity.id()));
+        save_scene_file(path, &dynamic_scene_builder.build(), type_registry).unwrap();
+    }
+
+    #[test]
+    fn entities_are_matched_by_editor_id() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .register_type::<Uuid>()
+            .register_type::<EditorId>();
+        let type_registry = app.world.resource::<AppTypeRegistry>().clone();
+
+        let [lamp, crate_, shelf] = [(); 3].map(|_| EditorId(Uuid::new_v4()));
+        let directory = std::env::temp_dir().join(format!("makeshift-merge-{}", Uuid::new_v4()));
+        let [base, ours, theirs] =
+            ["base", "ours", "theirs"].map(|name| directory.join(format!("{}.scn.ron", name)));
+
+        write_scene(&base, &type_registry, &[(1, lamp, Vec3::ZERO, None)]);
+        write_scene(&ours, &type_registry, &[(1, lamp, Vec3::X, None)]);
+        // The lamp got another number on their side, and a new crate on a new shelf took its
+        // number
+        write_scene(
+            &theirs,
+            &type_registry,
+            &[
+                (7, lamp, Vec3::Y, None),
+                (1, crate_, Vec3::Z, Some(8)),
+                (8, shelf, Vec3::ZERO, None),
+            ],
+        );
+
+        let scene_merge = merge_scene_files(&base, &ours, &theirs, &type_registry).unwrap();
+        assert!(scene_merge.conflicts.is_empty());
+
+        let merged = deserialize_scene(&scene_merge.output, &type_registry).unwrap();
+        let entity = |editor_id| {
+            merged
+                .entities
+                .iter()
+                .find(|dynamic_entity| {
+                    find_component::<EditorId>(dynamic_entity) == Some(editor_id)
+                })
+                .unwrap()
+        };
+
+        let merged_lamp = entity(lamp);
+        assert_eq!(merged_lamp.entity, Entity::from_raw(1));
+        assert_eq!(
+            find_component::<Transform>(merged_lamp)
+                .unwrap()
+                .translation,
+            Vec3::new(1.0, 1.0, 0.0)
+        );
+
+        let merged_crate = entity(crate_);
+        assert_ne!(merged_crate.entity, Entity::from_raw(1));
+        assert_eq!(
+            find_component::<Parent>(merged_crate).map(|parent| parent.get()),
+            Some(entity(shelf).entity)
+        );
+        assert_eq!(merged.entities.len(), 3);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use uuid::Uuid;
+
+use super::{ProjectItem, ProjectItemRegistry};
+
+/// Index over the names, kinds, folders, tags and metadata of all project items, kept up to date
+/// as items change.
+///
+/// Queries are made of whitespace separated terms. Plain terms are fuzzy matched against item
+/// names, while `kind:<kind>`, `tag:<tag>`, `meta:<key>` or `meta:<key>=<value>` and
+/// `path:<folder path>` filter the results. Values containing spaces can be quoted, as in
+/// `path:"Levels/Level 01"`.
+#[derive(Resource, Default)]
+pub struct ProjectSearch {
+    entries: HashMap<Uuid, SearchEntry>,
+}
+
+struct SearchEntry {
+    name: String,
+    chars: Vec<char>,
+    word_starts: Vec<bool>,
+    kind: &'static str,
+    parent_uuid: Option<Uuid>,
+    tags: Vec<String>,
+    metadata: HashMap<String, String>,
+}
+
+impl SearchEntry {
+    fn new(project_item: &ProjectItem, parent_uuid: Option<Uuid>) -> Self {
+        let original: Vec<char> = project_item.name.chars().collect();
+        let chars = original.iter().map(|c| lowercase(*c)).collect();
+        let word_starts = original
+            .iter()
+            .enumerate()
+            .map(|(index, c)| match index {
+                0 => true,
+                _ => {
+                    let previous = original[index - 1];
+                    !previous.is_alphanumeric()
+                        || (previous.is_lowercase() && c.is_uppercase())
+                        || (!previous.is_numeric() && c.is_numeric())
+                }
+            })
+            .collect();
+
+        SearchEntry {
+            name: project_item.name.clone(),
+            chars,
+            word_starts,
+            kind: project_item.kind.name(),
+            parent_uuid,
+            tags: project_item
+                .tags
+                .iter()
+                .map(|tag| tag.to_lowercase())
+                .collect(),
+            metadata: project_item
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.to_lowercase(), value.to_lowercase()))
+                .collect(),
+        }
+    }
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[derive(Default)]
+struct SearchQuery {
+    terms: Vec<Vec<char>>,
+    kinds: Vec<String>,
+    tags: Vec<String>,
+    metadata: Vec<(String, Option<String>)>,
+    path: Option<String>,
+}
+
+fn split_query(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+impl SearchQuery {
+    fn parse(query: &str) -> Self {
+        let mut parsed = SearchQuery::default();
+
+        for token in split_query(query) {
+            let token = token.to_lowercase();
+
+            if let Some(kind) = token.strip_prefix("kind:") {
+                parsed.kinds.push(kind.to_string());
+            } else if let Some(tag) = token.strip_prefix("tag:") {
+                parsed.tags.push(tag.to_string());
+            } else if let Some(metadata) = token.strip_prefix("meta:") {
+                parsed.metadata.push(match metadata.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (metadata.to_string(), None),
+                });
+            } else if let Some(path) = token.strip_prefix("path:") {
+                parsed.path = Some(path.trim_matches('/').to_string());
+            } else {
+                parsed.terms.push(token.chars().collect());
+            }
+        }
+
+        parsed
+    }
+}
+
+/// Matches `term` as a subsequence of `entry`'s name, favouring matches at word starts and runs
+/// of consecutive characters.
+fn fuzzy_score(term: &[char], entry: &SearchEntry) -> Option<i32> {
+    if term.len() > entry.chars.len() {
+        return None;
+    }
+
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for c in term {
+        let index = position + entry.chars[position..].iter().position(|x| x == c)?;
+
+        score += 1;
+        if entry.word_starts[index] {
+            score += 8;
+        }
+        match previous_match {
+            Some(previous) if previous + 1 == index => score += 5,
+            Some(previous) => score -= (index - previous - 1).min(5) as i32,
+            None => score -= index.min(5) as i32,
+        }
+
+        previous_match = Some(index);
+        position = index + 1;
+    }
+
+    if entry.chars.starts_with(term) {
+        score += 10;
+        if entry.chars.len() == term.len() {
+            score += 20;
+        }
+    }
+
+    Some(score)
+}
+
+impl ProjectSearch {
+    fn folder_path(&self, uuid: Uuid, folder_paths: &mut HashMap<Uuid, String>) -> String {
+        if let Some(path) = folder_paths.get(&uuid) {
+            return path.clone();
+        }
+
+        let Some(entry) = self.entries.get(&uuid) else {
+            return String::new();
+        };
+
+        let name = entry.name.to_lowercase();
+        let path = match entry.parent_uuid {
+            Some(parent_uuid) => {
+                format!("{}/{}", self.folder_path(parent_uuid, folder_paths), name)
+            }
+            None => name,
+        };
+
+        folder_paths.insert(uuid, path.clone());
+        path
+    }
+
+    fn in_path(
+        &self,
+        entry: &SearchEntry,
+        path: &str,
+        folder_paths: &mut HashMap<Uuid, String>,
+    ) -> bool {
+        let item_path = entry
+            .parent_uuid
+            .map(|parent_uuid| self.folder_path(parent_uuid, folder_paths))
+            .unwrap_or_default();
+
+        // Whole folder names are compared, so that `path:lev` doesn't match `levels`
+        match item_path.strip_prefix(path) {
+            Some(rest) => path.is_empty() || rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// Returns the uuids of up to `limit` items matching `query`, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Uuid> {
+        let query = SearchQuery::parse(query);
+        let mut folder_paths = HashMap::default();
+
+        let mut matches: Vec<(i32, &SearchEntry, Uuid)> =
+            self.entries
+                .iter()
+                .filter(|(_, entry)| {
+                    query.kinds.is_empty() || query.kinds.iter().any(|k| k == entry.kind)
+                })
+                .filter(|(_, entry)| query.tags.iter().all(|tag| entry.tags.contains(tag)))
+                .filter(|(_, entry)| {
+                    query.metadata.iter().all(|(key, value)| {
+                        match (entry.metadata.get(key), value) {
+                            (Some(entry_value), Some(value)) => entry_value == value,
+                            (Some(_), None) => true,
+                            (None, _) => false,
+                        }
+                    })
+                })
+                .filter(|(_, entry)| match &query.path {
+                    Some(path) => self.in_path(entry, path, &mut folder_paths),
+                    None => true,
+                })
+                .filter_map(|(uuid, entry)| {
+                    let score = query
+                        .terms
+                        .iter()
+                        .map(|term| fuzzy_score(term, entry))
+                        .sum::<Option<i32>>()?;
+                    Some((score, entry, *uuid))
+                })
+                .collect();
+
+        matches.sort_by(|(a_score, a, a_uuid), (b_score, b, b_uuid)| {
+            Reverse(a_score)
+                .cmp(&Reverse(b_score))
+                .then(a.chars.len().cmp(&b.chars.len()))
+                .then(a.name.cmp(&b.name))
+                .then(a_uuid.cmp(b_uuid))
+        });
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, uuid)| uuid)
+            .collect()
+    }
+}
+
+type ChangedItemFilter = (
+    With<ProjectItem>,
+    Or<(Changed<ProjectItem>, Changed<Parent>)>,
+);
+
+pub fn update_project_search(
+    mut search: ResMut<ProjectSearch>,
+    registry: Res<ProjectItemRegistry>,
+    changed_items: Query<Entity, ChangedItemFilter>,
+    mut removed_parents: RemovedComponents<Parent>,
+    items: Query<(&ProjectItem, Option<&Parent>)>,
+) {
+    if registry.is_changed() {
+        search
+            .entries
+            .retain(|uuid, _| registry.items.contains_key(uuid));
+    }
+
+    let updated_entities: Vec<Entity> =
+        changed_items.iter().chain(removed_parents.iter()).collect();
+
+    for entity in updated_entities {
+        let Ok((project_item, parent)) = items.get(entity) else {
+            continue;
+        };
+
+        let parent_uuid = parent
+            .and_then(|parent| items.get(parent.get()).ok())
+            .map(|(parent_item, _)| parent_item.uuid);
+
+        search.entries.insert(
+            project_item.uuid,
+            SearchEntry::new(project_item, parent_uuid),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::sync::Arc;
+
+    use super::super::kind::{FolderKind, ImageKind, MaterialKind};
+    use super::super::{ProjectItemData, ProjectItemKind};
+    use super::*;
+
+    fn add_item(
+        search: &mut ProjectSearch,
+        name: &str,
+        kind: Arc<dyn ProjectItemKind>,
+        parent_uuid: Option<Uuid>,
+        tags: &[&str],
+        metadata: &[(&str, &str)],
+    ) -> Uuid {
+        let project_item = ProjectItem {
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            kind,
+            data: ProjectItemData::Folder,
+            tags: tags
+                .iter()
+                .map(ToString::to_string)
+                .collect::<BTreeSet<_>>(),
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            has_unsaved_changes: false,
+            load_error: None,
+        };
+
+        search.entries.insert(
+            project_item.uuid,
+            SearchEntry::new(&project_item, parent_uuid),
+        );
+        project_item.uuid
+    }
+
+    fn add_named(search: &mut ProjectSearch, name: &str) -> Uuid {
+        add_item(search, name, Arc::new(MaterialKind), None, &[], &[])
+    }
+
+    #[test]
+    fn exact_and_prefix_matches_rank_first() {
+        let mut search = ProjectSearch::default();
+        let tall_grass = add_named(&mut search, "Tall Grass");
+        let grass_large = add_named(&mut search, "Grass Large");
+        let grass = add_named(&mut search, "Grass");
+        add_named(&mut search, "Gravel");
+        add_named(&mut search, "Ground Rock");
+
+        assert_eq!(
+            search.search("grass", 10),
+            vec![grass, grass_large, tall_grass]
+        );
+    }
+
+    #[test]
+    fn word_starts_rank_above_scattered_matches() {
+        let mut search = ProjectSearch::default();
+        let rouge = add_named(&mut search, "Rouge");
+        let rock_garden = add_named(&mut search, "RockGarden");
+        assert_eq!(search.search("rg", 10), vec![rock_garden, rouge]);
+
+        let mut search = ProjectSearch::default();
+        let tiger = add_named(&mut search, "Tiger");
+        let ground_rock = add_named(&mut search, "Ground Rock");
+        assert_eq!(search.search("gr", 10), vec![ground_rock, tiger]);
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let mut search = ProjectSearch::default();
+        let stone_wall = add_named(&mut search, "Stone Wall");
+        add_named(&mut search, "Stone Floor");
+        add_named(&mut search, "Brick Wall");
+
+        assert_eq!(search.search("wall stone", 10), vec![stone_wall]);
+        assert!(search.search("stone glass", 10).is_empty());
+    }
+
+    #[test]
+    fn results_are_limited() {
+        let mut search = ProjectSearch::default();
+        let rock = add_named(&mut search, "Rock");
+        add_named(&mut search, "Rock Large");
+        add_named(&mut search, "Rock Small");
+
+        assert_eq!(search.search("rock", 1), vec![rock]);
+    }
+
+    #[test]
+    fn kind_filter() {
+        let mut search = ProjectSearch::default();
+        let material = add_item(&mut search, "Rock", Arc::new(MaterialKind), None, &[], &[]);
+        let image = add_item(&mut search, "Rock", Arc::new(ImageKind), None, &[], &[]);
+
+        assert_eq!(search.search("kind:material", 10), vec![material]);
+        assert_eq!(search.search("rock kind:image", 10), vec![image]);
+        assert_eq!(search.search("kind:mesh", 10), Vec::<Uuid>::new());
+    }
+
+    #[test]
+    fn tag_filter() {
+        let mut search = ProjectSearch::default();
+        let hero = add_item(
+            &mut search,
+            "Knight",
+            Arc::new(MaterialKind),
+            None,
+            &["Hero", "metal"],
+            &[],
+        );
+        add_item(
+            &mut search,
+            "Peasant",
+            Arc::new(MaterialKind),
+            None,
+            &["npc"],
+            &[],
+        );
+
+        assert_eq!(search.search("tag:hero", 10), vec![hero]);
+        assert_eq!(search.search("tag:hero tag:metal", 10), vec![hero]);
+        assert!(search.search("tag:hero tag:npc", 10).is_empty());
+    }
+
+    #[test]
+    fn meta_filter() {
+        let mut search = ProjectSearch::default();
+        let alice = add_item(
+            &mut search,
+            "Door",
+            Arc::new(MaterialKind),
+            None,
+            &[],
+            &[("Owner", "Alice")],
+        );
+        let bob = add_item(
+            &mut search,
+            "Window",
+            Arc::new(MaterialKind),
+            None,
+            &[],
+            &[("owner", "bob")],
+        );
+        add_item(&mut search, "Wall", Arc::new(MaterialKind), None, &[], &[]);
+
+        let mut owned = search.search("meta:owner", 10);
+        owned.sort();
+        let mut expected = vec![alice, bob];
+        expected.sort();
+        assert_eq!(owned, expected);
+
+        assert_eq!(search.search("meta:owner=alice", 10), vec![alice]);
+        assert!(search.search("meta:owner=carol", 10).is_empty());
+    }
+
+    #[test]
+    fn path_filter_compares_whole_folders() {
+        let mut search = ProjectSearch::default();
+        let folder = |search: &mut ProjectSearch, name: &str, parent_uuid| {
+            add_item(search, name, Arc::new(FolderKind), parent_uuid, &[], &[])
+        };
+
+        let levels = folder(&mut search, "Levels", None);
+        let level_01 = folder(&mut search, "Level 01", Some(levels));
+        let levels_2 = folder(&mut search, "levels2", None);
+
+        let ground = add_item(
+            &mut search,
+            "Ground",
+            Arc::new(ImageKind),
+            Some(level_01),
+            &[],
+            &[],
+        );
+        let sky = add_item(
+            &mut search,
+            "Sky",
+            Arc::new(ImageKind),
+            Some(levels),
+            &[],
+            &[],
+        );
+        add_item(
+            &mut search,
+            "Water",
+            Arc::new(ImageKind),
+            Some(levels_2),
+            &[],
+            &[],
+        );
+
+        let mut in_levels = search.search("kind:image path:levels", 10);
+        in_levels.sort();
+        let mut expected = vec![ground, sky];
+        expected.sort();
+        assert_eq!(in_levels, expected);
+
+        assert_eq!(
+            search.search("kind:image path:\"Levels/Level 01/\"", 10),
+            vec![ground]
+        );
+        assert!(search.search("path:lev", 10).is_empty());
+    }
+}
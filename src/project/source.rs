@@ -0,0 +1,52 @@
+use bevy::asset::{Asset, AssetPath, LoadState};
+use bevy::prelude::*;
+
+use super::overrides::SourceAsset;
+use super::{asset_root_path, ProjectItem};
+
+/// Marks project items whose source asset is still being loaded by the `AssetServer`.
+#[derive(Component)]
+pub struct SourceLoading;
+
+fn describe_load_failure(asset_server: &AssetServer, source: &str) -> String {
+    let asset_path = AssetPath::from(source);
+    let path = asset_root_path(asset_server).join(asset_path.path());
+
+    if path.exists() {
+        format!(
+            "Failed to load {} (unsupported format or invalid data)",
+            source
+        )
+    } else {
+        format!("Failed to load {} (file not found)", source)
+    }
+}
+
+pub fn update_source_load_states<T: Asset>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut items: Query<(Entity, &mut ProjectItem, &SourceAsset<T>), With<SourceLoading>>,
+) {
+    for (entity, mut project_item, source_asset) in &mut items {
+        match asset_server.get_load_state(&source_asset.handle) {
+            LoadState::Loaded => {
+                commands.entity(entity).remove::<SourceLoading>();
+
+                if project_item.load_error.is_some() {
+                    project_item.load_error = None;
+                }
+            }
+            LoadState::Failed => {
+                commands.entity(entity).remove::<SourceLoading>();
+
+                let load_error = describe_load_failure(
+                    &asset_server,
+                    project_item.data.source().map_or("", String::as_str),
+                );
+                error!("{}: {}", project_item.name, load_error);
+                project_item.load_error = Some(load_error);
+            }
+            _ => {}
+        }
+    }
+}
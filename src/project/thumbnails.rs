@@ -0,0 +1,740 @@
+use bevy::asset::HandleId;
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use bevy::utils::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::{ProjectDependencies, ProjectItem, ProjectItemRegistry};
+
+const THUMBNAIL_SIZE: u32 = 64;
+
+/// Number of thumbnails rendered on the GPU at once, each on its own render layer.
+const THUMBNAIL_STAGES: usize = 8;
+const FIRST_THUMBNAIL_LAYER: u8 = RenderLayers::TOTAL_LAYERS as u8 - THUMBNAIL_STAGES as u8;
+
+/// Frames a stage is kept around, so that its camera renders at least once.
+const THUMBNAIL_STAGE_FRAMES: u32 = 3;
+
+// Stages are placed far away from the edited scene, so their lights don't affect it.
+const THUMBNAIL_STAGE_ORIGIN: Vec3 = Vec3::new(0.0, -10_000.0, 0.0);
+const THUMBNAIL_STAGE_SPACING: f32 = 100.0;
+
+/// Preview of a project item's asset, generated in the background for materials, images and
+/// meshes.
+#[derive(Component, Clone, Debug)]
+pub struct Thumbnail(pub Handle<Image>);
+
+/// What a project item's thumbnail is rendered from, as told by its
+/// [`ProjectItemKind`](super::ProjectItemKind).
+pub enum ThumbnailSource {
+    Image(Handle<Image>),
+    /// Rendered on a sphere.
+    Material(Handle<StandardMaterial>),
+    Mesh(Handle<Mesh>),
+}
+
+/// Keeps track of the project items whose thumbnail needs to be (re)generated.
+#[derive(Resource, Default)]
+pub struct Thumbnails {
+    pending: HashSet<Uuid>,
+    /// Whether materials and meshes are rendered on stages rather than on the CPU, which is
+    /// only known once the renderer is set up.
+    pub(super) render_on_gpu: bool,
+    stages: [bool; THUMBNAIL_STAGES],
+    sphere: Option<Handle<Mesh>>,
+    mesh_material: Option<Handle<StandardMaterial>>,
+}
+
+#[derive(Component)]
+pub struct ThumbnailStage {
+    index: usize,
+    frames_left: u32,
+}
+
+fn view_direction() -> Vec3 {
+    Vec3::new(1.0, 0.8, 1.0).normalize()
+}
+
+fn thumbnail_image(data: Vec<u8>, format: TextureFormat) -> Image {
+    Image::new(
+        Extent3d {
+            width: THUMBNAIL_SIZE,
+            height: THUMBNAIL_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+    )
+}
+
+fn render_target_image() -> Image {
+    let size = Extent3d {
+        width: THUMBNAIL_SIZE,
+        height: THUMBNAIL_SIZE,
+        depth_or_array_layers: 1,
+    };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("thumbnail"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+fn read_rgba8(image: &Image, x: u32, y: u32) -> Option<[u8; 4]> {
+    let size = image.texture_descriptor.size;
+    let index = ((y.min(size.height - 1) * size.width + x.min(size.width - 1)) * 4) as usize;
+    let pixel = image.data.get(index..index + 4)?;
+
+    match image.texture_descriptor.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            Some([pixel[2], pixel[1], pixel[0], pixel[3]])
+        }
+        _ => None,
+    }
+}
+
+/// Shrinks an image to fit the thumbnail, averaging the source pixels behind each one.
+fn downscale_image(image: &Image) -> Option<Image> {
+    let size = image.texture_descriptor.size;
+    if size.width == 0 || size.height == 0 {
+        return None;
+    }
+    read_rgba8(image, 0, 0)?;
+
+    let scale = (size.width.max(size.height) as f32 / THUMBNAIL_SIZE as f32).max(1.0);
+    let width = ((size.width as f32 / scale) as u32).clamp(1, THUMBNAIL_SIZE);
+    let height = ((size.height as f32 / scale) as u32).clamp(1, THUMBNAIL_SIZE);
+    let offset_x = (THUMBNAIL_SIZE - width) / 2;
+    let offset_y = (THUMBNAIL_SIZE - height) / 2;
+
+    let mut data = vec![0; (THUMBNAIL_SIZE * THUMBNAIL_SIZE * 4) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let x0 = (x as f32 * scale) as u32;
+            let y0 = (y as f32 * scale) as u32;
+            let x1 = (((x + 1) as f32 * scale) as u32).max(x0 + 1);
+            let y1 = (((y + 1) as f32 * scale) as u32).max(y0 + 1);
+
+            let mut sum = [0u32; 4];
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    let pixel = read_rgba8(image, sx, sy)?;
+                    for (sum, value) in sum.iter_mut().zip(pixel) {
+                        *sum += value as u32;
+                    }
+                }
+            }
+
+            let count = (x1 - x0) * (y1 - y0);
+            let index = (((y + offset_y) * THUMBNAIL_SIZE + x + offset_x) * 4) as usize;
+            for (value, sum) in data[index..index + 4].iter_mut().zip(sum) {
+                *value = (sum / count) as u8;
+            }
+        }
+    }
+
+    Some(thumbnail_image(data, TextureFormat::Rgba8UnormSrgb))
+}
+
+fn shade(normal: Vec3) -> f32 {
+    let light = Vec3::new(-0.4, 0.6, 0.7).normalize();
+    0.25 + 0.75 * normal.dot(light).max(0.0)
+}
+
+fn write_pixel(data: &mut [u8], x: u32, y: u32, color: Color) {
+    let index = ((y * THUMBNAIL_SIZE + x) * 4) as usize;
+    let color = color.as_rgba_u8();
+    data[index..index + 4].copy_from_slice(&color);
+}
+
+/// Software fallback rendering a material on a sphere, used without a GPU.
+fn render_material_on_cpu(material: &StandardMaterial, images: &Assets<Image>) -> Image {
+    let mut data = vec![0; (THUMBNAIL_SIZE * THUMBNAIL_SIZE * 4) as usize];
+    let texture = material
+        .base_color_texture
+        .as_ref()
+        .and_then(|texture| images.get(texture));
+    let base_color = material.base_color.as_linear_rgba_f32();
+    let emissive = material.emissive.as_linear_rgba_f32();
+    let radius = THUMBNAIL_SIZE as f32 * 0.45;
+    let center = THUMBNAIL_SIZE as f32 / 2.0;
+
+    for y in 0..THUMBNAIL_SIZE {
+        for x in 0..THUMBNAIL_SIZE {
+            let nx = (x as f32 + 0.5 - center) / radius;
+            let ny = (center - y as f32 - 0.5) / radius;
+            let d = nx * nx + ny * ny;
+            if d > 1.0 {
+                continue;
+            }
+
+            let normal = Vec3::new(nx, ny, (1.0 - d).sqrt());
+
+            let texel = texture
+                .and_then(|texture| {
+                    let size = texture.texture_descriptor.size;
+                    let u = 0.5 + normal.x.atan2(normal.z) / std::f32::consts::TAU;
+                    let v = 0.5 - normal.y.asin() / std::f32::consts::PI;
+                    read_rgba8(
+                        texture,
+                        (u * size.width as f32) as u32,
+                        (v * size.height as f32) as u32,
+                    )
+                })
+                .map(|[r, g, b, a]| Color::rgba_u8(r, g, b, a).as_linear_rgba_f32())
+                .unwrap_or([1.0; 4]);
+
+            let light = shade(normal);
+            let channel = |index: usize| base_color[index] * texel[index] * light + emissive[index];
+
+            write_pixel(
+                &mut data,
+                x,
+                y,
+                Color::rgba_linear(channel(0), channel(1), channel(2), base_color[3] * texel[3]),
+            );
+        }
+    }
+
+    thumbnail_image(data, TextureFormat::Rgba8UnormSrgb)
+}
+
+fn mesh_triangles(mesh: &Mesh) -> Option<Vec<[Vec3; 3]>> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+
+    indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            Some([
+                Vec3::from(*positions.get(triangle[0])?),
+                Vec3::from(*positions.get(triangle[1])?),
+                Vec3::from(*positions.get(triangle[2])?),
+            ])
+        })
+        .collect()
+}
+
+/// Software fallback rasterizing a mesh with flat shading, used without a GPU.
+fn render_mesh_on_cpu(mesh: &Mesh) -> Option<Image> {
+    let triangles = mesh_triangles(mesh)?;
+    let aabb = mesh.compute_aabb()?;
+    let center = Vec3::from(aabb.center);
+    let radius = Vec3::from(aabb.half_extents).length().max(f32::EPSILON);
+
+    let view = Transform::from_translation(view_direction())
+        .looking_at(Vec3::ZERO, Vec3::Y)
+        .compute_matrix()
+        .inverse();
+
+    let size = THUMBNAIL_SIZE as f32;
+    let to_screen = |position: Vec3| {
+        let view_position = view.transform_point3((position - center) / radius);
+        Vec3::new(
+            (view_position.x * 0.45 + 0.5) * size,
+            (0.5 - view_position.y * 0.45) * size,
+            view_position.z,
+        )
+    };
+
+    let mut data = vec![0; (THUMBNAIL_SIZE * THUMBNAIL_SIZE * 4) as usize];
+    let mut depth = vec![f32::NEG_INFINITY; (THUMBNAIL_SIZE * THUMBNAIL_SIZE) as usize];
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(to_screen);
+
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let normal = view
+            .transform_vector3((triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]))
+            .normalize_or_zero();
+        // Shade both sides, as meshes may not be closed
+        let normal = if normal.z < 0.0 { -normal } else { normal };
+        let light = shade(normal) * 0.8;
+        let color = Color::rgb_linear(light, light, light);
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(size - 1.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(size - 1.0) as u32;
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let edge = |from: Vec3, to: Vec3| {
+                    ((to.x - from.x) * (p.y - from.y) - (to.y - from.y) * (p.x - from.x)) / area
+                };
+                let (wa, wb, wc) = (edge(b, c), edge(c, a), edge(a, b));
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+
+                let z = wa * a.z + wb * b.z + wc * c.z;
+                let index = (y * THUMBNAIL_SIZE + x) as usize;
+                if z > depth[index] {
+                    depth[index] = z;
+                    write_pixel(&mut data, x, y, color);
+                }
+            }
+        }
+    }
+
+    Some(thumbnail_image(data, TextureFormat::Rgba8UnormSrgb))
+}
+
+fn spawn_stage(
+    commands: &mut Commands,
+    index: usize,
+    target: Handle<Image>,
+    model: PbrBundle,
+    distance: f32,
+) {
+    let layer = RenderLayers::layer(FIRST_THUMBNAIL_LAYER + index as u8);
+    let origin = THUMBNAIL_STAGE_ORIGIN + Vec3::Z * THUMBNAIL_STAGE_SPACING * index as f32;
+
+    commands
+        .spawn((
+            ThumbnailStage {
+                index,
+                frames_left: THUMBNAIL_STAGE_FRAMES,
+            },
+            SpatialBundle::from_transform(Transform::from_translation(origin)),
+        ))
+        .with_children(|stage| {
+            stage.spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        target: RenderTarget::Image(target),
+                        order: -1,
+                        ..default()
+                    },
+                    camera_3d: Camera3d {
+                        clear_color: ClearColorConfig::Custom(Color::NONE),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(view_direction() * distance)
+                        .looking_at(Vec3::ZERO, Vec3::Y),
+                    ..default()
+                },
+                layer,
+            ));
+
+            stage.spawn((
+                PointLightBundle {
+                    point_light: PointLight {
+                        intensity: 1600.0 * distance * distance,
+                        range: distance * 4.0,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        Vec3::new(-0.4, 0.6, 0.7) * distance * 2.0,
+                    ),
+                    ..default()
+                },
+                layer,
+            ));
+
+            stage.spawn((model, layer));
+        });
+}
+
+fn item_uuids_by_handle(items: &Query<&ProjectItem>) -> HashMap<HandleId, Uuid> {
+    items
+        .iter()
+        .filter_map(|project_item| Some((project_item.data.handle_id()?, project_item.uuid)))
+        .collect()
+}
+
+/// Queues a new thumbnail whenever the asset of a material, image or mesh item changes.
+pub fn queue_thumbnails(
+    mut thumbnails: ResMut<Thumbnails>,
+    mut material_events: EventReader<AssetEvent<StandardMaterial>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    dependencies: Res<ProjectDependencies>,
+    items: Query<&ProjectItem>,
+) {
+    let updated_handles: Vec<HandleId> = material_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .chain(image_events.iter().filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id()),
+            AssetEvent::Removed { .. } => None,
+        }))
+        .chain(mesh_events.iter().filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id()),
+            AssetEvent::Removed { .. } => None,
+        }))
+        .collect();
+
+    if updated_handles.is_empty() {
+        return;
+    }
+
+    let item_uuids = item_uuids_by_handle(&items);
+
+    for handle_id in updated_handles {
+        let Some(uuid) = item_uuids.get(&handle_id) else {
+            continue;
+        };
+
+        thumbnails.pending.insert(*uuid);
+
+        // Materials show the images they use
+        thumbnails.pending.extend(dependencies.usages(*uuid));
+    }
+}
+
+pub fn render_thumbnails(
+    mut commands: Commands,
+    mut thumbnails: ResMut<Thumbnails>,
+    registry: Res<ProjectItemRegistry>,
+    items: Query<(Entity, &ProjectItem)>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let pending: Vec<Uuid> = thumbnails.pending.iter().copied().collect();
+
+    for uuid in pending {
+        let Some((entity, project_item)) = registry
+            .items
+            .get(&uuid)
+            .and_then(|entity| items.get(*entity).ok())
+        else {
+            thumbnails.pending.remove(&uuid);
+            continue;
+        };
+
+        let Some(thumbnail_source) = project_item.kind.thumbnail(&project_item.data) else {
+            thumbnails.pending.remove(&uuid);
+            continue;
+        };
+
+        let stage = match thumbnail_source {
+            ThumbnailSource::Material(_) | ThumbnailSource::Mesh(_) if thumbnails.render_on_gpu => {
+                // Stays pending until a stage is free
+                let Some(stage) = thumbnails.stages.iter().position(|in_use| !in_use) else {
+                    continue;
+                };
+                Some(stage)
+            }
+            _ => None,
+        };
+
+        let thumbnail = match (&thumbnail_source, stage) {
+            (ThumbnailSource::Image(handle), _) => images
+                .get(handle)
+                .and_then(downscale_image)
+                .map(|image| images.add(image)),
+            (ThumbnailSource::Material(handle), Some(stage)) => {
+                let sphere = thumbnails
+                    .sphere
+                    .get_or_insert_with(|| {
+                        meshes.add(Mesh::from(shape::UVSphere {
+                            radius: 1.0,
+                            sectors: 32,
+                            stacks: 16,
+                        }))
+                    })
+                    .clone();
+                let target = images.add(render_target_image());
+                let model = PbrBundle {
+                    mesh: sphere,
+                    material: handle.clone(),
+                    ..default()
+                };
+
+                spawn_stage(&mut commands, stage, target.clone(), model, 3.2);
+                Some(target)
+            }
+            (ThumbnailSource::Mesh(handle), Some(stage)) => {
+                let Some(aabb) = meshes.get(handle).and_then(Mesh::compute_aabb) else {
+                    thumbnails.pending.remove(&uuid);
+                    continue;
+                };
+                let radius = Vec3::from(aabb.half_extents).length().max(f32::EPSILON);
+
+                let mesh_material = thumbnails
+                    .mesh_material
+                    .get_or_insert_with(|| materials.add(Color::rgb(0.8, 0.8, 0.8).into()))
+                    .clone();
+                let target = images.add(render_target_image());
+                let model = PbrBundle {
+                    mesh: handle.clone(),
+                    material: mesh_material,
+                    transform: Transform::from_translation(-Vec3::from(aabb.center)),
+                    ..default()
+                };
+
+                spawn_stage(&mut commands, stage, target.clone(), model, radius * 2.8);
+                Some(target)
+            }
+            (ThumbnailSource::Material(handle), None) => materials
+                .get(handle)
+                .map(|material| render_material_on_cpu(material, &images))
+                .map(|image| images.add(image)),
+            (ThumbnailSource::Mesh(handle), None) => meshes
+                .get(handle)
+                .and_then(render_mesh_on_cpu)
+                .map(|image| images.add(image)),
+        };
+
+        if let Some(stage) = stage {
+            thumbnails.stages[stage] = true;
+        }
+
+        thumbnails.pending.remove(&uuid);
+
+        if let Some(thumbnail) = thumbnail {
+            commands.entity(entity).insert(Thumbnail(thumbnail));
+        }
+    }
+}
+
+pub fn despawn_thumbnail_stages(
+    mut commands: Commands,
+    mut thumbnails: ResMut<Thumbnails>,
+    mut stages: Query<(Entity, &mut ThumbnailStage)>,
+) {
+    for (entity, mut stage) in &mut stages {
+        if stage.frames_left == 0 {
+            thumbnails.stages[stage.index] = false;
+            commands.entity(entity).despawn_recursive();
+        } else {
+            stage.frames_left -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy::reflect::ParsedPath;
+
+    use super::super::{ProjectEvent, ProjectPlugin};
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct ThumbnailRenders(usize);
+
+    fn count_thumbnail_renders(
+        mut renders: ResMut<ThumbnailRenders>,
+        thumbnails: Query<(), Changed<Thumbnail>>,
+    ) {
+        renders.0 += thumbnails.iter().count();
+    }
+
+    fn renders_after(app: &mut App, event: ProjectEvent) -> usize {
+        app.world.send_event(event);
+        for _ in 0..10 {
+            app.update();
+        }
+        app.world.resource::<ThumbnailRenders>().0
+    }
+
+    #[test]
+    fn one_edit_renders_one_thumbnail() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(ImagePlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_plugin(ProjectPlugin::default())
+            .insert_resource(ThumbnailRenders::default())
+            .add_systems(Last, count_thumbnail_renders);
+
+        let uuid = Uuid::new_v4();
+        let create = ProjectEvent::CreateMaterial {
+            uuid,
+            name: "Rock".into(),
+            parent_uuid: None,
+        };
+        assert_eq!(renders_after(&mut app, create), 1);
+
+        let set_override = ProjectEvent::SetOverride {
+            uuid,
+            path: ParsedPath::parse("perceptual_roughness").unwrap(),
+            value: Arc::new(0.2_f32),
+        };
+        assert_eq!(renders_after(&mut app, set_override), 2);
+
+        // Changes that don't affect the asset don't render it again
+        let rename = ProjectEvent::Rename {
+            uuid,
+            name: "Boulder".into(),
+        };
+        assert_eq!(renders_after(&mut app, rename), 2);
+    }
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+        read_rgba8(image, x, y).unwrap()
+    }
+
+    fn is_thumbnail_sized(image: &Image) -> bool {
+        let size = image.texture_descriptor.size;
+        size.width == THUMBNAIL_SIZE && size.height == THUMBNAIL_SIZE
+    }
+
+    #[test]
+    fn material_is_rendered_on_a_sphere() {
+        let material = StandardMaterial {
+            base_color: Color::RED,
+            ..default()
+        };
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Image>();
+
+        let image = render_material_on_cpu(&material, app.world.resource::<Assets<Image>>());
+        assert!(is_thumbnail_sized(&image));
+
+        let [r, g, b, a] = pixel(&image, THUMBNAIL_SIZE / 2, THUMBNAIL_SIZE / 2);
+        assert!(r > 0);
+        assert_eq!([g, b, a], [0, 0, 255]);
+
+        // Around the sphere is left transparent
+        assert_eq!(pixel(&image, 0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn mesh_is_rendered_within_the_thumbnail() {
+        let image = render_mesh_on_cpu(&Mesh::from(shape::Cube { size: 10.0 })).unwrap();
+        assert!(is_thumbnail_sized(&image));
+
+        let [r, g, b, a] = pixel(&image, THUMBNAIL_SIZE / 2, THUMBNAIL_SIZE / 2);
+        assert!(r > 0);
+        assert_eq!([r, r, 255], [g, b, a]);
+
+        assert_eq!(pixel(&image, 0, 0), [0; 4]);
+        assert_eq!(
+            pixel(&image, THUMBNAIL_SIZE - 1, THUMBNAIL_SIZE - 1),
+            [0; 4]
+        );
+
+        assert!(render_mesh_on_cpu(&Mesh::new(PrimitiveTopology::LineList)).is_none());
+    }
+
+    #[test]
+    fn downscaled_image_keeps_its_aspect_ratio() {
+        // White on the left half, opaque black on the right one
+        let (width, height) = (THUMBNAIL_SIZE * 2, THUMBNAIL_SIZE / 2);
+        let data = (0..height)
+            .flat_map(|_| 0..width)
+            .flat_map(|x| match x < width / 2 {
+                true => [255; 4],
+                false => [0, 0, 0, 255],
+            })
+            .collect();
+        let image = Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let thumbnail = downscale_image(&image).unwrap();
+        assert!(is_thumbnail_sized(&thumbnail));
+
+        // Scaled down to 64x16, and centered vertically
+        let middle = THUMBNAIL_SIZE / 2;
+        assert_eq!(pixel(&thumbnail, 0, middle), [255; 4]);
+        assert_eq!(pixel(&thumbnail, middle - 1, middle), [255; 4]);
+        assert_eq!(pixel(&thumbnail, middle, middle), [0, 0, 0, 255]);
+        assert_eq!(pixel(&thumbnail, 0, 23), [0; 4]);
+        assert_eq!(pixel(&thumbnail, 0, 24), [255; 4]);
+        assert_eq!(pixel(&thumbnail, 0, 39), [255; 4]);
+        assert_eq!(pixel(&thumbnail, 0, 40), [0; 4]);
+
+        let unsupported = Image::new_fill(
+            Extent3d::default(),
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::R32Float,
+        );
+        assert!(downscale_image(&unsupported).is_none());
+    }
+
+    #[test]
+    fn items_wait_for_a_free_stage() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(ImagePlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_plugin(ProjectPlugin::default());
+        app.world.resource_mut::<Thumbnails>().render_on_gpu = true;
+
+        let count = THUMBNAIL_STAGES * 2 + 1;
+        for index in 0..count {
+            app.world.send_event(ProjectEvent::CreateMaterial {
+                uuid: Uuid::new_v4(),
+                name: format!("Rock {}", index),
+                parent_uuid: None,
+            });
+        }
+
+        let mut stages = app.world.query::<&ThumbnailStage>();
+        let mut most_stages = 0;
+        for _ in 0..count * THUMBNAIL_STAGE_FRAMES as usize {
+            app.update();
+            most_stages = most_stages.max(stages.iter(&app.world).count());
+        }
+        assert_eq!(most_stages, THUMBNAIL_STAGES);
+
+        let mut thumbnails = app.world.query::<(&ProjectItem, &Thumbnail)>();
+        assert_eq!(thumbnails.iter(&app.world).count(), count);
+        assert!(app.world.resource::<Thumbnails>().pending.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use uuid::Uuid;
+
+use super::dependencies::unresolved_scene_handles;
+use super::prefab::scene_prefab_uuids;
+use super::{
+    read_scene_source, ProjectDependencies, ProjectItem, ProjectItemData, ProjectItemRegistry,
+};
+
+/// A problem found in a loaded project by [`validate_project`].
+pub struct ProjectIssue {
+    pub uuid: Uuid,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for ProjectIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.name, self.uuid, self.message)
+    }
+}
+
+fn scene_issues(world: &World, project_item: &ProjectItem) -> Vec<String> {
+    let ProjectItemData::Scene {
+        source,
+        dynamic_scene,
+    } = &project_item.data
+    else {
+        return Vec::new();
+    };
+
+    let Ok(dynamic_scene) = dynamic_scene.lock() else {
+        return vec!["scene is poisoned by an earlier panic".to_string()];
+    };
+
+    let read_dynamic_scene;
+    let dynamic_scene = match (dynamic_scene.as_ref(), source) {
+        (Some(dynamic_scene), _) => dynamic_scene,
+        (None, Some(source)) => match read_scene_source(world, source) {
+            Ok(dynamic_scene) => {
+                read_dynamic_scene = dynamic_scene;
+                &read_dynamic_scene
+            }
+            Err(err) => return vec![err.to_string()],
+        },
+        (None, None) => return Vec::new(),
+    };
+
+    let registry = world.resource::<ProjectItemRegistry>();
+    let mut missing_prefabs: Vec<Uuid> = scene_prefab_uuids(dynamic_scene)
+        .into_iter()
+        .filter(|prefab_uuid| !registry.items.contains_key(prefab_uuid))
+        .collect();
+    missing_prefabs.sort();
+
+    unresolved_scene_handles(world, dynamic_scene)
+        .into_iter()
+        .map(|handle_id| format!("references a missing asset {:?}", handle_id))
+        .chain(missing_prefabs.into_iter().map(|prefab_uuid| {
+            format!(
+                "has instances of a missing prefab {}, which is neither in the project nor \
+                     in a mounted library",
+                prefab_uuid
+            )
+        }))
+        .collect()
+}
+
+/// Checks the loaded project for items whose source failed to load, scenes that can't be read
+/// and references to items or assets that don't exist.
+///
+/// Sources should be done loading before validating, or their failures won't be reported.
+pub fn validate_project(world: &World) -> Vec<ProjectIssue> {
+    let registry = world.resource::<ProjectItemRegistry>();
+    let dependencies = world.resource::<ProjectDependencies>();
+
+    let mut items: Vec<&ProjectItem> = registry
+        .items
+        .values()
+        .filter_map(|entity| world.get::<ProjectItem>(*entity))
+        .collect();
+    items.sort_by(|a, b| a.name.cmp(&b.name).then(a.uuid.cmp(&b.uuid)));
+
+    let mut issues = Vec::new();
+
+    for project_item in items {
+        let mut messages = Vec::new();
+
+        if let Some(load_error) = &project_item.load_error {
+            messages.push(load_error.clone());
+        }
+
+        messages.extend(
+            dependencies
+                .references(project_item.uuid)
+                .filter(|reference| !registry.items.contains_key(reference))
+                .map(|reference| {
+                    format!(
+                        "references a missing project item {}, which is neither in the project \
+                         nor in a mounted library",
+                        reference
+                    )
+                }),
+        );
+
+        messages.extend(scene_issues(world, project_item));
+
+        issues.extend(messages.into_iter().map(|message| ProjectIssue {
+            uuid: project_item.uuid,
+            name: project_item.name.clone(),
+            message,
+        }));
+    }
+
+    issues
+}
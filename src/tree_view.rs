@@ -9,8 +9,15 @@ pub trait TreeViewItem {
     fn icon(&self) -> Icon;
     fn is_selected(&self) -> bool;
     fn is_hovered(&self) -> bool;
+    fn has_error(&self) -> bool {
+        false
+    }
 }
 
+/// Shows an image, such as a thumbnail, in place of the icon of the item it's added to.
+#[derive(Component, Clone, Debug)]
+pub struct TreeViewItemImage(pub Handle<Image>);
+
 #[derive(Component, Clone, Debug, Default)]
 pub struct TreeView {
     pub icon_size: IconSize,
@@ -81,7 +88,10 @@ fn update_tree_views<T: TreeViewItem + Component>(
     mut tree_views: Query<(Entity, &mut TreeView, &mut TreeViewState<T>)>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     items: Query<&T>,
-    changed_items: Query<(Entity, &T, Option<&Children>), Changed<T>>,
+    changed_items: Query<
+        (Entity, &T, Option<&TreeViewItemImage>, Option<&Children>),
+        Or<(Changed<T>, Changed<TreeViewItemImage>)>,
+    >,
     reparented_items: Query<(Entity, &Parent), (With<T>, Changed<Parent>)>,
     rechilded_items: Query<(Entity, &Children), (With<T>, Changed<Children>)>,
     mut orphaned_items: RemovedComponents<Parent>,
@@ -137,7 +147,7 @@ fn update_tree_views<T: TreeViewItem + Component>(
             content_node
         };
 
-        for (item_entity, ref item, item_children) in &changed_items {
+        for (item_entity, ref item, item_image, item_children) in &changed_items {
             let tree_node = if let Some(entity) = tree_view_state.node_by_item.get(&item_entity) {
                 *entity
             } else {
@@ -264,11 +274,14 @@ fn update_tree_views<T: TreeViewItem + Component>(
                     TreeViewIcon,
                     ImageBundle {
                         image: UiImage {
-                            texture: item.icon().request_icon(
-                                &asset_server,
-                                ui_scale.scale * logical_to_physical_factor,
-                                tree_view.icon_size,
-                            ),
+                            texture: match item_image {
+                                Some(item_image) => item_image.0.clone(),
+                                None => item.icon().request_icon(
+                                    &asset_server,
+                                    ui_scale.scale * logical_to_physical_factor,
+                                    tree_view.icon_size,
+                                ),
+                            },
                             ..default()
                         },
                         style: Style {
@@ -299,7 +312,14 @@ fn update_tree_views<T: TreeViewItem + Component>(
                         text: Text {
                             sections: vec![TextSection {
                                 value: item.title(),
-                                style: text_style.clone(),
+                                style: if item.has_error() {
+                                    TextStyle {
+                                        color: Color::rgb(1.0, 0.35, 0.3),
+                                        ..text_style.clone()
+                                    }
+                                } else {
+                                    text_style.clone()
+                                },
                             }],
                             ..default()
                         },
@@ -427,7 +447,7 @@ fn update_tree_views<T: TreeViewItem + Component>(
             // Deregister and Despawn Node Entity
             let node_entity = tree_view_state.node_by_item.remove(&item_entity).unwrap();
             tree_view_state.item_by_node.remove(&node_entity);
-            commands.entity(node_entity).despawn();
+            commands.entity(node_entity).despawn_recursive();
         }
     }
 }